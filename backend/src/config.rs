@@ -0,0 +1,86 @@
+// config.rs
+use std::env;
+use std::sync::OnceLock;
+
+// 環境変数から読み込むサーバー設定
+#[derive(Debug, Clone)]
+pub struct Config {
+    // リクエストボディの最大サイズ（バイト）
+    pub max_body_bytes: usize,
+    // PDFから一度にレンダリングできる最大ページ数
+    pub max_pdf_pages: usize,
+    // JSON問い合わせで受け付ける文書の最大サイズ（バイト、max_body_bytes とは別に設定）
+    pub max_query_bytes: usize,
+    // データURIとして埋め込む画像の目安サイズ（バイト、超えると警告を返す）
+    pub inline_budget_bytes: usize,
+    // ジョブのファイルを同時に処理する数（全ジョブ合計）
+    pub job_concurrency: usize,
+    // 保持できるジョブの最大数（完了済みを含む）
+    pub max_jobs: usize,
+    // 完了したジョブの結果を保持する時間（秒）
+    pub job_ttl_secs: u64,
+    // 画像の変換・圧縮をブロッキングスレッドで同時に実行する数（全リクエスト合計）
+    pub blocking_concurrency: usize,
+    // 空きを待てる変換・圧縮の数（超えると 503 を返す）
+    pub blocking_queue_size: usize,
+    // 1リクエストのファイルを同時に変換・圧縮する数
+    pub batch_concurrency: usize,
+    // 変換・圧縮で一時ファイルに書き出すファイルサイズの下限（バイト、これ以下はメモリ上で処理する）
+    pub spill_threshold_bytes: usize,
+    // 画像1ファイルの最大サイズ（バイト、超えると 413 を返す）
+    pub max_file_bytes: usize,
+    // 1リクエストで受け付ける画像の最大数（超えると 413 を返す）
+    pub max_files: usize,
+    // ここから3つは画像のデコードの上限（image・mozjpeg・ImageMagick・resvg で共通、超えると 422 を返す）
+    // 画像の最大画素数（幅×高さ）
+    pub max_pixels: u64,
+    // 画像の最大の幅・高さ（ピクセル）
+    pub max_image_dimension: u32,
+    // 1枚の画像の展開に使えるメモリ（バイト、RGBA 8bit で見積もる）
+    pub max_decode_memory_bytes: u64,
+}
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+impl Config {
+    pub fn from_env() -> Self {
+        Config {
+            max_body_bytes: env_or("MAX_BODY_BYTES", 20 * 1024 * 1024),
+            max_pdf_pages: env_or("MAX_PDF_PAGES", 50),
+            max_query_bytes: env_or("MAX_QUERY_BYTES", 20 * 1024 * 1024),
+            inline_budget_bytes: env_or("INLINE_BUDGET_BYTES", 8 * 1024),
+            job_concurrency: env_or("JOB_CONCURRENCY", std::thread::available_parallelism().map(|n| n.get()).unwrap_or(2)),
+            max_jobs: env_or("MAX_JOBS", 100),
+            job_ttl_secs: env_or("JOB_TTL_SECS", 60 * 60),
+            blocking_concurrency: env_or("BLOCKING_CONCURRENCY", std::thread::available_parallelism().map(|n| n.get()).unwrap_or(2)),
+            blocking_queue_size: env_or("BLOCKING_QUEUE_SIZE", 64),
+            batch_concurrency: env_or("BATCH_CONCURRENCY", 4),
+            spill_threshold_bytes: env_or("SPILL_THRESHOLD_BYTES", 8 * 1024 * 1024),
+            max_file_bytes: env_or("MAX_FILE_BYTES", 10 * 1024 * 1024),
+            max_files: env_or("MAX_FILES", 20),
+            max_pixels: env_or("MAX_PIXELS", 50_000_000),
+            max_image_dimension: env_or("MAX_IMAGE_DIMENSION", 16384),
+            max_decode_memory_bytes: env_or("MAX_DECODE_MEMORY_BYTES", 256 * 1024 * 1024),
+        }
+    }
+}
+
+// 設定を読み込んで保持する（main で一度だけ呼び出す）
+pub fn init() -> &'static Config {
+    CONFIG.get_or_init(Config::from_env)
+}
+
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(Config::from_env)
+}
+
+// 環境変数を解析し、未設定・不正な値の場合はデフォルト値を使う
+fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
+    match env::var(name) {
+        Ok(value) => value.trim().parse().unwrap_or_else(|_| {
+            tracing::warn!("環境変数{}の値が不正です: '{}'", name, value);
+            default
+        }),
+        Err(_) => default,
+    }
+}
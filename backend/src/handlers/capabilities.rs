@@ -0,0 +1,104 @@
+// handlers/capabilities.rs
+use axum::{http::StatusCode, response::IntoResponse, Json};
+use serde::Serialize;
+
+use crate::config;
+use crate::services::formats::{self, Backend, Delegate, InputFormat, OutputFormat, QualityRange};
+
+// 画像圧縮の品質指定（handlers::images::compress_image と同じ範囲）
+const COMPRESSION_QUALITY: QualityRange = QualityRange { min: 1, max: 100, default: 60 };
+
+#[derive(Serialize)]
+pub struct InputCapability {
+    #[serde(flatten)]
+    format: &'static InputFormat,
+    available: bool,
+}
+
+#[derive(Serialize)]
+pub struct OutputCapability {
+    #[serde(flatten)]
+    format: &'static OutputFormat,
+    available: bool,
+}
+
+#[derive(Serialize)]
+pub struct CompressionCapability {
+    quality: QualityRange,
+    formats: Vec<&'static str>,
+}
+
+#[derive(Serialize)]
+pub struct ImageMagickCapability {
+    delegates: &'static [Delegate],
+}
+
+// クライアントに公開する制限値（サーバー内部の設定は返さない）
+#[derive(Serialize)]
+pub struct LimitsInfo {
+    max_body_bytes: usize,
+    max_file_bytes: usize,
+    max_files: usize,
+    max_pixels: u64,
+    max_image_dimension: u32,
+}
+
+impl LimitsInfo {
+    fn from_config() -> Self {
+        let config = config::get();
+        LimitsInfo {
+            max_body_bytes: config.max_body_bytes,
+            max_file_bytes: config.max_file_bytes,
+            max_files: config.max_files,
+            max_pixels: config.max_pixels,
+            max_image_dimension: config.max_image_dimension,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct CapabilitiesResponse {
+    input_formats: Vec<InputCapability>,
+    output_formats: Vec<OutputCapability>,
+    compression: CompressionCapability,
+    limits: LimitsInfo,
+    imagemagick: ImageMagickCapability,
+}
+
+// 対応フォーマット・オプション・制限値を返すエンドポイント関数
+pub async fn get_capabilities() -> impl IntoResponse {
+    tracing::info!("開始: 対応機能の問い合わせ");
+
+    // ImageMagickが担当する形式はデリゲートの有無で利用可否を判定
+    let input_formats = formats::INPUT_FORMATS
+        .iter()
+        .map(|format| InputCapability {
+            format,
+            available: format.decoder != Backend::ImageMagick
+                || formats::output_format(format.id).is_some_and(|f| formats::magick_supports(f.magick_format, false)),
+        })
+        .collect();
+
+    let output_formats = formats::OUTPUT_FORMATS
+        .iter()
+        .map(|format| OutputCapability {
+            format,
+            available: format.encoder == Backend::Image || formats::magick_supports(format.magick_format, true),
+        })
+        .collect();
+
+    let response = CapabilitiesResponse {
+        input_formats,
+        output_formats,
+        compression: CompressionCapability {
+            quality: COMPRESSION_QUALITY,
+            formats: vec!["jpeg", "png", "webp"],
+        },
+        limits: LimitsInfo::from_config(),
+        imagemagick: ImageMagickCapability {
+            delegates: formats::magick_delegates(),
+        },
+    };
+
+    (StatusCode::OK, Json(response))
+}
@@ -0,0 +1,204 @@
+// handlers/encoding.rs
+use axum::{extract::Multipart, http::StatusCode, response::IntoResponse, Json};
+use base64::Engine;
+use serde::Serialize;
+use serde_json::json;
+use std::collections::HashMap;
+
+use crate::handlers::error::ApiError;
+use crate::handlers::images::read_upload_form;
+use crate::services::encoding::{Charset, Codec, EncodingError};
+
+impl From<EncodingError> for ApiError {
+    fn from(error: EncodingError) -> Self {
+        match &error {
+            EncodingError::Invalid(_) => ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, "invalid_input", error.to_string()),
+            EncodingError::Unmappable { charset, character, line, column } => {
+                ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, "unmappable_character", error.to_string()).with_details(json!({
+                    "charset": charset.name(),
+                    "character": character.to_string(),
+                    "line": line,
+                    "column": column,
+                }))
+            },
+            EncodingError::Malformed { charset, offset } => {
+                ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, "invalid_text", error.to_string())
+                    .with_details(json!({ "charset": charset.name(), "offset": offset }))
+            },
+            EncodingError::Unsupported(_) => ApiError::bad_request("invalid_option", error.to_string()),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct EncodedItem {
+    name: String,
+    // エンコード結果の文字列（encoding を指定した場合）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output: Option<String>,
+    // 文字コードを変換したファイル（encoding を省略した場合）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    charset: Option<&'static str>,
+    // エンコード前のバイト数
+    size: usize,
+}
+
+#[derive(Serialize)]
+pub struct DecodedItem {
+    name: String,
+    // テキストとして読めた場合の内容
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    // テキストとして読めないバイナリの場合のデータURL
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    charset: Option<&'static str>,
+    size: usize,
+}
+
+#[derive(Serialize)]
+pub struct EncodingResponse<T> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    encoding: Option<&'static str>,
+    results: Vec<T>,
+}
+
+fn field<'a>(fields: &'a HashMap<String, String>, key: &str) -> Option<&'a str> {
+    fields.get(key).map(|v| v.trim()).filter(|v| !v.is_empty())
+}
+
+fn flag(fields: &HashMap<String, String>, key: &str, default: bool) -> bool {
+    match field(fields, key) {
+        Some(value) => matches!(value.to_lowercase().as_str(), "true" | "1" | "on" | "yes"),
+        None => default,
+    }
+}
+
+fn data_url(mime_type: &str, data: &[u8]) -> String {
+    format!("data:{};base64,{}", mime_type, base64::engine::general_purpose::STANDARD.encode(data))
+}
+
+// 文字コードを指定（"auto" は推定）して読む
+fn decode_text(data: &[u8], charset: Option<Charset>) -> Result<(Charset, String), EncodingError> {
+    let charset = match charset.or_else(|| Charset::detect(data)) {
+        Some(charset) => charset,
+        None => return Err(EncodingError::Invalid("Could not detect the character set".to_string())),
+    };
+    Ok((charset, charset.decode(data)?))
+}
+
+fn parse_charset(value: Option<&str>) -> Result<Option<Charset>, EncodingError> {
+    match value {
+        Some(value) if !value.eq_ignore_ascii_case("auto") => value.parse().map(Some),
+        _ => Ok(None),
+    }
+}
+
+// Base64・パーセント・16進数・quoted-printable へのエンコードと文字コード変換のエンドポイント関数
+// （text は charset のバイト列にしてからエンコードし、ファイルは charset を指定した場合だけ source_charset から変換する）
+pub async fn encode(multipart: Multipart) -> Result<impl IntoResponse, ApiError> {
+    tracing::info!("開始: エンコードリクエスト受信");
+    let form = read_upload_form(multipart).await?;
+    let fields = &form.fields;
+
+    let codec = field(fields, "encoding").map(str::parse::<Codec>).transpose()?;
+    let charset = field(fields, "charset").map(str::parse::<Charset>).transpose()?;
+    let source_charset = parse_charset(field(fields, "source_charset"))?;
+    let padding = flag(fields, "padding", true);
+    let bom = flag(fields, "bom", false);
+    if codec.is_none() && charset.is_none() {
+        return Err(ApiError::bad_request("invalid_option", "Either encoding or charset is required"));
+    }
+    tracing::info!("エンコード設定: {:?}, 文字コード: {:?}, パディング: {}", codec, charset, padding);
+
+    let mut inputs = Vec::new();
+    if let Some(text) = fields.get("text").filter(|t| !t.is_empty()) {
+        let charset = charset.unwrap_or(Charset::Utf8);
+        let data = charset.encode(text, bom).map_err(|e| ApiError::from(e).with_file("text"))?;
+        inputs.push(("text".to_string(), Some(charset), data));
+    }
+    for (file_name, data) in form.files {
+        let (converted_charset, data) = match charset {
+            Some(charset) => {
+                let (source, text) = decode_text(&data, source_charset).map_err(|e| ApiError::from(e).with_file(&file_name))?;
+                tracing::debug!("文字コード変換: '{}' {} → {}", file_name, source.name(), charset.name());
+                (Some(charset), charset.encode(&text, bom).map_err(|e| ApiError::from(e).with_file(&file_name))?)
+            },
+            None => (None, data),
+        };
+        inputs.push((file_name, converted_charset, data));
+    }
+    if inputs.is_empty() {
+        return Err(ApiError::bad_request("no_files", "No files or text were uploaded"));
+    }
+
+    let results = inputs
+        .into_iter()
+        .map(|(name, charset, data)| {
+            let (output, url) = match codec {
+                Some(codec) => (Some(codec.encode(&data, padding)), None),
+                None => {
+                    let mime_type = format!("text/plain;charset={}", charset.unwrap_or(Charset::Utf8).name());
+                    (None, Some(data_url(&mime_type, &data)))
+                },
+            };
+            EncodedItem { name, output, url, charset: charset.map(Charset::name), size: data.len() }
+        })
+        .collect::<Vec<_>>();
+
+    tracing::info!("完了: {}件をエンコード", results.len());
+    Ok((StatusCode::OK, Json(EncodingResponse { encoding: codec.map(Codec::name), results })))
+}
+
+// デコードと文字コード判定のエンドポイント関数（charset を省略するか "auto" の場合は推定し、テキストとして読めなければデータURLで返す）
+pub async fn decode(multipart: Multipart) -> Result<impl IntoResponse, ApiError> {
+    tracing::info!("開始: デコードリクエスト受信");
+    let form = read_upload_form(multipart).await?;
+    let fields = &form.fields;
+
+    let codec = field(fields, "encoding").map(str::parse::<Codec>).transpose()?;
+    let charset = parse_charset(field(fields, "charset"))?;
+    tracing::info!("デコード設定: {:?}, 文字コード: {:?}", codec, charset);
+
+    let mut inputs = Vec::new();
+    if let Some(text) = fields.get("text").filter(|t| !t.is_empty()) {
+        if codec.is_none() {
+            return Err(ApiError::bad_request("invalid_option", "encoding is required to decode text"));
+        }
+        inputs.push(("text".to_string(), text.as_bytes().to_vec()));
+    }
+    inputs.extend(form.files);
+    if inputs.is_empty() {
+        return Err(ApiError::bad_request("no_files", "No files or text were uploaded"));
+    }
+
+    let mut results = Vec::new();
+    for (name, data) in inputs {
+        let data = match codec {
+            Some(codec) => {
+                let input = std::str::from_utf8(&data)
+                    .map_err(|_| ApiError::from(EncodingError::Invalid(format!("Encoded {} input must be text", codec.name()))).with_file(&name))?;
+                codec.decode(input).map_err(|e| ApiError::from(e).with_file(&name))?
+            },
+            None => data,
+        };
+
+        let item = match charset.or_else(|| Charset::detect(&data)) {
+            Some(charset) => {
+                let text = charset.decode(&data).map_err(|e| ApiError::from(e).with_file(&name))?;
+                DecodedItem { name, text: Some(text), url: None, charset: Some(charset.name()), size: data.len() }
+            },
+            None => {
+                tracing::debug!("テキストとして読めないためバイナリとして返す: '{}'", name);
+                DecodedItem { name, text: None, url: Some(data_url("application/octet-stream", &data)), charset: None, size: data.len() }
+            },
+        };
+        results.push(item);
+    }
+
+    tracing::info!("完了: {}件をデコード", results.len());
+    Ok((StatusCode::OK, Json(EncodingResponse { encoding: codec.map(Codec::name), results })))
+}
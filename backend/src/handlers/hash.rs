@@ -0,0 +1,126 @@
+// handlers/hash.rs
+use axum::{extract::Multipart, http::StatusCode, response::IntoResponse, Json};
+use serde::Serialize;
+
+use crate::handlers::error::ApiError;
+use crate::handlers::images::{stream_form, FormPart};
+use crate::services::hash::{Algorithm, Digests, Expected, Hasher, KeyEncoding, Verification};
+
+#[derive(Serialize)]
+pub struct HashResult {
+    // ファイル名（テキスト入力の場合は "text"）
+    name: String,
+    #[serde(flatten)]
+    digests: Digests,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    verification: Option<Verification>,
+}
+
+#[derive(Serialize)]
+pub struct HashResponse {
+    results: Vec<HashResult>,
+}
+
+// フォームの設定と計算中の状態
+#[derive(Default)]
+struct HashForm {
+    hmac_algorithm: Option<Algorithm>,
+    hmac_key: Option<String>,
+    key_encoding: KeyEncoding,
+    // 最初の入力を読み始めた時点で確定した HMAC の設定
+    hmac: Option<Option<(Algorithm, Vec<u8>)>>,
+    expected: Option<Expected>,
+    current: Option<(String, Hasher)>,
+    results: Vec<(String, Digests)>,
+}
+
+impl HashForm {
+    fn set_option(&mut self, name: &str, value: &str) -> Result<(), ApiError> {
+        let invalid = |e: anyhow::Error| ApiError::bad_request("invalid_option", e.to_string());
+        let hmac_option = matches!(name, "hmac_algorithm" | "hmac_key" | "hmac_key_encoding");
+        if hmac_option && self.hmac.is_some() {
+            return Err(ApiError::bad_request("invalid_option", "HMAC options must be sent before files and text"));
+        }
+        match name {
+            "hmac_algorithm" => self.hmac_algorithm = Some(value.parse().map_err(invalid)?),
+            "hmac_key" => self.hmac_key = Some(value.to_string()),
+            "hmac_key_encoding" => self.key_encoding = value.parse().map_err(invalid)?,
+            "expected" if !value.trim().is_empty() => self.expected = Some(value.parse().map_err(invalid)?),
+            _ => {},
+        }
+        Ok(())
+    }
+
+    // 入力を1つ読み始める（HMAC の鍵は最初の入力の前に確定させる）
+    fn start(&mut self, name: &str) -> Result<(), ApiError> {
+        if self.hmac.is_none() {
+            let hmac = match &self.hmac_key {
+                Some(key) => {
+                    let key = self
+                        .key_encoding
+                        .decode(key)
+                        .map_err(|e| ApiError::bad_request("invalid_option", e.to_string()))?;
+                    Some((self.hmac_algorithm.unwrap_or(Algorithm::Sha256), key))
+                },
+                None => None,
+            };
+            self.hmac = Some(hmac);
+        }
+        let hmac = self.hmac.as_ref().and_then(|hmac| hmac.as_ref()).map(|(algorithm, key)| (*algorithm, key.as_slice()));
+        let hasher = Hasher::new(hmac).map_err(|e| ApiError::bad_request("invalid_option", e.to_string()))?;
+        self.current = Some((name.to_string(), hasher));
+        Ok(())
+    }
+
+    fn update(&mut self, chunk: &[u8]) {
+        if let Some((_, hasher)) = &mut self.current {
+            hasher.update(chunk);
+        }
+    }
+
+    fn finish(&mut self) {
+        if let Some((name, hasher)) = self.current.take() {
+            self.results.push((name, hasher.finalize()));
+        }
+    }
+}
+
+// ファイル・テキストのハッシュ値計算のエンドポイント関数（ファイルは読み込みながら計算し、HMAC の鍵はログに出さない）
+pub async fn hash_files(multipart: Multipart) -> Result<impl IntoResponse, ApiError> {
+    tracing::info!("開始: ハッシュ値計算リクエスト受信");
+    let mut form = HashForm::default();
+
+    stream_form(multipart, |part| {
+        match part {
+            FormPart::Text { name: "text", value } => {
+                form.start("text")?;
+                form.update(value.as_bytes());
+                form.finish();
+            },
+            FormPart::Text { name, value } => form.set_option(name, value)?,
+            FormPart::FileStart { file_name } => form.start(file_name)?,
+            FormPart::FileChunk(chunk) => form.update(chunk),
+            FormPart::FileEnd => form.finish(),
+        }
+        Ok::<_, ApiError>(())
+    })
+    .await?;
+
+    if form.results.is_empty() {
+        tracing::warn!("ハッシュ値を計算する入力がありません");
+        return Err(ApiError::bad_request("no_files", "No files or text were uploaded"));
+    }
+
+    let expected = form.expected;
+    let results = form
+        .results
+        .into_iter()
+        .map(|(name, digests)| {
+            let verification = expected.as_ref().map(|expected| digests.verify(expected));
+            HashResult { name, digests, verification }
+        })
+        .collect::<Vec<_>>();
+
+    tracing::info!("完了: {}件のハッシュ値を計算", results.len());
+    Ok((StatusCode::OK, Json(HashResponse { results })))
+}
@@ -1,18 +1,25 @@
 // handlers/images.rs
 use axum::{
     extract::Multipart,
-    http::StatusCode,
+    http::{header, StatusCode},
     response::IntoResponse,
     Json,
 };
 use base64::Engine;
+use futures::{stream, StreamExt};
 use serde::Serialize;
+use serde_json::json;
 use uuid::Uuid;
-use std::io::Write;
+use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
+use std::time::Instant;
 
-use crate::services::{converter, compressor};
+use crate::config;
+use crate::handlers::error::ApiError;
+use crate::services::limits::{self, LimitError, UploadLimits};
+use crate::services::pool::{self, PoolError};
+use crate::services::{converter, compressor, editor, formats, icons, inline, responsive, svg, thumbnail};
 
 #[derive(Serialize)]
 pub struct ConvertedFile {
@@ -20,6 +27,9 @@ pub struct ConvertedFile {
     name: String,
     url: String,
     size: usize,
+    // 変換にかかった時間（/convert/images のみ、処理の空き待ちは含まない）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    processing_ms: Option<u64>,
 }
 
 #[derive(Serialize)]
@@ -27,53 +37,340 @@ pub struct ConversionResponse {
     files: Vec<ConvertedFile>,
 }
 
-pub async fn convert_image(mut multipart: Multipart) -> Result<impl IntoResponse, StatusCode> {
-    tracing::info!("開始: 画像変換リクエスト受信");
-    let mut result = Vec::<ConvertedFile>::new();
-    let mut target_format = String::new();
-    let mut files_to_process: Vec<(String, Vec<u8>)> = Vec::new();
-
-    // 一時ディレクトリの作成
-    let temp_dir = format!("/tmp/quicktoolify-{}", Uuid::new_v4());
-    tracing::debug!("一時ディレクトリ作成: {}", temp_dir);
-    fs::create_dir_all(&temp_dir).unwrap();
+// マルチパートフォームの内容（テキストフィールドとアップロードファイル）
+pub(crate) struct FormData {
+    pub fields: HashMap<String, String>,
+    pub files: Vec<(String, Vec<u8>)>,
+}
 
-    // マルチパートフォームデータの処理 - まずすべてのフィールドを収集
-    tracing::debug!("マルチパートフォームデータの処理開始");
+// 読み込み中のマルチパートフォームの内容（stream_form が順に渡す）
+pub(crate) enum FormPart<'a> {
+    Text { name: &'a str, value: &'a str },
+    FileStart { file_name: &'a str },
+    FileChunk(&'a [u8]),
+    FileEnd,
+}
 
-    while let Ok(Some(field)) = multipart.next_field().await {
+// マルチパートフォームデータを読み込みながら内容を handler に渡す（"files" フィールドはファイル全体をメモリに載せずチャンク単位で渡す）
+pub(crate) async fn stream_form<E, F>(mut multipart: Multipart, mut handler: F) -> Result<(), E>
+where
+    E: From<StatusCode>,
+    F: FnMut(FormPart<'_>) -> Result<(), E>,
+{
+    while let Ok(Some(mut field)) = multipart.next_field().await {
         let name = field.name().unwrap_or("unknown").to_string();
         tracing::debug!("フィールド検出: {}", name);
 
-        if name == "format" {
-            target_format = field.text().await.unwrap_or_else(|_| "webp".to_string());
-            tracing::info!("変換先フォーマット: '{}'", target_format);
-        } else if name == "files" {
+        if name == "files" {
             let file_name = field.file_name().unwrap_or("unknown.jpg").to_string();
             let content_type = field.content_type().unwrap_or("application/octet-stream").to_string();
             tracing::info!("ファイル検出: '{}', タイプ: {}", file_name, content_type);
 
-            // 詳細なログを追加
-            tracing::debug!("ファイルデータの読み込み開始");
+            handler(FormPart::FileStart { file_name: &file_name })?;
+            let mut size = 0;
+            loop {
+                match field.chunk().await {
+                    Ok(Some(chunk)) => {
+                        size += chunk.len();
+                        handler(FormPart::FileChunk(&chunk))?;
+                    },
+                    Ok(None) => break,
+                    Err(err) => {
+                        tracing::error!("ファイルの読み込みに失敗: {}", err);
+                        return Err(StatusCode::BAD_REQUEST.into());
+                    },
+                }
+            }
+            tracing::debug!("ファイルサイズ: {} バイト", size);
+            handler(FormPart::FileEnd)?;
+        } else {
+            let value = field.text().await.unwrap_or_default();
+            // 値には鍵や入力テキストが含まれるため、ログには長さだけを出す
+            tracing::debug!("フィールド値: {} ({} バイト)", name, value.len());
+            handler(FormPart::Text { name: &name, value: &value })?;
+        }
+    }
+
+    Ok(())
+}
 
-            // データを取得（シンプルに）
-            let data = match field.bytes().await {
-                Ok(bytes) => {
-                    tracing::debug!("ファイルデータ読み込み成功: {} バイト", bytes.len());
-                    bytes
-                },
-                Err(err) => {
-                    tracing::error!("ファイルデータ読み込みエラー: {}", err);
-                    return Err(StatusCode::BAD_REQUEST);
+// 画素数を確認するために先頭から読むバイト数の上限（これで分からない形式は画素数を確認しない）
+const PROBE_BYTES: usize = 256 * 1024;
+
+impl From<LimitError> for ApiError {
+    fn from(error: LimitError) -> Self {
+        match &error {
+            LimitError::FileTooLarge { max } => ApiError::new(StatusCode::PAYLOAD_TOO_LARGE, error.code(), error.to_string())
+                .with_details(json!({ "limit": max })),
+            LimitError::TooManyFiles { max } => ApiError::new(StatusCode::PAYLOAD_TOO_LARGE, error.code(), error.to_string())
+                .with_details(json!({ "limit": max })),
+            LimitError::TooManyPixels { width, height, max } => {
+                ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, error.code(), error.to_string())
+                    .with_details(json!({ "width": width, "height": height, "limit": max }))
+            },
+            LimitError::DimensionsTooLarge { width, height, max } => {
+                ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, error.code(), error.to_string())
+                    .with_details(json!({ "width": width, "height": height, "limit": max }))
+            },
+            LimitError::TooMuchMemory { required, max } => {
+                ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, error.code(), error.to_string())
+                    .with_details(json!({ "required": required, "limit": max }))
+            },
+            LimitError::InflatedTooLarge { max } => {
+                ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, error.code(), error.to_string())
+                    .with_details(json!({ "limit": max }))
+            },
+        }
+    }
+}
+
+// 画像の処理に失敗したときのエラー（デコードの上限を超えた場合はアップロード時と同じエラーを返す）
+pub(crate) fn processing_error(error: anyhow::Error, status: StatusCode, code: &'static str, file_name: &str) -> ApiError {
+    let error = match error.downcast::<LimitError>() {
+        Ok(limit) => ApiError::from(limit),
+        Err(e) => ApiError::new(status, code, e.to_string()),
+    };
+    error.with_file(file_name)
+}
+
+// マルチパートフォームデータを読み込み、"files" フィールドをファイルとして収集する
+// 画像のファイル数・サイズ・画素数の上限を読み込みながら確認し、上限を超えた時点で読み込みをやめて原因のファイル名を付けたエラーを返す
+pub(crate) async fn read_image_form(multipart: Multipart) -> Result<FormData, ApiError> {
+    read_limited_form(multipart, true).await
+}
+
+// read_image_form と同じくフォームを読み込み、ファイル数・サイズの上限だけを確認する（PDF・テキストなど画像以外のアップロード用）
+pub(crate) async fn read_upload_form(multipart: Multipart) -> Result<FormData, ApiError> {
+    read_limited_form(multipart, false).await
+}
+
+async fn read_limited_form(multipart: Multipart, probe_pixels: bool) -> Result<FormData, ApiError> {
+    let limits = UploadLimits::from_config();
+    let mut fields = HashMap::new();
+    let mut files: Vec<(String, Vec<u8>)> = Vec::new();
+    // 読み込み中のファイルの画素数をまだ確認していない
+    let mut probing = false;
+
+    let reject = |error: LimitError, file_name: &str| {
+        tracing::warn!("アップロードの上限超過 - ファイル: '{}', エラー: {}", file_name, error);
+        ApiError::from(error).with_file(file_name)
+    };
+
+    stream_form(multipart, |part| {
+        match part {
+            FormPart::Text { name, value } => {
+                fields.insert(name.to_string(), value.to_string());
+            },
+            FormPart::FileStart { file_name } => {
+                limits.check_file_count(files.len() + 1).map_err(|e| reject(e, file_name))?;
+                files.push((file_name.to_string(), Vec::new()));
+                probing = probe_pixels;
+            },
+            FormPart::FileChunk(chunk) => {
+                if let Some((file_name, data)) = files.last_mut() {
+                    limits.check_file_size(data.len() + chunk.len()).map_err(|e| reject(e, file_name))?;
+                    data.extend_from_slice(chunk);
+                    // ヘッダーが揃った時点で確認する
+                    if probing && data.len() <= PROBE_BYTES {
+                        if let Some((width, height)) = limits::probe_dimensions(data) {
+                            probing = false;
+                            limits.check_dimensions(width, height).map_err(|e| reject(e, file_name))?;
+                        }
+                    }
                 }
-            };
+            },
+            FormPart::FileEnd => {
+                if let Some((file_name, data)) = files.last() {
+                    if probing && data.len() > PROBE_BYTES {
+                        if let Some((width, height)) = limits::probe_dimensions(data) {
+                            limits.check_dimensions(width, height).map_err(|e| reject(e, file_name))?;
+                        }
+                    }
+                }
+                probing = false;
+            },
+        }
+        Ok::<_, ApiError>(())
+    })
+    .await?;
+
+    Ok(FormData { fields, files })
+}
+
+// ファイル名から小文字の拡張子を取得
+pub(crate) fn file_extension(file_name: &str) -> String {
+    Path::new(file_name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_lowercase()
+}
+
+// 失敗したファイルのレスポンス項目
+fn error_file(original_name: String, error: &anyhow::Error) -> ConvertedFile {
+    ConvertedFile {
+        original_name,
+        name: format!("error-{}", Uuid::new_v4()),
+        url: format!("error:{}", error),
+        size: 0,
+        processing_ms: None,
+    }
+}
+
+// エンコード済みデータをデータURL付きのレスポンス項目に変換
+fn encoded_file(original_name: String, format: &str, data: &[u8]) -> ConvertedFile {
+    let name = format!("{}.{}", Uuid::new_v4(), converter::extension_for(format));
+    let data_base64 = base64::engine::general_purpose::STANDARD.encode(data);
+    ConvertedFile {
+        original_name,
+        name,
+        url: format!("data:{};base64,{}", converter::mime_type_for(format), data_base64),
+        size: data.len(),
+        processing_ms: None,
+    }
+}
+
+// 一時ディレクトリを作って処理し、終わったら削除する
+fn with_temp_dir<T>(process: impl FnOnce(&str) -> anyhow::Result<T>) -> anyhow::Result<T> {
+    let temp_dir = format!("/tmp/quicktoolify-{}", Uuid::new_v4());
+    tracing::debug!("一時ディレクトリ作成: {}", temp_dir);
+    fs::create_dir_all(&temp_dir)?;
+    let result = process(&temp_dir);
+    tracing::debug!("一時ディレクトリ削除: {}", temp_dir);
+    let _ = fs::remove_dir_all(&temp_dir);
+    result
+}
 
-            tracing::debug!("ファイルデータの読み込み完了: {} バイト", data.len());
+// 変換・圧縮の入力を用意する（通常はメモリ上のまま渡し、spill_threshold_bytes を超える場合だけ一時ファイルに書き出す）
+// 一時ファイル名にはアップロード時のファイル名を使わない
+fn with_source<T>(
+    data: &[u8],
+    extension: &str,
+    process: impl FnOnce(converter::Source) -> anyhow::Result<T>,
+) -> anyhow::Result<T> {
+    let threshold = config::get().spill_threshold_bytes;
+    if data.len() <= threshold {
+        return process(converter::Source::Memory(data));
+    }
+    tracing::info!("{} バイトのファイルのため一時ファイル経由で処理 (しきい値: {} バイト)", data.len(), threshold);
+    with_temp_dir(|temp_dir| {
+        let extension = extension.chars().filter(char::is_ascii_alphanumeric).collect::<String>();
+        let input_path = format!("{}/input.{}", temp_dir, extension);
+        tracing::debug!("入力ファイルパス: {}", input_path);
+        fs::write(&input_path, data)?;
+        process(converter::Source::File(&input_path))
+    })
+}
+
+// 1ファイルを変換し、出力ファイル名とデータを返す（on_stage にはデコード・エンコードの開始を通知）
+pub(crate) fn convert_file(
+    file_name: &str,
+    data: &[u8],
+    target_format: &str,
+    options: &converter::ConvertOptions,
+    on_stage: &dyn Fn(converter::Stage),
+) -> anyhow::Result<(String, Vec<u8>)> {
+    let input_ext = file_extension(file_name);
+
+    // 新しいファイル名の生成
+    let extension = converter::extension_for(target_format);
+    let new_filename = format!("{}.{}", Uuid::new_v4(), extension);
+
+    // 変換処理
+    tracing::info!("変換処理開始: {} -> {}", input_ext, target_format);
+    let output_data = with_source(data, &input_ext, |source| {
+        converter::convert(source, &input_ext, target_format, options, on_stage)
+    })?;
+    tracing::info!("変換成功: {}", new_filename);
+
+    Ok((new_filename, output_data))
+}
+
+// 1ファイルを圧縮し、出力ファイル名とデータを返す（on_stage にはデコード・エンコードの開始を通知）
+pub(crate) fn compress_file(
+    file_name: &str,
+    data: &[u8],
+    quality: i32,
+    on_stage: &dyn Fn(converter::Stage),
+) -> anyhow::Result<(String, Vec<u8>)> {
+    let input_ext = file_extension(file_name);
 
-            // 後で処理するためにファイルを保存
-            files_to_process.push((file_name, data.to_vec()));
+    // 新しいファイル名の生成
+    let new_filename = format!("compressed-{}.{}", Uuid::new_v4(), input_ext);
+
+    // 圧縮処理
+    tracing::info!("圧縮処理開始: {} (品質: {}%)", file_name, quality);
+    let output_data = with_source(data, &input_ext, |source| compressor::compress(source, &input_ext, quality, on_stage))?;
+    tracing::info!("圧縮成功: {}", new_filename);
+
+    Ok((new_filename, output_data))
+}
+
+impl From<PoolError> for ApiError {
+    fn from(error: PoolError) -> Self {
+        match &error {
+            PoolError::Busy(_) | PoolError::Closed => {
+                ApiError::new(StatusCode::SERVICE_UNAVAILABLE, "server_busy", error.to_string())
+            },
+            PoolError::Panicked(_) => ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "internal_error", error.to_string()),
         }
     }
+}
+
+// デコード・エンコードなどの処理をブロッキングスレッドで実行する（待ち行列が一杯なら 503）
+pub(crate) async fn run_blocking<T: Send + 'static>(task: impl FnOnce() -> T + Send + 'static) -> Result<T, ApiError> {
+    pool::pool().run(task).await.map_err(ApiError::from)
+}
+
+// 1ファイル分の処理をブロッキングスレッドで実行し、結果と処理時間（ミリ秒）を返す
+// 待ち行列が一杯な場合やパニックは、リクエスト全体ではなくそのファイルの失敗として扱う（処理時間は None）
+async fn run_file<T: Send + 'static>(task: impl FnOnce() -> anyhow::Result<T> + Send + 'static) -> (anyhow::Result<T>, Option<u64>) {
+    let timed = move || {
+        let started = Instant::now();
+        let result = task();
+        (result, Some(started.elapsed().as_millis() as u64))
+    };
+    pool::pool().run(timed).await.unwrap_or_else(|e| (Err(e.into()), None))
+}
+
+// 1リクエスト内で同時に処理するファイル数（全体の上限は処理プールの同時実行数）
+fn batch_concurrency() -> usize {
+    config::get().batch_concurrency.max(1)
+}
+
+// 圧縮後のファイルのMIMEタイプ（圧縮では形式を変えない）
+pub(crate) fn compressed_mime_type(extension: &str) -> &'static str {
+    match extension {
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "webp" => "image/webp",
+        _ => "application/octet-stream",
+    }
+}
+
+// 変換オプション（compression・quality）をフォームから取得（/convert/images とジョブで共通）
+pub(crate) fn convert_options(fields: &HashMap<String, String>) -> Result<converter::ConvertOptions, ApiError> {
+    let value = |key: &str| fields.get(key).map(|v| v.trim()).filter(|v| !v.is_empty());
+    let quality = match value("quality") {
+        Some(v) => match v.parse::<u8>() {
+            Ok(quality @ 1..=100) => Some(quality),
+            _ => return Err(ApiError::bad_request("invalid_option", format!("Quality must be between 1 and 100: {}", v))),
+        },
+        None => None,
+    };
+    Ok(converter::ConvertOptions { compression: value("compression").map(str::to_lowercase), quality })
+}
+
+pub async fn convert_image(multipart: Multipart) -> Result<impl IntoResponse, ApiError> {
+    tracing::info!("開始: 画像変換リクエスト受信");
+
+    // マルチパートフォームデータの処理 - まずすべてのフィールドを収集
+    let form = read_image_form(multipart).await?;
+    let mut target_format = form.fields.get("format").cloned().unwrap_or_default();
+    tracing::info!("変換先フォーマット: '{}'", target_format);
+    let options = convert_options(&form.fields)?;
+    tracing::info!("変換設定: {:?}", options);
+    let files_to_process = form.files;
 
     // フォーマットが空の場合はデフォルト値を設定
     if target_format.is_empty() {
@@ -81,87 +378,69 @@ pub async fn convert_image(mut multipart: Multipart) -> Result<impl IntoResponse
         tracing::info!("フォーマットが空のため、デフォルト値を使用: '{}'", target_format);
     }
 
-    // すべてのファイルを処理
-    let mut file_count = 0;
-    for (file_name, data) in files_to_process {
-        file_count += 1;
-        tracing::info!("ファイル {}の処理開始: {}", file_count, file_name);
-
-        // 一時ファイルとして保存
-        let input_path = format!("{}/{}", temp_dir, file_name);
-        tracing::debug!("入力ファイルパス: {}", input_path);
+    // 変換を始める前に、すべてのファイルについて入力・出力の組み合わせを検証
+    for (file_name, _) in &files_to_process {
+        if let Err(e) = formats::check_conversion(&file_extension(file_name), &target_format, options.compression.as_deref()) {
+            tracing::warn!("未対応の変換 - ファイル: '{}', エラー: {}", file_name, e);
+            return Err(ApiError::bad_request("unsupported_conversion", e.to_string()).with_file(file_name.clone()));
+        }
+    }
+    if let Err(e) = formats::check_conversion("", &target_format, options.compression.as_deref()) {
+        tracing::warn!("未対応の変換: {}", e);
+        return Err(ApiError::bad_request("unsupported_conversion", e.to_string()));
+    }
 
-        let mut file = fs::File::create(&input_path).unwrap();
-        file.write_all(&data).unwrap();
-
-        // 入力ファイルの情報取得
-        let input_ext = Path::new(&file_name)
-            .extension()
-            .and_then(|ext| ext.to_str())
-            .unwrap_or("")
-            .to_lowercase();
-        tracing::debug!("入力ファイル拡張子: {}", input_ext);
-
-        // 新しいファイル名の生成
-        let extension = match target_format.as_str() {
-            "jpeg" => "jpg",
-            format => format,
-        };
-        let new_filename = format!("{}.{}", Uuid::new_v4(), extension);
-        let output_path = format!("{}/{}", temp_dir, new_filename);
-        tracing::debug!("出力ファイルパス: {}", output_path);
-
-        // 変換処理
-        tracing::info!("変換処理開始: {} -> {}", input_ext, target_format);
-        match converter::convert_image(&input_path, &output_path, &target_format) {
-            Ok(_) => {
-                tracing::info!("変換成功: {}", new_filename);
-
-                // 変換されたファイルの読み込み
-                let output_data = fs::read(&output_path).unwrap();
-                let size = output_data.len();
-                tracing::debug!("変換後ファイルサイズ: {} バイト", size);
-
-                // Base64エンコード
-                tracing::debug!("Base64エンコード開始");
-                let data_base64 = base64::engine::general_purpose::STANDARD.encode(&output_data);
-                tracing::debug!("Base64エンコード完了: {} 文字", data_base64.len());
-
-                let mime_type = match target_format.as_str() {
-                    "jpeg" => "image/jpeg",
-                    "png" => "image/png",
-                    "webp" => "image/webp",
-                    "avif" => "image/avif",
-                    _ => "application/octet-stream",
+    // ファイルを並列に処理し（変換はブロッキングスレッドで行い、非同期ランタイムを止めない）、結果はアップロード順に返す
+    let result = stream::iter(files_to_process.into_iter().enumerate())
+        .map(|(index, (file_name, data))| {
+            let (target_format, options) = (target_format.clone(), options.clone());
+            async move {
+                tracing::info!("ファイル {}の処理開始: {}", index + 1, file_name);
+                let (converted, processing_ms) = {
+                    let (file_name, target_format) = (file_name.clone(), target_format.clone());
+                    run_file(move || convert_file(&file_name, &data, &target_format, &options, &|_| {})).await
                 };
-                let url = format!("data:{};base64,{}", mime_type, data_base64);
-                tracing::debug!("データURL生成完了");
+                match converted {
+                    Ok((new_filename, output_data)) => {
+                        let size = output_data.len();
+                        tracing::debug!("変換後ファイルサイズ: {} バイト", size);
 
-                result.push(ConvertedFile {
-                    original_name: file_name,
-                    name: new_filename,
-                    url,
-                    size,
-                });
-                tracing::info!("ファイル {}の処理完了", file_count);
-            },
-            Err(e) => {
-                tracing::error!("変換エラー - ファイル: '{}', フォーマット: '{}', エラー: {:?}",
-                 file_name, target_format, e);
-                // エラー情報をレスポンスに含める
-                result.push(ConvertedFile {
-                    original_name: file_name,
-                    name: format!("error-{}", Uuid::new_v4()),
-                    url: format!("error:{}", e),
-                    size: 0,
-                });
-            }
-        }
-    }
+                        // Base64エンコード
+                        tracing::debug!("Base64エンコード開始");
+                        let data_base64 = base64::engine::general_purpose::STANDARD.encode(&output_data);
+                        tracing::debug!("Base64エンコード完了: {} 文字", data_base64.len());
 
-    // 一時ディレクトリの削除
-    tracing::debug!("一時ディレクトリ削除: {}", temp_dir);
-    let _ = fs::remove_dir_all(temp_dir);
+                        let mime_type = converter::mime_type_for(&target_format);
+                        let url = format!("data:{};base64,{}", mime_type, data_base64);
+                        tracing::debug!("データURL生成完了");
+
+                        tracing::info!("ファイル {}の処理完了 ({} ms)", index + 1, processing_ms.unwrap_or_default());
+                        ConvertedFile {
+                            original_name: file_name,
+                            name: new_filename,
+                            url,
+                            size,
+                            processing_ms,
+                        }
+                    },
+                    Err(e) => {
+                        tracing::error!("変換エラー - ファイル: '{}', フォーマット: '{}', エラー: {:?}",
+                         file_name, target_format, e);
+                        // エラー情報をレスポンスに含める
+                        ConvertedFile {
+                            original_name: file_name,
+                            name: format!("error-{}", Uuid::new_v4()),
+                            url: format!("error:{}", e),
+                            size: 0,
+                            processing_ms,
+                        }
+                    }
+                }
+            }
+        })
+        .buffered(batch_concurrency())
+        .collect::<Vec<_>>()
+        .await;
 
     tracing::info!("完了: {}ファイルを処理", result.len());
     Ok((StatusCode::OK, Json(ConversionResponse { files: result })))
@@ -177,6 +456,9 @@ pub struct CompressedFile {
     original_size: usize,
     compressed_size: usize,
     compression_ratio: f32,
+    // 圧縮にかかった時間（/compress/images のみ、処理の空き待ちは含まない）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    processing_ms: Option<u64>,
 }
 
 #[derive(Serialize)]
@@ -185,128 +467,535 @@ pub struct CompressionResponse {
 }
 
 // 画像圧縮のエンドポイント関数
-pub async fn compress_image(mut multipart: Multipart) -> Result<impl IntoResponse, StatusCode> {
+pub async fn compress_image(multipart: Multipart) -> Result<impl IntoResponse, ApiError> {
     tracing::info!("開始: 画像圧縮リクエスト受信");
-    let mut result = Vec::<CompressedFile>::new();
+
+    // マルチパートフォームデータの処理 - まずすべてのフィールドを収集
+    let form = read_image_form(multipart).await?;
     let mut quality = 60; // デフォルト圧縮品質
-    let mut files_to_process: Vec<(String, Vec<u8>)> = Vec::new();
+    if let Some(quality_str) = form.fields.get("quality") {
+        tracing::info!("圧縮品質設定: '{}'", quality_str);
+        // 品質を1-100の範囲で解析
+        quality = quality_str.parse::<i32>().unwrap_or(60).clamp(1, 100);
+        tracing::info!("適用される圧縮品質: {}", quality);
+    }
+    let files_to_process = form.files;
 
-    // 一時ディレクトリの作成
-    let temp_dir = format!("/tmp/quicktoolify-{}", Uuid::new_v4());
-    tracing::debug!("一時ディレクトリ作成: {}", temp_dir);
-    fs::create_dir_all(&temp_dir).unwrap();
+    // ファイルを並列に処理し（圧縮はブロッキングスレッドで行い、非同期ランタイムを止めない）、結果はアップロード順に返す
+    let result = stream::iter(files_to_process.into_iter().enumerate())
+        .map(|(index, (file_name, data))| async move {
+            tracing::info!("ファイル {}の処理開始: {}", index + 1, file_name);
 
-    // マルチパートフォームデータの処理 - まずすべてのフィールドを収集
-    tracing::debug!("マルチパートフォームデータの処理開始");
+            // 入力ファイルの情報取得
+            let original_size = data.len();
+            tracing::debug!("元のファイルサイズ: {} バイト", original_size);
 
-    while let Ok(Some(field)) = multipart.next_field().await {
-        let name = field.name().unwrap_or("unknown").to_string();
-        tracing::debug!("フィールド検出: {}", name);
+            let (compressed, processing_ms) = {
+                let file_name = file_name.clone();
+                run_file(move || compress_file(&file_name, &data, quality, &|_| {})).await
+            };
+            match compressed {
+                Ok((new_filename, output_data)) => {
+                    let compressed_size = output_data.len();
+                    tracing::debug!("圧縮後ファイルサイズ: {} バイト", compressed_size);
 
-        if name == "quality" {
-            let quality_str = field.text().await.unwrap_or_else(|_| "60".to_string());
-            tracing::info!("圧縮品質設定: '{}'", quality_str);
-            // 品質を1-100の範囲で解析
-            quality = quality_str.parse::<i32>().unwrap_or(60);
-            if quality < 1 { quality = 1; }
-            if quality > 100 { quality = 100; }
-            tracing::info!("適用される圧縮品質: {}", quality);
-        } else if name == "files" {
-            let file_name = field.file_name().unwrap_or("unknown.jpg").to_string();
-            let content_type = field.content_type().unwrap_or("application/octet-stream").to_string();
-            tracing::info!("ファイル検出: '{}', タイプ: {}", file_name, content_type);
+                    // 圧縮率の計算
+                    let compression_ratio = if original_size > 0 {
+                        compressed_size as f32 / original_size as f32
+                    } else {
+                        1.0
+                    };
+                    tracing::debug!("圧縮率: {:.2}%", compression_ratio * 100.0);
+
+                    // Base64エンコード
+                    tracing::debug!("Base64エンコード開始");
+                    let data_base64 = base64::engine::general_purpose::STANDARD.encode(&output_data);
+                    tracing::debug!("Base64エンコード完了: {} 文字", data_base64.len());
+
+                    let mime_type = compressed_mime_type(&file_extension(&file_name));
+                    let url = format!("data:{};base64,{}", mime_type, data_base64);
+                    tracing::debug!("データURL生成完了");
 
-            // ファイルデータの取得
-            let data = match field.bytes().await {
-                Ok(bytes) => bytes,
-                Err(err) => {
-                    tracing::error!("ファイルの読み込みに失敗: {}", err);
-                    return Err(StatusCode::BAD_REQUEST);
+                    tracing::info!("ファイル {}の処理完了 ({} ms)", index + 1, processing_ms.unwrap_or_default());
+                    CompressedFile {
+                        original_name: file_name,
+                        name: new_filename,
+                        url,
+                        original_size,
+                        compressed_size,
+                        compression_ratio,
+                        processing_ms,
+                    }
+                },
+                Err(e) => {
+                    tracing::error!("圧縮エラー - ファイル: '{}', 品質: {}, エラー: {:?}",
+                     file_name, quality, e);
+                    // エラー情報をレスポンスに含める
+                    CompressedFile {
+                        original_name: file_name,
+                        name: format!("error-{}", Uuid::new_v4()),
+                        url: format!("error:{}", e),
+                        original_size,
+                        compressed_size: 0,
+                        compression_ratio: 1.0,
+                        processing_ms,
+                    }
                 }
-            };
-            tracing::debug!("ファイルサイズ: {} バイト", data.len());
+            }
+        })
+        .buffered(batch_concurrency())
+        .collect::<Vec<_>>()
+        .await;
 
-            // 後で処理するためにファイルを保存
-            files_to_process.push((file_name, data.to_vec()));
+    tracing::info!("完了: {}ファイルを処理", result.len());
+    Ok((StatusCode::OK, Json(CompressionResponse { files: result })))
+}
+// 編集オプションをフォームのフィールドから組み立てる
+fn parse_edit_options(fields: &HashMap<String, String>) -> anyhow::Result<editor::EditOptions> {
+    let mut options = editor::EditOptions::default();
+    let value = |key: &str| fields.get(key).map(|v| v.trim()).filter(|v| !v.is_empty());
+
+    if let Some(crop) = value("crop") {
+        options.crop = Some(crop.parse()?);
+    }
+    if let Some(aspect) = value("aspect") {
+        options.aspect = Some(aspect.parse()?);
+    }
+    if let Some(gravity) = value("gravity") {
+        options.gravity = gravity.parse()?;
+    }
+    if let Some(rotate) = value("rotate") {
+        options.rotate = rotate
+            .parse::<f32>()
+            .ok()
+            .filter(|r| r.is_finite())
+            .ok_or_else(|| anyhow::anyhow!("Invalid rotation angle: {}", rotate))?;
+    }
+    if let Some(background) = value("background") {
+        options.background = editor::parse_color(background)?;
+    }
+    if let Some(flip) = value("flip") {
+        options.flip = Some(flip.parse()?);
+    }
+    Ok(options)
+}
+
+// 画像編集（切り抜き・回転・反転）のエンドポイント関数
+pub async fn edit_image(multipart: Multipart) -> Result<impl IntoResponse, ApiError> {
+    tracing::info!("開始: 画像編集リクエスト受信");
+    let form = read_image_form(multipart).await?;
+
+    let options = match parse_edit_options(&form.fields) {
+        Ok(options) => options,
+        Err(e) => {
+            tracing::warn!("編集オプションが不正です: {}", e);
+            return Err(ApiError::bad_request("invalid_option", e.to_string()));
         }
+    };
+    let target_format = form.fields.get("format").cloned().unwrap_or_default();
+    tracing::info!("編集オプション: {:?}, 出力フォーマット: '{}'", options, target_format);
+    if !target_format.is_empty() && formats::output_format(&target_format).is_none() {
+        tracing::warn!("未サポート出力形式: {}", target_format);
+        return Err(ApiError::bad_request("unsupported_format", format!("Unsupported output format: {}", target_format)));
     }
 
-    // すべてのファイルを処理
-    let mut file_count = 0;
-    for (file_name, data) in files_to_process {
-        file_count += 1;
-        tracing::info!("ファイル {}の処理開始: {}", file_count, file_name);
+    let mut result = Vec::<ConvertedFile>::new();
+    for (file_name, data) in form.files {
+        tracing::info!("ファイルの編集開始: {}", file_name);
+        let input_ext = file_extension(&file_name);
 
-        // 一時ファイルとして保存
-        let input_path = format!("{}/{}", temp_dir, file_name);
-        tracing::debug!("入力ファイルパス: {}", input_path);
+        // 出力フォーマットの指定がなければ入力と同じ形式（未対応の形式はPNG）
+        let format = if target_format.is_empty() {
+            formats::output_format(&input_ext).map(|f| f.id).unwrap_or("png").to_string()
+        } else {
+            target_format.clone()
+        };
 
-        let mut file = fs::File::create(&input_path).unwrap();
-        file.write_all(&data).unwrap();
+        let (edited, _) = {
+            let (options, format) = (options.clone(), format.clone());
+            run_file(move || {
+                converter::decode_image(&data, &input_ext)
+                    .and_then(|img| editor::apply_edits(img, &options))
+                    .and_then(|img| converter::encode_image(&img, &format))
+            })
+            .await
+        };
 
-        // 入力ファイルの情報取得
+        match edited {
+            Ok(output_data) => {
+                tracing::info!("編集成功: {} ({} バイト)", file_name, output_data.len());
+                result.push(encoded_file(file_name, &format, &output_data));
+            },
+            Err(e) => {
+                tracing::error!("編集エラー - ファイル: '{}', エラー: {:?}", file_name, e);
+                result.push(error_file(file_name, &e));
+            }
+        }
+    }
+
+    tracing::info!("完了: {}ファイルを処理", result.len());
+    Ok((StatusCode::OK, Json(ConversionResponse { files: result })))
+}
+
+// サムネイル1件分
+#[derive(Serialize)]
+pub struct ThumbnailFile {
+    name: String,
+    url: String,
+    size: usize,
+    width: u32,
+    height: u32,
+}
+
+// 入力ファイルごとのサムネイル一覧
+#[derive(Serialize)]
+pub struct ThumbnailSet {
+    original_name: String,
+    thumbnails: Vec<ThumbnailFile>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ThumbnailResponse {
+    files: Vec<ThumbnailSet>,
+}
+
+// サムネイル生成のエンドポイント関数
+pub async fn generate_thumbnails(multipart: Multipart) -> Result<impl IntoResponse, ApiError> {
+    tracing::info!("開始: サムネイル生成リクエスト受信");
+    let form = read_image_form(multipart).await?;
+
+    let sizes = match thumbnail::parse_sizes(form.fields.get("sizes").map(String::as_str).unwrap_or("")) {
+        Ok(sizes) => sizes,
+        Err(e) => {
+            tracing::warn!("サムネイルサイズが不正です: {}", e);
+            return Err(ApiError::bad_request("invalid_option", e.to_string()));
+        }
+    };
+    let mode = match form.fields.get("mode").map(|m| m.parse::<thumbnail::CropMode>()) {
+        Some(Ok(mode)) => mode,
+        Some(Err(e)) => {
+            tracing::warn!("切り抜きモードが不正です: {}", e);
+            return Err(ApiError::bad_request("invalid_option", e.to_string()));
+        }
+        None => thumbnail::CropMode::default(),
+    };
+    let format = form.fields.get("format").cloned().filter(|f| !f.is_empty()).unwrap_or_else(|| "webp".to_string());
+    tracing::info!("サムネイルサイズ: {:?}, モード: {:?}, フォーマット: '{}'", sizes, mode, format);
+
+    let mut result = Vec::<ThumbnailSet>::new();
+    for (file_name, data) in form.files {
+        tracing::info!("サムネイル生成開始: {}", file_name);
+
+        // デコードと各サイズのエンコードはブロッキングスレッドで行う（エンコードに失敗したらそこまでの結果を返す）
+        let (generated, _) = {
+            let (input_ext, sizes, format) = (file_extension(&file_name), sizes.clone(), format.clone());
+            run_file(move || {
+                let img = converter::decode_image(&data, &input_ext)?;
+                let mut encoded = Vec::new();
+                for size in sizes {
+                    let thumb = thumbnail::generate_thumbnail(&img, size, mode);
+                    match converter::encode_image(&thumb, &format) {
+                        Ok(output_data) => encoded.push((size, output_data)),
+                        Err(e) => return Ok((encoded, Some((size, e)))),
+                    }
+                }
+                Ok((encoded, None))
+            })
+            .await
+        };
+        let (encoded, failed) = match generated {
+            Ok(generated) => generated,
+            Err(e) => {
+                tracing::error!("デコードエラー - ファイル: '{}', エラー: {:?}", file_name, e);
+                result.push(ThumbnailSet { original_name: file_name, thumbnails: Vec::new(), error: Some(e.to_string()) });
+                continue;
+            }
+        };
+
+        let thumbnails = encoded
+            .into_iter()
+            .map(|(size, output_data)| {
+                let encoded = encoded_file(file_name.clone(), &format, &output_data);
+                ThumbnailFile {
+                    name: encoded.name,
+                    url: encoded.url,
+                    size: encoded.size,
+                    width: size.width,
+                    height: size.height,
+                }
+            })
+            .collect::<Vec<_>>();
+        let error = failed.map(|(size, e)| {
+            tracing::error!("エンコードエラー - ファイル: '{}', サイズ: {:?}, エラー: {:?}", file_name, size, e);
+            e.to_string()
+        });
+
+        tracing::info!("サムネイル生成完了: {} ({}件)", file_name, thumbnails.len());
+        result.push(ThumbnailSet { original_name: file_name, thumbnails, error });
+    }
+
+    tracing::info!("完了: {}ファイルを処理", result.len());
+    Ok((StatusCode::OK, Json(ThumbnailResponse { files: result })))
+}
+
+// レスポンシブ画像1件分
+#[derive(Serialize)]
+pub struct ResponsiveFile {
+    name: String,
+    url: String,
+    size: usize,
+    format: String,
+    width: u32,
+    height: u32,
+}
+
+#[derive(Serialize)]
+pub struct ResponsiveResponse {
+    original_name: String,
+    files: Vec<ResponsiveFile>,
+    html: String,
+}
+
+// レスポンシブ画像セット（srcset用の複数幅・複数フォーマット）生成のエンドポイント関数
+pub async fn generate_responsive_images(multipart: Multipart) -> Result<impl IntoResponse, ApiError> {
+    tracing::info!("開始: レスポンシブ画像生成リクエスト受信");
+    let form = read_image_form(multipart).await?;
+    let field = |key: &str, default: &str| -> String {
+        form.fields.get(key).map(|v| v.trim().to_string()).filter(|v| !v.is_empty()).unwrap_or_else(|| default.to_string())
+    };
+
+    let widths = responsive::parse_widths(&field("widths", "320,640,960,1280,1920"));
+    let formats = responsive::parse_formats(&field("formats", "avif,webp,jpeg"));
+    let (widths, formats) = match (widths, formats) {
+        (Ok(widths), Ok(formats)) => (widths, formats),
+        (Err(e), _) | (_, Err(e)) => {
+            tracing::warn!("レスポンシブ画像の設定が不正です: {}", e);
+            return Err(ApiError::bad_request("invalid_option", e.to_string()));
+        }
+    };
+    let quality = field("quality", "75").parse::<i32>().unwrap_or(75).clamp(1, 100);
+    let sizes = field("sizes", "100vw");
+    let alt = form.fields.get("alt").cloned().unwrap_or_default();
+    let url_prefix = form.fields.get("url_prefix").cloned().unwrap_or_default();
+    tracing::info!("幅: {:?}, フォーマット: {:?}, 品質: {}", widths, formats, quality);
+
+    // 元画像は1ファイルのみ
+    let Some((file_name, data)) = form.files.into_iter().next() else {
+        tracing::warn!("元画像が指定されていません");
+        return Err(ApiError::bad_request("no_files", "No image was uploaded"));
+    };
+
+    let input_ext = file_extension(&file_name);
+    let source = match run_blocking(move || converter::decode_image(&data, &input_ext)).await? {
+        Ok(img) => img,
+        Err(e) => {
+            tracing::error!("デコードエラー - ファイル: '{}', エラー: {:?}", file_name, e);
+            return Err(processing_error(e, StatusCode::UNPROCESSABLE_ENTITY, "decode_failed", &file_name));
+        }
+    };
+
+    let base_name = responsive::sanitize_base_name(&file_name);
+    let variant_formats = formats.clone();
+    let generated = run_blocking(move || responsive::generate_variants(&source, &base_name, &widths, &variant_formats, quality)).await?;
+    let variants = match generated {
+        Ok(variants) => variants,
+        Err(e) => {
+            tracing::error!("レスポンシブ画像生成エラー - ファイル: '{}', エラー: {:?}", file_name, e);
+            return Err(processing_error(e, StatusCode::INTERNAL_SERVER_ERROR, "processing_failed", &file_name));
+        }
+    };
+
+    let html = responsive::picture_html(&variants, &formats, &sizes, &alt, &url_prefix);
+    let files = variants
+        .into_iter()
+        .map(|v| {
+            let data_base64 = base64::engine::general_purpose::STANDARD.encode(&v.data);
+            ResponsiveFile {
+                url: format!("data:{};base64,{}", converter::mime_type_for(&v.format), data_base64),
+                size: v.data.len(),
+                name: v.file_name,
+                format: v.format,
+                width: v.width,
+                height: v.height,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    tracing::info!("完了: {}ファイルを生成", files.len());
+    Ok((StatusCode::OK, Json(ResponsiveResponse { original_name: file_name, files, html })))
+}
+
+// favicon・アプリアイコン一式（ZIP）生成のエンドポイント関数
+pub async fn generate_icons(multipart: Multipart) -> Result<impl IntoResponse, ApiError> {
+    tracing::info!("開始: アイコンパッケージ生成リクエスト受信");
+    let form = read_image_form(multipart).await?;
+
+    let mut options = icons::IconOptions::default();
+    if let Some(name) = form.fields.get("name").filter(|v| !v.trim().is_empty()) {
+        options.name = name.trim().to_string();
+        options.short_name = options.name.clone();
+    }
+    if let Some(short_name) = form.fields.get("short_name").filter(|v| !v.trim().is_empty()) {
+        options.short_name = short_name.trim().to_string();
+    }
+    if let Some(theme_color) = form.fields.get("theme_color").filter(|v| !v.trim().is_empty()) {
+        options.theme_color = theme_color.trim().to_string();
+    }
+    if let Some(background) = form.fields.get("background_color").filter(|v| !v.trim().is_empty()) {
+        match editor::parse_color(background) {
+            Ok(color) => options.background_color = color,
+            Err(e) => {
+                tracing::warn!("背景色が不正です: {}", e);
+                return Err(ApiError::bad_request("invalid_option", e.to_string()));
+            }
+        }
+    }
+    if let Some(safe_zone) = form.fields.get("safe_zone").and_then(|v| v.trim().parse::<f32>().ok()) {
+        options.safe_zone = safe_zone.clamp(0.1, 1.0);
+    }
+    tracing::info!("アイコン設定: {:?}", options);
+
+    // 元画像は1ファイルのみ
+    let Some((file_name, data)) = form.files.into_iter().next() else {
+        tracing::warn!("元画像が指定されていません");
+        return Err(ApiError::bad_request("no_files", "No image was uploaded"));
+    };
+
+    let input_ext = file_extension(&file_name);
+    let source = match run_blocking(move || converter::decode_image(&data, &input_ext)).await? {
+        Ok(img) => img,
+        Err(e) => {
+            tracing::error!("デコードエラー - ファイル: '{}', エラー: {:?}", file_name, e);
+            return Err(processing_error(e, StatusCode::UNPROCESSABLE_ENTITY, "decode_failed", &file_name));
+        }
+    };
+
+    match run_blocking(move || icons::generate_icon_package(&source, &options)).await? {
+        Ok(zip) => {
+            tracing::info!("完了: アイコンパッケージ生成 ({} バイト)", zip.len());
+            Ok((
+                StatusCode::OK,
+                [
+                    (header::CONTENT_TYPE, "application/zip"),
+                    (header::CONTENT_DISPOSITION, "attachment; filename=\"icons.zip\""),
+                ],
+                zip,
+            ))
+        },
+        Err(e) => {
+            tracing::error!("アイコンパッケージ生成エラー - ファイル: '{}', エラー: {:?}", file_name, e);
+            Err(processing_error(e, StatusCode::INTERNAL_SERVER_ERROR, "processing_failed", &file_name))
+        }
+    }
+}
+
+// SVGラスタライズのエンドポイント関数
+pub async fn rasterize_svg(multipart: Multipart) -> Result<impl IntoResponse, ApiError> {
+    tracing::info!("開始: SVGラスタライズリクエスト受信");
+    let form = read_image_form(multipart).await?;
+
+    let format = form.fields.get("format").map(|v| v.trim()).filter(|v| !v.is_empty()).unwrap_or("png").to_string();
+    if formats::output_format(&format).is_none() {
+        tracing::warn!("未サポート出力形式: {}", format);
+        return Err(ApiError::bad_request("unsupported_format", format!("Unsupported output format: {}", format)));
+    }
+
+    let mut options = svg::RasterizeOptions::default();
+    if let Some(width) = form.fields.get("width").map(|v| v.trim()).filter(|v| !v.is_empty()) {
+        match width.parse::<u32>() {
+            Ok(width) if width > 0 => options.width = Some(width),
+            _ => {
+                tracing::warn!("幅の指定が不正です: {}", width);
+                return Err(ApiError::bad_request("invalid_option", format!("Invalid width: {}", width)));
+            }
+        }
+    }
+    if let Some(dpi) = form.fields.get("dpi").map(|v| v.trim()).filter(|v| !v.is_empty()) {
+        match dpi.parse::<f32>() {
+            Ok(dpi) if dpi.is_finite() && dpi > 0.0 => options.dpi = Some(dpi),
+            _ => {
+                tracing::warn!("DPIの指定が不正です: {}", dpi);
+                return Err(ApiError::bad_request("invalid_option", format!("Invalid DPI: {}", dpi)));
+            }
+        }
+    }
+    tracing::info!("ラスタライズ設定: {:?}, 出力フォーマット: '{}'", options, format);
+
+    let mut result = Vec::<ConvertedFile>::new();
+    for (file_name, data) in form.files {
+        tracing::info!("ファイルのラスタライズ開始: {}", file_name);
+        let (rasterized, _) = {
+            let (options, format) = (options.clone(), format.clone());
+            run_file(move || svg::rasterize(&data, &options).and_then(|img| converter::encode_image(&img, &format))).await
+        };
+
+        match rasterized {
+            Ok(output_data) => {
+                tracing::info!("ラスタライズ成功: {} ({} バイト)", file_name, output_data.len());
+                result.push(encoded_file(file_name, &format, &output_data));
+            },
+            Err(e) => {
+                tracing::error!("ラスタライズエラー - ファイル: '{}', エラー: {:?}", file_name, e);
+                result.push(error_file(file_name, &e));
+            }
+        }
+    }
+
+    tracing::info!("完了: {}ファイルを処理", result.len());
+    Ok((StatusCode::OK, Json(ConversionResponse { files: result })))
+}
+
+// SVG最適化のエンドポイント関数（結果は圧縮と同じ形式で返す）
+pub async fn optimize_svg(multipart: Multipart) -> Result<impl IntoResponse, ApiError> {
+    tracing::info!("開始: SVG最適化リクエスト受信");
+    let form = read_image_form(multipart).await?;
+
+    let mut options = svg::MinifyOptions::default();
+    if let Some(precision) = form.fields.get("precision").and_then(|v| v.trim().parse::<usize>().ok()) {
+        options.precision = precision.min(8);
+    }
+    if let Some(value) = form.fields.get("remove_metadata") {
+        options.remove_metadata = value.trim() != "false";
+    }
+    if let Some(value) = form.fields.get("collapse_groups") {
+        options.collapse_groups = value.trim() != "false";
+    }
+    tracing::info!("最適化設定: {:?}", options);
+
+    let mut result = Vec::<CompressedFile>::new();
+    for (file_name, data) in form.files {
+        tracing::info!("ファイルの最適化開始: {}", file_name);
         let original_size = data.len();
-        tracing::debug!("元のファイルサイズ: {} バイト", original_size);
-
-        let input_ext = Path::new(&file_name)
-            .extension()
-            .and_then(|ext| ext.to_str())
-            .unwrap_or("")
-            .to_lowercase();
-        tracing::debug!("入力ファイル拡張子: {}", input_ext);
-
-        // 新しいファイル名の生成
-        let new_filename = format!("compressed-{}.{}", Uuid::new_v4(), input_ext);
-        let output_path = format!("{}/{}", temp_dir, new_filename);
-        tracing::debug!("出力ファイルパス: {}", output_path);
-
-        // 圧縮処理
-        tracing::info!("圧縮処理開始: {} (品質: {}%)", file_name, quality);
-        match compressor::compress_image(&input_path, &output_path, quality) {
-            Ok(_) => {
-                tracing::info!("圧縮成功: {}", new_filename);
-
-                // 圧縮されたファイルの読み込み
-                let output_data = fs::read(&output_path).unwrap();
-                let compressed_size = output_data.len();
-                tracing::debug!("圧縮後ファイルサイズ: {} バイト", compressed_size);
-
-                // 圧縮率の計算
+
+        let (minified, _) = {
+            let options = options.clone();
+            run_file(move || svg::minify(&data, &options)).await
+        };
+        match minified {
+            Ok(output) => {
+                let compressed_size = output.len();
                 let compression_ratio = if original_size > 0 {
                     compressed_size as f32 / original_size as f32
                 } else {
                     1.0
                 };
-                tracing::debug!("圧縮率: {:.2}%", compression_ratio * 100.0);
-
-                // Base64エンコード
-                tracing::debug!("Base64エンコード開始");
-                let data_base64 = base64::engine::general_purpose::STANDARD.encode(&output_data);
-                tracing::debug!("Base64エンコード完了: {} 文字", data_base64.len());
-
-                let mime_type = match input_ext.as_str() {
-                    "jpg" | "jpeg" => "image/jpeg",
-                    "png" => "image/png",
-                    "webp" => "image/webp",
-                    _ => "application/octet-stream",
-                };
-                let url = format!("data:{};base64,{}", mime_type, data_base64);
-                tracing::debug!("データURL生成完了");
+                tracing::info!("最適化成功: {} ({} → {} バイト)", file_name, original_size, compressed_size);
 
+                let data_base64 = base64::engine::general_purpose::STANDARD.encode(output.as_bytes());
                 result.push(CompressedFile {
                     original_name: file_name,
-                    name: new_filename,
-                    url,
+                    name: format!("optimized-{}.svg", Uuid::new_v4()),
+                    url: format!("data:image/svg+xml;base64,{}", data_base64),
                     original_size,
                     compressed_size,
                     compression_ratio,
+                    processing_ms: None,
                 });
-                tracing::info!("ファイル {}の処理完了", file_count);
             },
             Err(e) => {
-                tracing::error!("圧縮エラー - ファイル: '{}', 品質: {}, エラー: {:?}",
-                 file_name, quality, e);
-                // エラー情報をレスポンスに含める
+                tracing::error!("最適化エラー - ファイル: '{}', エラー: {:?}", file_name, e);
                 result.push(CompressedFile {
                     original_name: file_name,
                     name: format!("error-{}", Uuid::new_v4()),
@@ -314,15 +1003,121 @@ pub async fn compress_image(mut multipart: Multipart) -> Result<impl IntoRespons
                     original_size,
                     compressed_size: 0,
                     compression_ratio: 1.0,
+                    processing_ms: None,
                 });
             }
         }
     }
 
-    // 一時ディレクトリの削除
-    tracing::debug!("一時ディレクトリ削除: {}", temp_dir);
-    let _ = fs::remove_dir_all(temp_dir);
-
     tracing::info!("完了: {}ファイルを処理", result.len());
     Ok((StatusCode::OK, Json(CompressionResponse { files: result })))
-}
\ No newline at end of file
+}
+
+// 埋め込み用に変換した画像1件分
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InlineFile {
+    original_name: String,
+    mime_type: &'static str,
+    original_size: usize,
+    // 埋め込む画像のバイト数（最適化した場合は最適化後）
+    size: usize,
+    optimized: bool,
+    #[serde(flatten)]
+    inline: inline::InlineImage,
+    // データURIの文字数
+    length: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    warning: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InlineResponse {
+    files: Vec<InlineFile>,
+    budget_bytes: usize,
+}
+
+// 埋め込み用に画像を最適化する（JPEG・PNG・WebP は compress_file、SVG は minify、それ以外はそのまま）
+fn optimize_for_inline(extension: &str, data: &[u8], quality: i32) -> anyhow::Result<Option<Vec<u8>>> {
+    match extension {
+        "jpg" | "jpeg" | "png" | "webp" => {
+            let file_name = format!("input.{}", extension);
+            compress_file(&file_name, data, quality, &|_| {}).map(|(_, output)| Some(output))
+        },
+        "svg" => Ok(Some(svg::minify(data, &svg::MinifyOptions::default())?.into_bytes())),
+        _ => Ok(None),
+    }
+}
+
+// 画像をデータURIと CSS・<img> のスニペットに変換するエンドポイント関数
+pub async fn inline_images(multipart: Multipart) -> Result<impl IntoResponse, ApiError> {
+    tracing::info!("開始: データURI生成リクエスト受信");
+    let form = read_image_form(multipart).await?;
+    if form.files.is_empty() {
+        return Err(ApiError::bad_request("no_files", "No images were uploaded"));
+    }
+
+    let optimize = form.fields.get("optimize").is_some_and(|v| v.trim() == "true");
+    let quality = form.fields.get("quality").and_then(|v| v.trim().parse::<i32>().ok()).unwrap_or(60).clamp(1, 100);
+    let alt = form.fields.get("alt").cloned().unwrap_or_default();
+    let budget = config::get().inline_budget_bytes;
+    tracing::info!("埋め込み設定: 最適化={}, 品質={}, 目安={} バイト", optimize, quality, budget);
+
+    let mut files = Vec::new();
+    for (file_name, data) in form.files {
+        let extension = file_extension(&file_name);
+        // SVGZ は gzip 圧縮されたままではデータURIとして表示できない
+        let mime_type = formats::input_format(&extension)
+            .filter(|_| extension != "svgz")
+            .map(|format| format.mime_type)
+            .ok_or_else(|| {
+                ApiError::bad_request("unsupported_format", format!("Unsupported image format: '{}'", extension)).with_file(&file_name)
+            })?;
+
+        let original_size = data.len();
+        let (data, optimized) = if optimize {
+            // 最適化はブロッキングスレッドで行い、元の画像は最適化できなかった場合のために戻してもらう
+            let extension = extension.clone();
+            run_blocking(move || {
+                let optimized = optimize_for_inline(&extension, &data, quality);
+                (data, optimized)
+            })
+            .await?
+        } else {
+            (data, Ok(None))
+        };
+        let optimized = match optimized {
+            // 最適化で大きくなった場合は元の画像を使う
+            Ok(Some(output)) if output.len() < original_size => Some(output),
+            Ok(_) => None,
+            Err(e) => {
+                tracing::warn!("最適化に失敗したため元の画像を使用: '{}', エラー: {}", file_name, e);
+                None
+            },
+        };
+
+        let embedded = optimized.as_deref().unwrap_or(&data);
+        let inline = inline::build(embedded, mime_type, &alt);
+        let length = inline.data_uri.len();
+        let warning = inline::budget_warning(length, budget);
+        if warning.is_some() {
+            tracing::warn!("データURIが目安サイズを超過: '{}' ({} > {} バイト)", file_name, length, budget);
+        }
+        tracing::info!("データURI生成: '{}' ({} → {} バイト, {} 文字)", file_name, original_size, embedded.len(), length);
+
+        files.push(InlineFile {
+            original_name: file_name,
+            mime_type,
+            original_size,
+            size: embedded.len(),
+            optimized: optimized.is_some(),
+            inline,
+            length,
+            warning,
+        });
+    }
+
+    tracing::info!("完了: {}ファイルを処理", files.len());
+    Ok((StatusCode::OK, Json(InlineResponse { files, budget_bytes: budget })))
+}
@@ -0,0 +1,146 @@
+// handlers/jobs.rs
+use axum::{
+    extract::{Multipart, Path},
+    http::StatusCode,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse,
+    },
+    Json,
+};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use tokio::sync::broadcast::{self, error::RecvError};
+use uuid::Uuid;
+
+use crate::handlers::error::ApiError;
+use crate::handlers::images::{compress_file, compressed_mime_type, convert_file, convert_options, file_extension, read_image_form};
+use crate::services::jobs::{self, FileOutput, Job, JobError, JobEvent, Processor};
+use crate::services::{converter, formats};
+
+impl From<JobError> for ApiError {
+    fn from(error: JobError) -> Self {
+        match &error {
+            JobError::TooManyJobs(_) => ApiError::new(StatusCode::SERVICE_UNAVAILABLE, "too_many_jobs", error.to_string()),
+        }
+    }
+}
+
+// 変換（/convert/images と同じ format・compression）の処理を作る
+fn convert_processor(format: String, options: converter::ConvertOptions) -> Processor {
+    Arc::new(move |file_name: &str, data: &[u8], on_stage: &dyn Fn(converter::Stage)| {
+        let (name, data) = convert_file(file_name, data, &format, &options, on_stage)?;
+        Ok(FileOutput { name, mime_type: converter::mime_type_for(&format), data })
+    })
+}
+
+// 圧縮（/compress/images と同じ quality）の処理を作る
+fn compress_processor(quality: i32) -> Processor {
+    Arc::new(move |file_name: &str, data: &[u8], on_stage: &dyn Fn(converter::Stage)| {
+        let (name, data) = compress_file(file_name, data, quality, on_stage)?;
+        Ok(FileOutput { name, mime_type: compressed_mime_type(&file_extension(file_name)), data })
+    })
+}
+
+// 画像の一括処理ジョブを登録するエンドポイント関数（処理はバックグラウンドで行い、すぐにジョブIDを返す）
+pub async fn create_job(multipart: Multipart) -> Result<impl IntoResponse, ApiError> {
+    tracing::info!("開始: ジョブ登録リクエスト受信");
+    let form = read_image_form(multipart).await?;
+    if form.files.is_empty() {
+        return Err(ApiError::bad_request("no_files", "No images were uploaded"));
+    }
+    let value = |key: &str| form.fields.get(key).map(|v| v.trim()).filter(|v| !v.is_empty());
+
+    let (operation, processor) = match value("operation").unwrap_or("convert") {
+        "convert" => {
+            let format = value("format").unwrap_or("webp").to_string();
+            let options = convert_options(&form.fields)?;
+            // 登録前にすべてのファイルについて入力・出力の組み合わせを検証
+            for (file_name, _) in &form.files {
+                formats::check_conversion(&file_extension(file_name), &format, options.compression.as_deref()).map_err(|e| {
+                    tracing::warn!("未対応の変換 - ファイル: '{}', エラー: {}", file_name, e);
+                    ApiError::bad_request("unsupported_conversion", e.to_string()).with_file(file_name.clone())
+                })?;
+            }
+            tracing::info!("変換ジョブ: {} ({:?})", format, options);
+            ("convert", convert_processor(format, options))
+        },
+        "compress" => {
+            let quality = value("quality").and_then(|v| v.parse::<i32>().ok()).unwrap_or(60).clamp(1, 100);
+            tracing::info!("圧縮ジョブ: 品質 {}", quality);
+            ("compress", compress_processor(quality))
+        },
+        other => {
+            return Err(ApiError::bad_request("invalid_option", format!("Unsupported operation: {}", other)));
+        },
+    };
+
+    let job = jobs::store().submit(operation, form.files, processor)?;
+    Ok((StatusCode::ACCEPTED, Json(job.snapshot())))
+}
+
+fn find_job(id: &str) -> Result<Arc<Job>, ApiError> {
+    id.parse::<Uuid>()
+        .ok()
+        .and_then(|id| jobs::store().get(id))
+        .ok_or_else(|| ApiError::new(StatusCode::NOT_FOUND, "job_not_found", format!("Job not found: {}", id)))
+}
+
+// ジョブの進捗とファイルごとの結果を返すエンドポイント関数
+pub async fn get_job(Path(id): Path<String>) -> Result<impl IntoResponse, ApiError> {
+    let job = find_job(&id)?;
+    Ok((StatusCode::OK, Json(job.snapshot())))
+}
+
+// ジョブを取り消すエンドポイント関数（処理中のファイルは最後まで処理し、待機中のファイルは処理しない）
+pub async fn cancel_job(Path(id): Path<String>) -> Result<impl IntoResponse, ApiError> {
+    let job = find_job(&id)?;
+    tracing::info!("ジョブ取り消し: {}", job.id);
+    job.cancel();
+    Ok((StatusCode::OK, Json(job.snapshot())))
+}
+
+// SSE で送るイベントの状態（ジョブが終わったら配信を終える）
+struct EventStream {
+    job: Arc<Job>,
+    receiver: broadcast::Receiver<JobEvent>,
+    pending: VecDeque<JobEvent>,
+    finished: bool,
+}
+
+// ジョブの進捗を Server-Sent Events で配信するエンドポイント関数
+// イベント名はファイルの状態（received・decoding・encoding・done・failed・cancelled）で、ジョブの終了時に end を送る
+pub async fn job_events(Path(id): Path<String>) -> Result<impl IntoResponse, ApiError> {
+    let job = find_job(&id)?;
+    tracing::info!("ジョブの進捗配信開始: {}", job.id);
+
+    // 購読してから現在の状態を取得し、その間のイベントを取りこぼさないようにする（重複は状態の再送なので問題ない）
+    let receiver = job.subscribe();
+    let pending = job.replay().into();
+    let state = EventStream { job, receiver, pending, finished: false };
+
+    let stream = futures::stream::unfold(state, |mut state| async move {
+        loop {
+            if state.finished {
+                return None;
+            }
+            if let Some(event) = state.pending.pop_front() {
+                state.finished = matches!(event, JobEvent::Finished(_));
+                let event = Event::default().event(event.name()).json_data(&event);
+                return Some((event, state));
+            }
+            match state.receiver.recv().await {
+                Ok(event) => state.pending.push_back(event),
+                // 配信が追いつかずイベントを取りこぼした場合は、現在の状態を送り直す
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!("ジョブ {} のイベントを {} 件取りこぼしたため状態を再送", state.job.id, skipped);
+                    let replay = state.job.replay();
+                    state.pending.extend(replay);
+                },
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
@@ -0,0 +1,325 @@
+// handlers/json.rs
+use axum::{body::Body, extract::Query, http::StatusCode, response::IntoResponse, Json};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio_util::io::{StreamReader, SyncIoBridge};
+
+use crate::config;
+use crate::handlers::error::ApiError;
+use crate::handlers::images::run_blocking;
+use crate::services::diff::{self, PatchError, PatchKind};
+use crate::services::json::{self, DataFormat, DocumentError, FormatOptions};
+use crate::services::query::{self, QueryError, QueryLanguage, QueryResult};
+use crate::services::schema;
+
+// 問い合わせ結果の件数（省略時と上限）
+const DEFAULT_QUERY_LIMIT: usize = 1000;
+const MAX_QUERY_LIMIT: usize = 10_000;
+
+#[derive(Deserialize)]
+pub struct FormatRequest {
+    input: String,
+    // 入力形式（省略時は json）
+    from: Option<String>,
+    // 出力形式（省略時は入力と同じ）
+    to: Option<String>,
+    #[serde(default)]
+    minify: bool,
+    #[serde(default)]
+    sort_keys: bool,
+    // 空白の数または "tab"
+    indent: Option<serde_json::Value>,
+}
+
+#[derive(Serialize)]
+pub struct FormatResponse {
+    output: String,
+    format: DataFormat,
+    size: usize,
+}
+
+// 解析・変換エラーをレスポンスに変換（構文エラーは行・列を付ける）
+impl From<DocumentError> for ApiError {
+    fn from(error: DocumentError) -> Self {
+        match &error {
+            DocumentError::Syntax { format, line, column, .. } => {
+                ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, "invalid_document", error.to_string())
+                    .with_details(json!({ "format": format, "line": line, "column": column }))
+            },
+            DocumentError::Conversion(_) => ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, "conversion_failed", error.to_string()),
+            DocumentError::Unsupported(_) => ApiError::bad_request("invalid_option", error.to_string()),
+        }
+    }
+}
+
+// 整形の設定をリクエストから組み立てる
+fn parse_format_options(request: &FormatRequest) -> Result<FormatOptions, DocumentError> {
+    let mut options = FormatOptions {
+        minify: request.minify,
+        sort_keys: request.sort_keys,
+        ..FormatOptions::default()
+    };
+    if let Some(from) = request.from.as_deref().filter(|v| !v.trim().is_empty()) {
+        options.from = from.parse()?;
+    }
+    options.to = match request.to.as_deref().filter(|v| !v.trim().is_empty()) {
+        Some(to) => to.parse()?,
+        None => options.from,
+    };
+    match &request.indent {
+        None | Some(serde_json::Value::Null) => {},
+        Some(serde_json::Value::String(indent)) => options.indent = indent.parse()?,
+        Some(indent) => options.indent = indent.to_string().parse()?,
+    }
+    Ok(options)
+}
+
+// JSONの整形・検証・形式変換のエンドポイント関数
+pub async fn format_json(Json(request): Json<FormatRequest>) -> Result<impl IntoResponse, ApiError> {
+    tracing::info!("開始: JSON整形リクエスト受信 ({} バイト)", request.input.len());
+    let options = parse_format_options(&request)?;
+    tracing::info!("整形設定: {:?}", options);
+
+    let (input_len, format) = (request.input.len(), options.to);
+    let output = run_blocking(move || json::format_document(&request.input, &options))
+        .await?
+        .inspect_err(|e| {
+            tracing::warn!("整形エラー: {}", e);
+        })?;
+
+    tracing::info!("完了: {} → {} バイト", input_len, output.len());
+    Ok((StatusCode::OK, Json(FormatResponse { size: output.len(), output, format })))
+}
+
+#[derive(Deserialize)]
+pub struct ValidateRequest {
+    document: String,
+    schema: String,
+    // 検証する文書の形式（省略時は json、スキーマは常にJSON）
+    from: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct InferRequest {
+    examples: Vec<String>,
+    from: Option<String>,
+}
+
+fn parse_data_format(format: Option<&str>) -> Result<DataFormat, DocumentError> {
+    match format.filter(|v| !v.trim().is_empty()) {
+        Some(format) => format.parse(),
+        None => Ok(DataFormat::Json),
+    }
+}
+
+// JSON Schema（draft 2020-12）による検証のエンドポイント関数
+pub async fn validate_json(Json(request): Json<ValidateRequest>) -> Result<impl IntoResponse, ApiError> {
+    tracing::info!("開始: JSON Schema検証リクエスト受信 ({} バイト)", request.document.len());
+    let from = parse_data_format(request.from.as_deref())?;
+
+    let report = run_blocking(move || {
+        // どちらの入力の構文エラーかを file に入れて返す
+        let schema = json::parse_json(&request.schema).map_err(|e| ApiError::from(e).with_file("schema"))?;
+        let document = json::parse(&request.document, from).map_err(|e| ApiError::from(e).with_file("document"))?;
+
+        schema::validate(&schema, &document).map_err(|e| {
+            tracing::warn!("スキーマが不正です: {}", e);
+            ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, "invalid_schema", e.to_string()).with_file("schema")
+        })
+    })
+    .await??;
+
+    tracing::info!("完了: 検証結果 valid={}, 違反{}件", report.valid, report.errors.len());
+    Ok((StatusCode::OK, Json(report)))
+}
+
+// 例の文書からスキーマを推定するエンドポイント関数
+pub async fn infer_schema(Json(request): Json<InferRequest>) -> Result<impl IntoResponse, ApiError> {
+    tracing::info!("開始: スキーマ推定リクエスト受信 ({}件)", request.examples.len());
+    if request.examples.is_empty() {
+        return Err(ApiError::bad_request("no_examples", "At least one example is required"));
+    }
+    let from = parse_data_format(request.from.as_deref())?;
+
+    let schema = run_blocking(move || {
+        let examples = request
+            .examples
+            .iter()
+            .enumerate()
+            .map(|(index, example)| json::parse(example, from).map_err(|e| ApiError::from(e).with_file(format!("examples[{}]", index))))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok::<_, ApiError>(schema::infer(&examples))
+    })
+    .await??;
+    tracing::info!("完了: スキーマ推定");
+    Ok((StatusCode::OK, Json(schema)))
+}
+
+
+#[derive(Deserialize)]
+pub struct DiffRequest {
+    left: String,
+    right: String,
+    from: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct PatchRequest {
+    document: String,
+    // JSON Patch（配列）または merge patch（オブジェクト）のJSON
+    patch: String,
+    // 文書の形式（結果も同じ形式で返す）
+    from: Option<String>,
+    // "json-patch" または "merge-patch"（省略時はパッチの形から判定）
+    #[serde(rename = "type")]
+    patch_type: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct PatchResponse {
+    output: String,
+    format: DataFormat,
+    #[serde(rename = "type")]
+    patch_type: &'static str,
+    changed: bool,
+}
+
+impl From<PatchError> for ApiError {
+    fn from(error: PatchError) -> Self {
+        match &error {
+            PatchError::Invalid(_) => {
+                ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, "invalid_patch", error.to_string()).with_file("patch")
+            },
+            PatchError::Failed { operation, path, .. } => {
+                ApiError::new(StatusCode::CONFLICT, "patch_failed", error.to_string())
+                    .with_details(json!({ "operation": operation, "path": path }))
+            },
+        }
+    }
+}
+
+// 2つの文書の差分（構造的な差分・JSON Patch・merge patch）を返すエンドポイント関数
+pub async fn diff_json(Json(request): Json<DiffRequest>) -> Result<impl IntoResponse, ApiError> {
+    tracing::info!("開始: JSON差分リクエスト受信 ({} / {} バイト)", request.left.len(), request.right.len());
+    let from = parse_data_format(request.from.as_deref())?;
+
+    let report = run_blocking(move || {
+        let left = json::parse(&request.left, from).map_err(|e| ApiError::from(e).with_file("left"))?;
+        let right = json::parse(&request.right, from).map_err(|e| ApiError::from(e).with_file("right"))?;
+        Ok::<_, ApiError>(diff::diff(&left, &right))
+    })
+    .await??;
+    tracing::info!("完了: 差分{}件, パッチ{}操作", report.changes.len(), report.patch.0.len());
+    Ok((StatusCode::OK, Json(report)))
+}
+
+// 文書にパッチを適用するエンドポイント関数
+pub async fn patch_json(Json(request): Json<PatchRequest>) -> Result<impl IntoResponse, ApiError> {
+    tracing::info!("開始: JSONパッチ適用リクエスト受信 ({} バイト)", request.document.len());
+    let from = parse_data_format(request.from.as_deref())?;
+
+    let explicit_kind = match request.patch_type.as_deref().filter(|v| !v.trim().is_empty()) {
+        Some(patch_type) => Some(patch_type.parse::<PatchKind>()?),
+        None => None,
+    };
+
+    let response = run_blocking(move || {
+        let mut document = json::parse(&request.document, from).map_err(|e| ApiError::from(e).with_file("document"))?;
+        let patch = json::parse_json(&request.patch).map_err(|e| ApiError::from(e).with_file("patch"))?;
+        let kind = explicit_kind.unwrap_or_else(|| PatchKind::detect(&patch));
+
+        let original = document.clone();
+        diff::apply(&mut document, &patch, kind).inspect_err(|e| {
+            tracing::warn!("パッチ適用エラー: {}", e);
+        })?;
+
+        let options = FormatOptions { from, to: from, ..FormatOptions::default() };
+        let output = json::serialize(&document, &options)?;
+        Ok::<_, ApiError>(PatchResponse {
+            output,
+            format: from,
+            patch_type: kind.name(),
+            changed: document != original,
+        })
+    })
+    .await??;
+
+    tracing::info!("完了: {} を適用", response.patch_type);
+    Ok((StatusCode::OK, Json(response)))
+}
+
+#[derive(Deserialize)]
+pub struct QueryParams {
+    expression: String,
+    // "jsonpath" または "jq"（省略時は式の先頭が $ なら JSONPath）
+    language: Option<String>,
+    limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct QueryResponse {
+    language: QueryLanguage,
+    #[serde(flatten)]
+    result: QueryResult,
+}
+
+impl From<QueryError> for ApiError {
+    fn from(error: QueryError) -> Self {
+        match &error {
+            QueryError::Syntax { position, .. } => {
+                ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, "invalid_expression", error.to_string())
+                    .with_details(json!({ "position": position }))
+            },
+            QueryError::Runtime(_) => ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, "query_failed", error.to_string()),
+            QueryError::TooExpensive => ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, "query_too_expensive", error.to_string()),
+            QueryError::Unsupported(_) => ApiError::bad_request("invalid_option", error.to_string()),
+        }
+    }
+}
+
+// JSONPath / jq 形式の式で文書を問い合わせるエンドポイント関数
+// 文書はリクエストボディそのもので、受信しながら解析する（上限は MAX_QUERY_BYTES）
+pub async fn query_json(Query(params): Query<QueryParams>, body: Body) -> Result<impl IntoResponse, ApiError> {
+    tracing::info!("開始: JSON問い合わせリクエスト受信 ('{}')", params.expression);
+    let language = match params.language.as_deref().filter(|v| !v.trim().is_empty()) {
+        Some(language) => language.parse()?,
+        None => QueryLanguage::detect(&params.expression),
+    };
+    let limit = params.limit.unwrap_or(DEFAULT_QUERY_LIMIT).clamp(1, MAX_QUERY_LIMIT);
+
+    // DefaultBodyLimit は Body には適用されないため、受信したバイト数をここで数える
+    let max_bytes = config::get().max_query_bytes;
+    let received = Arc::new(AtomicUsize::new(0));
+    let counter = received.clone();
+    let stream = body.into_data_stream().map(move |chunk| {
+        let chunk = chunk.map_err(std::io::Error::other)?;
+        if counter.fetch_add(chunk.len(), Ordering::Relaxed) + chunk.len() > max_bytes {
+            return Err(std::io::Error::other("document too large"));
+        }
+        Ok(chunk)
+    });
+    let reader = SyncIoBridge::new(StreamReader::new(stream));
+
+    let result = run_blocking(move || {
+        let document = json::parse_json_reader(reader).map_err(|e| {
+            if received.load(Ordering::Relaxed) > max_bytes {
+                return ApiError::new(
+                    StatusCode::PAYLOAD_TOO_LARGE,
+                    "payload_too_large",
+                    format!("Document exceeds the limit of {} bytes", max_bytes),
+                );
+            }
+            ApiError::from(e).with_file("document")
+        })?;
+        tracing::info!("文書の解析完了: {} バイト", received.load(Ordering::Relaxed));
+        query::run(&document, &params.expression, language, limit).map_err(ApiError::from)
+    })
+    .await?
+    .inspect_err(|e| tracing::warn!("問い合わせエラー: {:?}", e))?;
+
+    tracing::info!("完了: {}件の結果{}", result.matches.len(), if result.truncated { "（省略あり）" } else { "" });
+    Ok((StatusCode::OK, Json(QueryResponse { language, result })))
+}
@@ -1,4 +1,12 @@
+pub mod capabilities;
+pub mod encoding;
+pub mod error;
+pub mod hash;
 pub mod images;
+pub mod jobs;
+pub mod json;
+pub mod passwords;
+pub mod pdf;
 
 pub async fn health_check() -> &'static str {
     "OK"
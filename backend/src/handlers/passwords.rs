@@ -0,0 +1,130 @@
+// handlers/passwords.rs
+use axum::{http::StatusCode, response::IntoResponse, Json};
+use serde::{Deserialize, Serialize};
+
+use crate::handlers::error::ApiError;
+use crate::services::password::{self, Generated, PassphraseOptions, PasswordOptions};
+use crate::services::strength;
+
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum GenerateMode {
+    #[default]
+    Password,
+    Passphrase,
+}
+
+// 省略した項目は PasswordOptions / PassphraseOptions のデフォルト値を使う
+#[derive(Debug, Deserialize)]
+pub struct GenerateRequest {
+    #[serde(default)]
+    mode: GenerateMode,
+    // 生成する件数
+    count: Option<usize>,
+    length: Option<usize>,
+    lowercase: Option<bool>,
+    uppercase: Option<bool>,
+    digits: Option<bool>,
+    symbols: Option<bool>,
+    #[serde(default)]
+    exclude_ambiguous: bool,
+    min_lowercase: Option<usize>,
+    min_uppercase: Option<usize>,
+    min_digits: Option<usize>,
+    min_symbols: Option<usize>,
+    words: Option<usize>,
+    separator: Option<String>,
+    #[serde(default)]
+    capitalize: bool,
+    #[serde(default)]
+    include_number: bool,
+}
+
+#[derive(Serialize)]
+pub struct GenerateResponse {
+    mode: GenerateMode,
+    results: Vec<Generated>,
+}
+
+fn password_options(request: &GenerateRequest) -> PasswordOptions {
+    let defaults = PasswordOptions::default();
+    let lowercase = request.lowercase.unwrap_or(defaults.lowercase);
+    let uppercase = request.uppercase.unwrap_or(defaults.uppercase);
+    let digits = request.digits.unwrap_or(defaults.digits);
+    let symbols = request.symbols.unwrap_or(defaults.symbols);
+    PasswordOptions {
+        length: request.length.unwrap_or(defaults.length),
+        lowercase,
+        uppercase,
+        digits,
+        symbols,
+        exclude_ambiguous: request.exclude_ambiguous,
+        // 無効な文字種の最低文字数は数えない
+        min_lowercase: if lowercase { request.min_lowercase.unwrap_or(defaults.min_lowercase) } else { 0 },
+        min_uppercase: if uppercase { request.min_uppercase.unwrap_or(defaults.min_uppercase) } else { 0 },
+        min_digits: if digits { request.min_digits.unwrap_or(defaults.min_digits) } else { 0 },
+        min_symbols: if symbols { request.min_symbols.unwrap_or(defaults.min_symbols) } else { 0 },
+    }
+}
+
+fn passphrase_options(request: &GenerateRequest) -> PassphraseOptions {
+    let defaults = PassphraseOptions::default();
+    PassphraseOptions {
+        words: request.words.unwrap_or(defaults.words),
+        separator: request.separator.clone().unwrap_or(defaults.separator),
+        capitalize: request.capitalize,
+        include_number: request.include_number,
+    }
+}
+
+// パスワード・パスフレーズ生成のエンドポイント関数（生成した値はログに出さない）
+pub async fn generate_passwords(Json(request): Json<GenerateRequest>) -> Result<impl IntoResponse, ApiError> {
+    let count = request.count.unwrap_or(1);
+    if count == 0 || count > password::MAX_COUNT {
+        return Err(ApiError::bad_request("invalid_option", format!("Count must be between 1 and {}", password::MAX_COUNT)));
+    }
+
+    let results = match request.mode {
+        GenerateMode::Password => {
+            let options = password_options(&request);
+            tracing::info!("開始: パスワード生成 {}件, {:?}", count, options);
+            (0..count).map(|_| password::generate_password(&options)).collect::<anyhow::Result<Vec<_>>>()
+        },
+        GenerateMode::Passphrase => {
+            let options = passphrase_options(&request);
+            tracing::info!("開始: パスフレーズ生成 {}件, {:?}", count, options);
+            (0..count).map(|_| password::generate_passphrase(&options)).collect::<anyhow::Result<Vec<_>>>()
+        },
+    }
+    .map_err(|e| {
+        tracing::warn!("生成設定が不正です: {}", e);
+        ApiError::bad_request("invalid_option", e.to_string())
+    })?;
+
+    tracing::info!("完了: {}件生成", results.len());
+    Ok((StatusCode::OK, Json(GenerateResponse { mode: request.mode, results })))
+}
+
+// Debug を実装しない（パスワードがログに出ないようにする）
+#[derive(Deserialize)]
+pub struct AnalyzeRequest {
+    password: String,
+    // ユーザー名・メールアドレスなど、パスワードに含めるべきでない語
+    #[serde(default)]
+    user_inputs: Vec<String>,
+}
+
+// パスワード強度分析のエンドポイント関数（入力されたパスワード・user_inputs はログに出さない）
+pub async fn analyze_password(Json(request): Json<AnalyzeRequest>) -> Result<impl IntoResponse, ApiError> {
+    tracing::info!("開始: パスワード強度分析");
+    if request.password.chars().count() > strength::MAX_PASSWORD_CHARS {
+        return Err(ApiError::bad_request(
+            "invalid_option",
+            format!("Password must be at most {} characters", strength::MAX_PASSWORD_CHARS),
+        ));
+    }
+
+    let analysis = strength::analyze(&request.password, &request.user_inputs);
+    tracing::info!("完了: スコア {}", analysis.score);
+    Ok((StatusCode::OK, Json(analysis)))
+}
@@ -0,0 +1,391 @@
+// handlers/pdf.rs
+use axum::{
+    extract::Multipart,
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use base64::Engine;
+use serde::Serialize;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::handlers::error::ApiError;
+use crate::handlers::images::{file_extension, processing_error, read_image_form, read_upload_form, run_blocking};
+use crate::services::{converter, pdf};
+
+// レンダリングされたページ1件分
+#[derive(Serialize)]
+pub struct RenderedPageFile {
+    page: u32,
+    name: String,
+    url: String,
+    size: usize,
+    width: u32,
+    height: u32,
+}
+
+#[derive(Serialize)]
+pub struct RenderResponse {
+    original_name: String,
+    pages: Vec<RenderedPageFile>,
+}
+
+// 作成されたPDF1件分
+#[derive(Serialize)]
+pub struct PdfFile {
+    name: String,
+    url: String,
+    size: usize,
+    pages: usize,
+}
+
+#[derive(Serialize)]
+pub struct PdfResponse {
+    original_name: String,
+    files: Vec<PdfFile>,
+}
+
+// 圧縮したPDF（画像圧縮の CompressedFile と同じ項目に統計を追加）
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompressedPdf {
+    original_name: String,
+    name: String,
+    url: String,
+    original_size: usize,
+    compressed_size: usize,
+    compression_ratio: f32,
+    images_recompressed: usize,
+    objects_removed: usize,
+}
+
+#[derive(Serialize)]
+pub struct PdfCompressionResponse {
+    files: Vec<CompressedPdf>,
+}
+
+// アップロードされたPDFをブロッキングスレッドで読み込む
+async fn load_upload(file_name: &str, data: Vec<u8>) -> Result<lopdf::Document, ApiError> {
+    run_blocking(move || pdf::load_document(&data)).await?.map_err(|e| {
+        tracing::error!("PDF読み込みエラー - ファイル: '{}', エラー: {:?}", file_name, e);
+        processing_error(e, StatusCode::UNPROCESSABLE_ENTITY, "decode_failed", file_name)
+    })
+}
+
+fn pdf_file(data: &[u8], pages: usize) -> PdfFile {
+    PdfFile {
+        name: format!("{}.pdf", Uuid::new_v4()),
+        url: format!("data:application/pdf;base64,{}", base64::engine::general_purpose::STANDARD.encode(data)),
+        size: data.len(),
+        pages,
+    }
+}
+
+fn pdf_failed(e: anyhow::Error) -> ApiError {
+    tracing::error!("PDF処理エラー: {:?}", e);
+    ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, "pdf_failed", e.to_string())
+}
+
+// 1ファイルのみを受け付けるエンドポイント用に、最初のPDFを取り出す
+async fn single_upload(files: Vec<(String, Vec<u8>)>) -> Result<(String, lopdf::Document), ApiError> {
+    let Some((file_name, data)) = files.into_iter().next() else {
+        return Err(ApiError::bad_request("no_files", "No PDF was uploaded"));
+    };
+    let doc = load_upload(&file_name, data).await?;
+    Ok((file_name, doc))
+}
+
+// ページ設定をフォームのフィールドから組み立てる
+fn parse_page_options(fields: &HashMap<String, String>) -> anyhow::Result<pdf::PageOptions> {
+    let mut options = pdf::PageOptions::default();
+    let value = |key: &str| fields.get(key).map(|v| v.trim()).filter(|v| !v.is_empty());
+
+    if let Some(size) = value("page_size") {
+        options.size = size.parse()?;
+    }
+    if let Some(orientation) = value("orientation") {
+        options.orientation = orientation.parse()?;
+    }
+    if let Some(fit) = value("fit") {
+        options.fit = fit.parse()?;
+    }
+    if let Some(margin) = value("margin") {
+        options.margin_mm = margin
+            .parse::<f32>()
+            .ok()
+            .filter(|m| m.is_finite() && *m >= 0.0)
+            .ok_or_else(|| anyhow::anyhow!("Invalid margin: {}", margin))?;
+    }
+    Ok(options)
+}
+
+// 複数の画像を1つのPDFにまとめるエンドポイント関数（アップロード順にページを並べる）
+pub async fn images_to_pdf(multipart: Multipart) -> Result<impl IntoResponse, ApiError> {
+    tracing::info!("開始: 画像→PDF変換リクエスト受信");
+    let form = read_image_form(multipart).await?;
+
+    let options = parse_page_options(&form.fields).map_err(|e| {
+        tracing::warn!("ページ設定が不正です: {}", e);
+        ApiError::bad_request("invalid_option", e.to_string())
+    })?;
+    tracing::info!("ページ設定: {:?}", options);
+
+    if form.files.is_empty() {
+        return Err(ApiError::bad_request("no_files", "No images were uploaded"));
+    }
+
+    // 画像の読み込みとPDFの作成はブロッキングスレッドで行う
+    let page_count = form.files.len();
+    let document = run_blocking(move || {
+        let mut images = Vec::with_capacity(form.files.len());
+        for (file_name, data) in &form.files {
+            tracing::info!("画像の読み込み: {}", file_name);
+            match pdf::PdfImage::from_upload(data, &file_extension(file_name)) {
+                Ok(image) => images.push(image),
+                Err(e) => {
+                    tracing::error!("デコードエラー - ファイル: '{}', エラー: {:?}", file_name, e);
+                    return Err(processing_error(e, StatusCode::UNPROCESSABLE_ENTITY, "decode_failed", file_name));
+                }
+            }
+        }
+
+        pdf::images_to_pdf(&images, &options).map_err(|e| {
+            tracing::error!("PDF作成エラー: {:?}", e);
+            ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, "pdf_failed", e.to_string())
+        })
+    })
+    .await??;
+
+    tracing::info!("完了: {}ページのPDFを作成 ({} バイト)", page_count, document.len());
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "application/pdf"),
+            (header::CONTENT_DISPOSITION, "attachment; filename=\"images.pdf\""),
+        ],
+        document,
+    ))
+}
+
+// PDFのページを画像にレンダリングするエンドポイント関数
+pub async fn pdf_to_images(multipart: Multipart) -> Result<impl IntoResponse, ApiError> {
+    tracing::info!("開始: PDF→画像変換リクエスト受信");
+    let form = read_upload_form(multipart).await?;
+
+    let mut options = pdf::RenderOptions::default();
+    if let Some(format) = form.fields.get("format").map(|v| v.trim()).filter(|v| !v.is_empty()) {
+        if !matches!(converter::extension_for(format), "png" | "jpg") {
+            return Err(ApiError::bad_request("unsupported_format", format!("Unsupported output format: {}", format)));
+        }
+        options.format = format.to_string();
+    }
+    if let Some(dpi) = form.fields.get("dpi").map(|v| v.trim()).filter(|v| !v.is_empty()) {
+        options.dpi = dpi
+            .parse::<u32>()
+            .ok()
+            .filter(|d| (1..=pdf::MAX_RENDER_DPI).contains(d))
+            .ok_or_else(|| ApiError::bad_request(
+                "invalid_option",
+                format!("DPI must be between 1 and {}: {}", pdf::MAX_RENDER_DPI, dpi),
+            ))?;
+    }
+
+    // PDFは1ファイルのみ
+    let Some((file_name, data)) = form.files.into_iter().next() else {
+        return Err(ApiError::bad_request("no_files", "No PDF was uploaded"));
+    };
+
+    if let Some(range) = form.fields.get("pages").map(|v| v.trim()).filter(|v| !v.is_empty()) {
+        let page_count = load_upload(&file_name, data.clone()).await?.get_pages().len() as u32;
+        options.pages = Some(
+            pdf::parse_page_range(range, page_count)
+                .map_err(|e| ApiError::bad_request("invalid_page_range", e.to_string()).with_file(file_name.clone()))?,
+        );
+    }
+    tracing::info!("レンダリング設定: {:?}", options);
+
+    let render_options = options.clone();
+    let pages = run_blocking(move || pdf::render_pages(&data, &render_options)).await?.map_err(|e| {
+        tracing::error!("レンダリングエラー - ファイル: '{}', エラー: {:?}", file_name, e);
+        processing_error(e, StatusCode::UNPROCESSABLE_ENTITY, "render_failed", &file_name)
+    })?;
+
+    let extension = converter::extension_for(&options.format);
+    let mime_type = converter::mime_type_for(&options.format);
+    let pages = pages
+        .into_iter()
+        .map(|page| RenderedPageFile {
+            name: format!("page-{}-{}.{}", page.page, Uuid::new_v4(), extension),
+            url: format!("data:{};base64,{}", mime_type, base64::engine::general_purpose::STANDARD.encode(&page.data)),
+            size: page.data.len(),
+            page: page.page,
+            width: page.width,
+            height: page.height,
+        })
+        .collect::<Vec<_>>();
+
+    tracing::info!("完了: {}ページをレンダリング", pages.len());
+    Ok((StatusCode::OK, Json(RenderResponse { original_name: file_name, pages })))
+}
+
+// PDF圧縮のエンドポイント関数
+pub async fn compress_pdf(multipart: Multipart) -> Result<impl IntoResponse, ApiError> {
+    tracing::info!("開始: PDF圧縮リクエスト受信");
+    let form = read_upload_form(multipart).await?;
+
+    // 品質を1-100の範囲で解析（画像圧縮と同じデフォルト値）
+    let quality = form.fields.get("quality").and_then(|v| v.trim().parse::<i32>().ok()).unwrap_or(60).clamp(1, 100);
+    tracing::info!("適用される圧縮品質: {}", quality);
+
+    let mut result = Vec::<CompressedPdf>::new();
+    for (file_name, data) in form.files {
+        tracing::info!("ファイルの圧縮開始: {}", file_name);
+        let original_size = data.len();
+
+        match run_blocking(move || pdf::compress(&data, quality)).await? {
+            Ok((output, stats)) => {
+                let compressed_size = output.len();
+                let compression_ratio = if original_size > 0 {
+                    compressed_size as f32 / original_size as f32
+                } else {
+                    1.0
+                };
+                tracing::info!("圧縮成功: {} ({} → {} バイト)", file_name, original_size, compressed_size);
+                result.push(CompressedPdf {
+                    original_name: file_name,
+                    name: format!("compressed-{}.pdf", Uuid::new_v4()),
+                    url: format!("data:application/pdf;base64,{}", base64::engine::general_purpose::STANDARD.encode(&output)),
+                    original_size,
+                    compressed_size,
+                    compression_ratio,
+                    images_recompressed: stats.images_recompressed,
+                    objects_removed: stats.objects_removed,
+                });
+            },
+            Err(e) => {
+                tracing::error!("圧縮エラー - ファイル: '{}', 品質: {}, エラー: {:?}", file_name, quality, e);
+                result.push(CompressedPdf {
+                    original_name: file_name,
+                    name: format!("error-{}", Uuid::new_v4()),
+                    url: format!("error:{}", e),
+                    original_size,
+                    compressed_size: 0,
+                    compression_ratio: 1.0,
+                    images_recompressed: 0,
+                    objects_removed: 0,
+                });
+            }
+        }
+    }
+
+    tracing::info!("完了: {}ファイルを処理", result.len());
+    Ok((StatusCode::OK, Json(PdfCompressionResponse { files: result })))
+}
+
+// 複数のPDFをアップロード順に結合するエンドポイント関数
+pub async fn merge_pdf(multipart: Multipart) -> Result<impl IntoResponse, ApiError> {
+    tracing::info!("開始: PDF結合リクエスト受信");
+    let form = read_upload_form(multipart).await?;
+    if form.files.len() < 2 {
+        return Err(ApiError::bad_request("no_files", "At least two PDFs are required"));
+    }
+
+    let original_name = form.files[0].0.clone();
+    let mut documents = Vec::with_capacity(form.files.len());
+    for (file_name, data) in form.files {
+        documents.push(load_upload(&file_name, data).await?);
+    }
+    let pages = documents.iter().map(|doc| doc.get_pages().len()).sum();
+
+    let file_count = documents.len();
+    let merged = run_blocking(move || pdf::merge(&documents)).await?.map_err(pdf_failed)?;
+    tracing::info!("完了: {}ファイルを結合 ({}ページ)", file_count, pages);
+    Ok((StatusCode::OK, Json(PdfResponse { original_name, files: vec![pdf_file(&merged, pages)] })))
+}
+
+// ページ範囲ごとにPDFを分割するエンドポイント関数（範囲の指定がなければ1ページずつ）
+pub async fn split_pdf(multipart: Multipart) -> Result<impl IntoResponse, ApiError> {
+    tracing::info!("開始: PDF分割リクエスト受信");
+    let form = read_upload_form(multipart).await?;
+    let (file_name, doc) = single_upload(form.files).await?;
+    let page_count = doc.get_pages().len() as u32;
+
+    let ranges = match form.fields.get("ranges").map(|v| v.trim()).filter(|v| !v.is_empty()) {
+        Some(ranges) => pdf::parse_split_ranges(ranges, page_count)
+            .map_err(|e| ApiError::bad_request("invalid_page_range", e.to_string()).with_file(file_name.clone()))?,
+        None => (1..=page_count).map(|page| vec![page]).collect(),
+    };
+    tracing::info!("分割範囲: {:?}", ranges);
+
+    let split_ranges = ranges.clone();
+    let documents = run_blocking(move || pdf::split(&doc, &split_ranges))
+        .await?
+        .map_err(|e| pdf_failed(e).with_file(file_name.clone()))?;
+    let files = documents
+        .iter()
+        .zip(&ranges)
+        .map(|(data, pages)| pdf_file(data, pages.len()))
+        .collect::<Vec<_>>();
+
+    tracing::info!("完了: {}ファイルに分割", files.len());
+    Ok((StatusCode::OK, Json(PdfResponse { original_name: file_name, files })))
+}
+
+// ページを回転するエンドポイント関数（ページの指定がなければ全ページ）
+pub async fn rotate_pdf(multipart: Multipart) -> Result<impl IntoResponse, ApiError> {
+    tracing::info!("開始: PDF回転リクエスト受信");
+    let form = read_upload_form(multipart).await?;
+
+    let angle = form
+        .fields
+        .get("angle")
+        .and_then(|v| v.trim().parse::<i64>().ok())
+        .filter(|angle| angle % 90 == 0)
+        .ok_or_else(|| ApiError::bad_request("invalid_option", "Rotation angle must be a multiple of 90"))?;
+
+    let (file_name, doc) = single_upload(form.files).await?;
+    let page_count = doc.get_pages().len() as u32;
+    let pages = match form.fields.get("pages").map(|v| v.trim()).filter(|v| !v.is_empty()) {
+        Some(range) => pdf::parse_page_range(range, page_count)
+            .map_err(|e| ApiError::bad_request("invalid_page_range", e.to_string()).with_file(file_name.clone()))?,
+        None => (1..=page_count).collect(),
+    };
+    tracing::info!("回転: {}度, ページ: {:?}", angle, pages);
+
+    let rotated_count = pages.len();
+    let rotated = run_blocking(move || pdf::rotate(&doc, &pages, angle))
+        .await?
+        .map_err(|e| pdf_failed(e).with_file(file_name.clone()))?;
+    tracing::info!("完了: {}ページを回転", rotated_count);
+    Ok((StatusCode::OK, Json(PdfResponse { original_name: file_name, files: vec![pdf_file(&rotated, page_count as usize)] })))
+}
+
+// ページを並べ替えるエンドポイント関数（order に含まれないページは削除される）
+pub async fn reorder_pdf(multipart: Multipart) -> Result<impl IntoResponse, ApiError> {
+    tracing::info!("開始: PDF並べ替えリクエスト受信");
+    let form = read_upload_form(multipart).await?;
+    let (file_name, doc) = single_upload(form.files).await?;
+    let page_count = doc.get_pages().len() as u32;
+
+    let order = form
+        .fields
+        .get("order")
+        .map(|v| v.trim())
+        .filter(|v| !v.is_empty())
+        .ok_or_else(|| ApiError::bad_request("invalid_page_range", "Page order is required"))
+        .and_then(|order| {
+            pdf::parse_page_range(order, page_count)
+                .map_err(|e| ApiError::bad_request("invalid_page_range", e.to_string()))
+        })
+        .map_err(|e| e.with_file(file_name.clone()))?;
+    tracing::info!("並べ替え: {:?}", order);
+
+    let page_count = order.len();
+    let reordered = run_blocking(move || pdf::reorder(&doc, &order))
+        .await?
+        .map_err(|e| pdf_failed(e).with_file(file_name.clone()))?;
+    tracing::info!("完了: {}ページを並べ替え", page_count);
+    Ok((StatusCode::OK, Json(PdfResponse { original_name: file_name, files: vec![pdf_file(&reordered, page_count)] })))
+}
+
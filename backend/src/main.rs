@@ -11,6 +11,7 @@ use tower_http::cors::{Any, CorsLayer};
 use tracing::{Level, info};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, filter::EnvFilter};
 
+mod config;
 mod handlers;
 mod services;
 
@@ -34,6 +35,16 @@ async fn main() {
 
     info!("QuickToolify バックエンドサーバー起動中...");
 
+    // サーバー設定の読み込み
+    let config = config::init();
+    info!("サーバー設定: {:?}", config);
+    services::limits::apply_magick_limits();
+
+    // ImageMagickのデリゲート対応状況を確認
+    for delegate in services::formats::magick_delegates() {
+        info!("ImageMagick {}: 読み込み={}, 書き出し={}", delegate.format, delegate.read, delegate.write);
+    }
+
     // CORSの設定
     let origins_str = env::var("ALLOWED_ORIGINS")
         .unwrap_or_else(|e| {
@@ -62,9 +73,39 @@ async fn main() {
     // ルーティングの設定
     let app = Router::new()
         .route("/", get(handlers::health_check))
+        .route("/capabilities", get(handlers::capabilities::get_capabilities))
         .route("/convert/images", post(handlers::images::convert_image))
         .route("/compress/images", post(handlers::images::compress_image))
-        .layer(DefaultBodyLimit::max(20 * 1024 * 1024))
+        .route("/edit/images", post(handlers::images::edit_image))
+        .route("/thumbnails/images", post(handlers::images::generate_thumbnails))
+        .route("/responsive/images", post(handlers::images::generate_responsive_images))
+        .route("/icons/images", post(handlers::images::generate_icons))
+        .route("/rasterize/svg", post(handlers::images::rasterize_svg))
+        .route("/optimize/svg", post(handlers::images::optimize_svg))
+        .route("/inline/images", post(handlers::images::inline_images))
+        .route("/jobs", post(handlers::jobs::create_job))
+        .route("/jobs/:id", get(handlers::jobs::get_job))
+        .route("/jobs/:id/cancel", post(handlers::jobs::cancel_job))
+        .route("/jobs/:id/events", get(handlers::jobs::job_events))
+        .route("/pdf/from-images", post(handlers::pdf::images_to_pdf))
+        .route("/pdf/to-images", post(handlers::pdf::pdf_to_images))
+        .route("/pdf/compress", post(handlers::pdf::compress_pdf))
+        .route("/pdf/merge", post(handlers::pdf::merge_pdf))
+        .route("/pdf/split", post(handlers::pdf::split_pdf))
+        .route("/pdf/rotate", post(handlers::pdf::rotate_pdf))
+        .route("/pdf/reorder", post(handlers::pdf::reorder_pdf))
+        .route("/json/format", post(handlers::json::format_json))
+        .route("/json/validate", post(handlers::json::validate_json))
+        .route("/json/schema", post(handlers::json::infer_schema))
+        .route("/json/diff", post(handlers::json::diff_json))
+        .route("/json/patch", post(handlers::json::patch_json))
+        .route("/json/query", post(handlers::json::query_json))
+        .route("/passwords/generate", post(handlers::passwords::generate_passwords))
+        .route("/passwords/analyze", post(handlers::passwords::analyze_password))
+        .route("/hash", post(handlers::hash::hash_files))
+        .route("/encode", post(handlers::encoding::encode))
+        .route("/decode", post(handlers::encoding::decode))
+        .layer(DefaultBodyLimit::max(config.max_body_bytes))
         .layer(cors);
 
     info!("ルーティング設定完了");
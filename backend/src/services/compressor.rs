@@ -1,104 +1,138 @@
 use anyhow::{Result, anyhow};
-use std::path::Path;
-use image::{GenericImageView};
+use image::{DynamicImage, GenericImageView};
 use mozjpeg::{Compress, ColorSpace};
 use magick_rust::{MagickWand};
 
-pub fn compress_image(input: &str, output: &str, quality: i32) -> Result<()> {
-    tracing::debug!("圧縮開始: {} → {} (品質: {}%)", input, output, quality);
+use crate::services::converter::{self, Source, Stage};
+use crate::services::formats;
 
-    let input_ext = Path::new(input)
-        .extension()
-        .and_then(|ext| ext.to_str())
-        .unwrap_or("")
-        .to_lowercase();
+// 画像を圧縮して出力データを返す（形式は変えない、デコード・エンコードを始めるたびに on_stage を呼び出す）
+pub fn compress(source: Source, input_ext: &str, quality: i32, on_stage: &dyn Fn(Stage)) -> Result<Vec<u8>> {
     tracing::debug!("入力ファイル拡張子: {}", input_ext);
 
     if input_ext == "jpg" || input_ext == "jpeg" {
         // ==== JPEG の圧縮を `mozjpeg` に変更 ====
         tracing::debug!("JPEG圧縮最適化適用 (mozjpeg 使用)");
 
-        let img = image::open(input).map_err(|e| anyhow!("JPEG 画像読み込みエラー: {}", e))?;
-        let (width, height) = img.dimensions();
-        let rgb = img.to_rgb8();
-        let raw_data = rgb.as_raw(); // RGBデータを取得
+        on_stage(Stage::Decoding);
+        let img = source.decode(input_ext).map_err(|e| anyhow!("JPEG 画像読み込みエラー: {}", e))?;
+        on_stage(Stage::Encoding);
+        return encode_jpeg(&img, quality);
+    }
 
-        let mut comp = Compress::new(ColorSpace::JCS_RGB);
-        comp.set_size(width as usize, height as usize);
-        comp.set_quality(quality as f32);
-        comp.set_progressive_mode(); // Progressive JPEG を有効化
-        comp.set_optimize_scans(true); // Huffman テーブルの最適化
+    // ==== PNG / WebP の処理は `magick_rust` を使う ====
+    tracing::debug!("ImageMagick を使用した圧縮処理を適用");
 
-        // `start_compress()` に `Vec<u8>` を渡して開始
-        let mut writer = comp.start_compress(Vec::new())?;
+    let mut wand = MagickWand::new();
+    on_stage(Stage::Decoding);
+    if let Err(e) = source.read_into(&wand) {
+        tracing::error!("ImageMagick で画像読み込みエラー: {:?}", e);
+        return Err(anyhow!("ImageMagick 画像読み込み失敗: {}", e));
+    }
+    on_stage(Stage::Encoding);
 
-        // 画像データを `write_scanlines()` で書き込む
-        writer.write_scanlines(raw_data)?;
+    // 圧縮品質を適用
+    if let Err(e) = wand.set_compression_quality(quality as usize) {
+        tracing::warn!("圧縮品質設定エラー: {:?}", e);
+    }
 
-        // `finish()` の結果を取得
-        let jpeg_data = writer.finish()?;
+    match input_ext {
+        "png" => {
+            tracing::debug!("PNG最適化設定適用");
+
+            let _ = wand.set_compression_quality(60 as usize);
+            let _ = wand.set_image_compression(magick_rust::CompressionType::Zip);
+
+            if quality < 80 {
+                let _ = wand.quantize_image(
+                    256,
+                    magick_rust::ColorspaceType::RGB,
+                    0,
+                    magick_rust::DitherMethod::FloydSteinberg,
+                    false
+                );
+            }
 
-        std::fs::write(output, jpeg_data).map_err(|e| anyhow!("JPEG保存エラー: {}", e))?;
-    } else {
-        // ==== PNG / WebP の処理は `magick_rust` を使う ====
-        tracing::debug!("ImageMagick を使用した圧縮処理を適用");
+            let _ = wand.set_image_property("png:compression-filter", "5");
+            let _ = wand.set_image_property("png:compression-level", "9");
+            let _ = wand.set_image_property("png:compression-strategy", "2");
 
-        let mut wand = MagickWand::new();
-        if let Err(e) = wand.read_image(input) {
-            tracing::error!("ImageMagick で画像読み込みエラー: {:?}", e);
-            return Err(anyhow!("ImageMagick 画像読み込み失敗: {}", e));
+            if quality < 90 {
+                let _ = wand.set_image_type(magick_rust::ImageType::Palette);
+            }
+        },
+        "webp" => {
+            tracing::debug!("WebP最適化設定適用");
+            let _ = wand.set_image_property("webp:lossless", "false");
+            let _ = wand.set_image_property("webp:method", "6");
+        },
+        _ => {
+            tracing::debug!("一般的な圧縮設定を適用");
         }
+    }
 
-        // 圧縮品質を適用
-        if let Err(e) = wand.set_compression_quality(quality as usize) {
-            tracing::warn!("圧縮品質設定エラー: {:?}", e);
+    // メタデータ削除
+    let _ = wand.strip_image();
+
+    // 読み込んだ形式のまま書き出す
+    let format = wand.get_image_format().ok().filter(|f| !f.is_empty()).unwrap_or_else(|| input_ext.to_uppercase());
+    match wand.write_image_blob(&format) {
+        Ok(data) => {
+            tracing::debug!("圧縮画像の書き出し成功: {} バイト", data.len());
+            Ok(data)
+        },
+        Err(e) => {
+            tracing::error!("圧縮画像の保存エラー: {:?}", e);
+            Err(anyhow!("Failed to save compressed image: {}", e))
         }
+    }
+}
 
-        match input_ext.as_str() {
-            "png" => {
-                tracing::debug!("PNG最適化設定適用");
-
-                let _ = wand.set_compression_quality(60 as usize);
-                let _ = wand.set_image_compression(magick_rust::CompressionType::Zip);
-
-                if quality < 80 {
-                    let _ = wand.quantize_image(
-                        256,
-                        magick_rust::ColorspaceType::RGB,
-                        0,
-                        magick_rust::DitherMethod::FloydSteinberg,
-                        false
-                    );
-                }
-
-                let _ = wand.set_image_property("png:compression-filter", "5");
-                let _ = wand.set_image_property("png:compression-level", "9");
-                let _ = wand.set_image_property("png:compression-strategy", "2");
-
-                if quality < 90 {
-                    let _ = wand.set_image_type(magick_rust::ImageType::Palette);
-                }
-            },
-            "webp" => {
-                tracing::debug!("WebP最適化設定適用");
-                let _ = wand.set_image_property("webp:lossless", "false");
-                let _ = wand.set_image_property("webp:method", "6");
-            },
-            _ => {
-                tracing::debug!("一般的な圧縮設定を適用");
-            }
-        }
+// `mozjpeg` でJPEGにエンコード（グレースケール画像はグレースケールのまま）
+pub fn encode_jpeg(img: &DynamicImage, quality: i32) -> Result<Vec<u8>> {
+    let (width, height) = img.dimensions();
+    let (color_space, raw_data) = match img {
+        DynamicImage::ImageLuma8(gray) => (ColorSpace::JCS_GRAYSCALE, gray.as_raw().clone()),
+        _ => (ColorSpace::JCS_RGB, img.to_rgb8().into_raw()), // RGBデータを取得
+    };
 
-        // メタデータ削除
-        let _ = wand.strip_image();
+    let mut comp = Compress::new(color_space);
+    comp.set_size(width as usize, height as usize);
+    comp.set_quality(quality as f32);
+    comp.set_progressive_mode(); // Progressive JPEG を有効化
+    comp.set_optimize_scans(true); // Huffman テーブルの最適化
 
-        // 保存処理
-        if let Err(e) = wand.write_image(output) {
-            tracing::error!("圧縮画像の保存エラー: {:?}", e);
-            return Err(anyhow!("Failed to save compressed image: {}", e));
-        }
+    // `start_compress()` に `Vec<u8>` を渡して開始
+    let mut writer = comp.start_compress(Vec::new())?;
+
+    // 画像データを `write_scanlines()` で書き込む
+    writer.write_scanlines(&raw_data)?;
+
+    // `finish()` の結果を取得
+    Ok(writer.finish()?)
+}
+
+// ImageMagick で非可逆の WebP にエンコード（image クレートの WebP エンコーダーは可逆のみ）
+// ImageMagick に WebP のデリゲートがない場合は可逆で出力する
+pub fn encode_webp(img: &DynamicImage, quality: i32) -> Result<Vec<u8>> {
+    if !formats::magick_supports("WEBP", true) {
+        tracing::warn!("ImageMagickでWebPを書き出せないため可逆で出力します");
+        return converter::encode_image(img, "webp");
+    }
+
+    let png = converter::encode_image(img, "png")?;
+    let wand = MagickWand::new();
+    wand.read_image_blob(&png)
+        .map_err(|e| anyhow!("Failed to read image with ImageMagick: {}", e))?;
+    wand.set_image_format("WEBP")
+        .map_err(|e| anyhow!("Failed to set image format: {}", e))?;
+    if let Err(e) = wand.set_compression_quality(quality.clamp(1, 100) as usize) {
+        tracing::warn!("圧縮品質設定エラー: {:?}", e);
     }
+    let _ = wand.set_image_property("webp:lossless", "false");
+    let _ = wand.set_image_property("webp:method", "6");
+    let _ = wand.strip_image();
 
-    tracing::debug!("圧縮画像の保存成功: {}", output);
-    Ok(())
+    wand.write_image_blob("WEBP")
+        .map_err(|e| anyhow!("Failed to save image with ImageMagick: {}", e))
 }
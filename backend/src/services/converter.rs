@@ -1,30 +1,107 @@
 use anyhow::{Result, anyhow};
-use std::path::Path;
-use image::{self, ImageFormat};
-use magick_rust::{MagickWand};
-
-pub fn convert_image(input: &str, output: &str, format: &str) -> Result<()> {
-    tracing::debug!("変換開始: {} → {} ({}形式)", input, output, format);
-
-    let input_ext = Path::new(input)
-        .extension()
-        .and_then(|ext| ext.to_str())
-        .unwrap_or("")
-        .to_lowercase();
+use std::borrow::Cow;
+use std::io::Cursor;
+use image::{self, DynamicImage, ImageFormat, ImageOutputFormat};
+use image::imageops::FilterType;
+use magick_rust::{CompressionType, MagickWand};
+
+use crate::services::formats::{self, Backend, OutputFormat};
+use crate::services::limits;
+use crate::services::svg;
+
+// 変換オプション
+#[derive(Debug, Clone, Default)]
+pub struct ConvertOptions {
+    // TIFFの圧縮方式（lzw / zip / jpeg / none）
+    pub compression: Option<String>,
+    // 非可逆形式の品質（未指定の場合はフォーマットの既定値）
+    pub quality: Option<u8>,
+}
+
+// 変換の段階（ジョブの進捗通知に使う）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stage {
+    Decoding,
+    Encoding,
+}
+
+// 変換・圧縮の入力（通常はメモリ上のデータ、大きなファイルは一時ファイルのパス）
+#[derive(Debug, Clone, Copy)]
+pub enum Source<'a> {
+    Memory(&'a [u8]),
+    File(&'a str),
+}
+
+impl Source<'_> {
+    // imageクレートでデコード（image::open と同じく拡張子から形式を決め、不明な場合は内容から推定）
+    // ヘッダーのサイズがデコードの上限を超える場合は展開しない
+    pub fn decode(&self, input_ext: &str) -> Result<DynamicImage> {
+        match self {
+            Source::Memory(data) => limits::decode_memory(data, ImageFormat::from_extension(input_ext)),
+            Source::File(path) => limits::decode_file(path),
+        }
+    }
+
+    // ImageMagickで読み込む（先にサイズだけを読んで上限を確認する）
+    pub fn read_into(&self, wand: &MagickWand) -> Result<()> {
+        match self {
+            Source::Memory(data) => {
+                limits::ping_magick(|wand| wand.ping_image_blob(data))?;
+                wand.read_image_blob(data)?;
+            },
+            Source::File(path) => {
+                limits::ping_magick(|wand| wand.ping_image(path))?;
+                wand.read_image(path)?;
+            },
+        }
+        Ok(())
+    }
+
+    fn bytes(&self) -> std::io::Result<Cow<'_, [u8]>> {
+        match self {
+            Source::Memory(data) => Ok(Cow::Borrowed(data)),
+            Source::File(path) => std::fs::read(path).map(Cow::Owned),
+        }
+    }
+}
+
+// 画像を変換して出力データを返す（デコード・エンコードを始めるたびに on_stage を呼び出す）
+pub fn convert(
+    source: Source,
+    input_ext: &str,
+    format: &str,
+    options: &ConvertOptions,
+    on_stage: &dyn Fn(Stage),
+) -> Result<Vec<u8>> {
     tracing::debug!("入力ファイル拡張子: {}", input_ext);
 
-    // HEIC、AVIFの場合、またはこれらの形式に変換する場合はImageMagickを使用
-    if input_ext == "heic" || input_ext == "avif" || format == "avif" {
+    // 入力・出力・オプションの組み合わせを事前に検証
+    let spec = formats::check_conversion(input_ext, format, options.compression.as_deref())?;
+
+    // SVGはresvgでラスタライズしてからエンコード
+    let input_backend = formats::input_format(input_ext).map(|f| f.decoder);
+    if input_backend == Some(Backend::Resvg) {
+        tracing::info!("resvgを使用して変換します: {} -> {}", input_ext, format);
+        on_stage(Stage::Decoding);
+        let data = source.bytes().map_err(|e| anyhow!("Failed to read SVG: {}", e))?;
+        let img = svg::rasterize(&data, &svg::RasterizeOptions::default())?;
+        on_stage(Stage::Encoding);
+        return encode_image_with_options(&img, spec.id, options);
+    }
+
+    // ImageMagickでしか扱えない形式の場合はImageMagickを使用
+    if input_backend == Some(Backend::ImageMagick) || spec.encoder == Backend::ImageMagick {
         tracing::info!("ImageMagickを使用して変換します: {} -> {}", input_ext, format);
-        return convert_with_imagemagick(input, output, format);
+        return convert_with_imagemagick(source, spec, options, on_stage);
     }
 
     // 通常の画像変換はimageクレートを使用
     tracing::info!("標準ライブラリを使用して変換します: {} -> {}", input_ext, format);
 
     // 画像を読み込み
-    tracing::debug!("画像ファイル読み込み開始: {}", input);
-    let img = match image::open(input) {
+    tracing::debug!("画像読み込み開始");
+    on_stage(Stage::Decoding);
+    let img = match source.decode(input_ext) {
         Ok(img) => {
             tracing::debug!("画像読み込み成功: {}x{}", img.width(), img.height());
             img
@@ -35,50 +112,25 @@ pub fn convert_image(input: &str, output: &str, format: &str) -> Result<()> {
         }
     };
 
-    // 出力フォーマットを決定
-    tracing::debug!("出力フォーマット決定: {}", format);
-    let format_enum = match format {
-        "jpeg" => {
-            tracing::debug!("JPEG形式として変換");
-            ImageFormat::Jpeg
-        },
-        "png" => {
-            tracing::debug!("PNG形式として変換");
-            ImageFormat::Png
-        },
-        "webp" => {
-            tracing::debug!("WebP形式として変換");
-            ImageFormat::WebP
-        },
-        _ => {
-            tracing::error!("未サポート出力形式: {}", format);
-            return Err(anyhow!("Unsupported output format: {}", format));
-        }
-    };
-
-    // 画像を保存
-    tracing::debug!("変換画像の保存開始: {}", output);
-    match img.save_with_format(output, format_enum) {
-        Ok(_) => {
-            tracing::debug!("変換画像の保存成功");
-            Ok(())
-        },
-        Err(e) => {
-            tracing::error!("変換画像の保存エラー: {:?}", e);
-            Err(anyhow!("Failed to save converted image: {}", e))
-        }
-    }
+    on_stage(Stage::Encoding);
+    encode_image_with_options(&img, spec.id, options)
 }
 
 // ImageMagickを使用した変換
-fn convert_with_imagemagick(input: &str, output: &str, format: &str) -> Result<()> {
-    tracing::debug!("ImageMagickで変換開始: {} -> {}", input, output);
+fn convert_with_imagemagick(
+    source: Source,
+    spec: &OutputFormat,
+    options: &ConvertOptions,
+    on_stage: &dyn Fn(Stage),
+) -> Result<Vec<u8>> {
+    tracing::debug!("ImageMagickで変換開始: -> {}", spec.id);
 
     // MagickWandを作成
-    let mut wand = MagickWand::new();
+    let wand = MagickWand::new();
 
     // 入力画像を読み込み
-    match wand.read_image(input) {
+    on_stage(Stage::Decoding);
+    match source.read_into(&wand) {
         Ok(_) => tracing::debug!("ImageMagickで画像読み込み成功"),
         Err(e) => {
             tracing::error!("ImageMagickで画像読み込みエラー: {:?}", e);
@@ -86,40 +138,160 @@ fn convert_with_imagemagick(input: &str, output: &str, format: &str) -> Result<(
         }
     }
 
-    // フォーマットの設定
-    let magick_format = match format {
-        "jpeg" => "JPEG",
-        "png" => "PNG",
-        "webp" => "WEBP",
-        "avif" => "AVIF",
-        _ => {
-            return Err(anyhow!("ImageMagickでサポートされていないフォーマット: {}", format));
+    on_stage(Stage::Encoding);
+    apply_magick_settings(&wand, spec, options)?;
+
+    // 画像を書き出し
+    match wand.write_image_blob(spec.magick_format) {
+        Ok(data) => {
+            tracing::debug!("ImageMagickで画像書き出し成功: {} バイト", data.len());
+            Ok(data)
+        },
+        Err(e) => {
+            tracing::error!("ImageMagickで画像保存エラー: {:?}", e);
+            Err(anyhow!("Failed to save image with ImageMagick: {}", e))
         }
-    };
+    }
+}
 
+// 出力フォーマットに応じたImageMagickの設定
+fn apply_magick_settings(wand: &MagickWand, spec: &OutputFormat, options: &ConvertOptions) -> Result<()> {
     // 画像形式を設定
-    if let Err(e) = wand.set_image_format(magick_format) {
+    if let Err(e) = wand.set_image_format(spec.magick_format) {
         tracing::error!("画像フォーマット設定エラー: {:?}", e);
         return Err(anyhow!("Failed to set image format: {}", e));
     }
 
-    // JPEGとWebP形式の場合は品質を設定
-    if format == "jpeg" || format == "webp" {
-        if let Err(e) = wand.set_compression_quality(90) {
+    // 非可逆形式の場合は品質を設定
+    if let Some(quality) = quality_for(spec, options) {
+        if let Err(e) = wand.set_compression_quality(quality as usize) {
             tracing::warn!("圧縮品質設定エラー: {:?}", e);
             // エラーは無視して続行
         }
     }
 
-    // 画像を保存
-    match wand.write_image(output) {
-        Ok(_) => {
-            tracing::debug!("ImageMagickで画像保存成功: {}", output);
-            Ok(())
-        },
-        Err(e) => {
-            tracing::error!("ImageMagickで画像保存エラー: {:?}", e);
-            Err(anyhow!("Failed to save image with ImageMagick: {}", e))
+    // TIFFの圧縮方式
+    if spec.id == "tiff" {
+        let compression = options.compression.as_deref().unwrap_or(spec.compressions[0]);
+        let compression_type = match compression {
+            "lzw" => CompressionType::LZW,
+            "zip" => CompressionType::Zip,
+            "jpeg" => CompressionType::JPEG,
+            _ => CompressionType::No,
+        };
+        tracing::debug!("TIFF圧縮方式: {}", compression);
+        if let Err(e) = wand.set_image_compression(compression_type) {
+            tracing::warn!("圧縮方式設定エラー: {:?}", e);
         }
     }
-}
\ No newline at end of file
+
+    Ok(())
+}
+
+// 品質を指定できる形式の場合、指定された品質（形式の範囲に収める）または既定値
+fn quality_for(spec: &OutputFormat, options: &ConvertOptions) -> Option<u8> {
+    spec.quality.map(|range| options.quality.map_or(range.default, |q| q.clamp(range.min, range.max)))
+}
+
+// バイト列から画像をデコード（HEIC、AVIFなどはImageMagick経由、SVGはresvg経由）
+pub fn decode_image(data: &[u8], input_ext: &str) -> Result<DynamicImage> {
+    tracing::debug!("画像デコード開始: {} バイト (拡張子: {})", data.len(), input_ext);
+
+    let decoder = formats::input_format(input_ext).map(|f| f.decoder);
+    if decoder == Some(Backend::Resvg) {
+        return svg::rasterize(data, &svg::RasterizeOptions::default());
+    }
+    if decoder == Some(Backend::ImageMagick) {
+        tracing::info!("ImageMagickを使用してデコードします: {}", input_ext);
+        let wand = MagickWand::new();
+        Source::Memory(data).read_into(&wand)
+            .map_err(|e| anyhow!("Failed to read image with ImageMagick: {}", e))?;
+        // imageクレートで扱えるようにPNGへ書き出してから読み込む
+        let png = wand.write_image_blob("PNG")
+            .map_err(|e| anyhow!("Failed to export image with ImageMagick: {}", e))?;
+        return limits::decode_memory(&png, Some(ImageFormat::Png))
+            .map_err(|e| anyhow!("Failed to open image: {}", e));
+    }
+
+    let img = limits::decode_memory(data, None).map_err(|e| {
+        tracing::error!("画像読み込みエラー: {:?}", e);
+        anyhow!("Failed to open image: {}", e)
+    })?;
+    tracing::debug!("画像デコード成功: {}x{}", img.width(), img.height());
+    Ok(img)
+}
+
+// DynamicImageを指定フォーマットでエンコード
+pub fn encode_image(img: &DynamicImage, format: &str) -> Result<Vec<u8>> {
+    encode_image_with_options(img, format, &ConvertOptions::default())
+}
+
+pub fn encode_image_with_options(img: &DynamicImage, format: &str, options: &ConvertOptions) -> Result<Vec<u8>> {
+    tracing::debug!("画像エンコード開始: {}x{} → {}", img.width(), img.height(), format);
+    let spec = formats::check_conversion("", format, options.compression.as_deref())
+        .inspect_err(|_| tracing::error!("未サポート出力形式: {}", format))?;
+
+    let mut buffer = Cursor::new(Vec::new());
+    match spec.encoder {
+        // SVGへのエンコードは行わない（最適化は svg::minify）
+        Backend::Resvg => return Err(anyhow!("Unsupported output format: {}", format)),
+        Backend::ImageMagick => {
+            // PNGを経由してImageMagickでエンコード
+            img.write_to(&mut buffer, ImageOutputFormat::Png)
+                .map_err(|e| anyhow!("Failed to encode image: {}", e))?;
+            let wand = MagickWand::new();
+            wand.read_image_blob(buffer.get_ref())
+                .map_err(|e| anyhow!("Failed to read image with ImageMagick: {}", e))?;
+            apply_magick_settings(&wand, spec, options)?;
+            return wand.write_image_blob(spec.magick_format)
+                .map_err(|e| anyhow!("Failed to save image with ImageMagick: {}", e));
+        },
+        Backend::Image => {
+            let output_format = match spec.id {
+                // JPEGはアルファチャンネルを持てないためRGBに変換
+                "jpeg" => {
+                    let quality = quality_for(spec, options).unwrap_or(90);
+                    DynamicImage::ImageRgb8(img.to_rgb8())
+                        .write_to(&mut buffer, ImageOutputFormat::Jpeg(quality))
+                        .map_err(|e| anyhow!("Failed to encode image: {}", e))?;
+                    return Ok(buffer.into_inner());
+                },
+                "png" => ImageOutputFormat::Png,
+                "webp" => ImageOutputFormat::WebP,
+                "gif" => ImageOutputFormat::Gif,
+                "bmp" => ImageOutputFormat::Bmp,
+                "ico" => ImageOutputFormat::Ico,
+                _ => return Err(anyhow!("Unsupported output format: {}", format)),
+            };
+
+            // ICOなど最大サイズのある形式は縮小してから保存
+            let mut img = match spec.max_dimension {
+                Some(max) if img.width() > max || img.height() > max => {
+                    tracing::debug!("{}の最大サイズ{}に縮小", spec.id, max);
+                    img.resize(max, max, FilterType::Lanczos3)
+                },
+                _ => img.clone(),
+            };
+            // ICO内のPNGはRGBAである必要がある
+            if spec.id == "ico" {
+                img = DynamicImage::ImageRgba8(img.to_rgba8());
+            }
+
+            img.write_to(&mut buffer, output_format)
+                .map_err(|e| anyhow!("Failed to encode image: {}", e))?;
+        },
+    }
+
+    tracing::debug!("画像エンコード完了: {} バイト", buffer.get_ref().len());
+    Ok(buffer.into_inner())
+}
+
+// 出力フォーマットに対応する拡張子
+pub fn extension_for(format: &str) -> &str {
+    formats::output_format(format).map(|f| f.extension).unwrap_or(format)
+}
+
+// 出力フォーマットに対応するMIMEタイプ
+pub fn mime_type_for(format: &str) -> &'static str {
+    formats::output_format(format).map(|f| f.mime_type).unwrap_or("application/octet-stream")
+}
@@ -0,0 +1,233 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::fmt;
+use std::str::FromStr;
+
+// 構造的な差分の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+// 差分1件分（パスはJSON Pointer）
+#[derive(Debug, Serialize)]
+pub struct Change {
+    pub kind: ChangeKind,
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new: Option<Value>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct DiffSummary {
+    pub added: usize,
+    pub removed: usize,
+    pub changed: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DiffReport {
+    pub identical: bool,
+    pub summary: DiffSummary,
+    pub changes: Vec<Change>,
+    // RFC 6902 JSON Patch（left に適用すると right になる）
+    pub patch: json_patch::Patch,
+    // RFC 7386 JSON Merge Patch
+    pub merge_patch: Value,
+    // merge patch で right を完全に再現できるか（オブジェクト内の null は削除と区別できない）
+    pub merge_patch_exact: bool,
+}
+
+// 2つの文書を比較して差分・パッチを作る
+pub fn diff(left: &Value, right: &Value) -> DiffReport {
+    let mut changes = Vec::new();
+    collect_changes(left, right, &mut String::new(), &mut changes);
+
+    let mut summary = DiffSummary::default();
+    for change in &changes {
+        match change.kind {
+            ChangeKind::Added => summary.added += 1,
+            ChangeKind::Removed => summary.removed += 1,
+            ChangeKind::Changed => summary.changed += 1,
+        }
+    }
+    tracing::debug!("差分検出: 追加{}件, 削除{}件, 変更{}件", summary.added, summary.removed, summary.changed);
+
+    let merge_patch = merge_patch(left, right);
+    let mut merged = left.clone();
+    json_patch::merge(&mut merged, &merge_patch);
+
+    DiffReport {
+        identical: changes.is_empty(),
+        summary,
+        changes,
+        patch: json_patch::diff(left, right),
+        merge_patch_exact: merged == *right,
+        merge_patch,
+    }
+}
+
+// オブジェクトはキーごと、配列は添字ごとに比較する
+fn collect_changes(left: &Value, right: &Value, path: &mut String, changes: &mut Vec<Change>) {
+    match (left, right) {
+        (Value::Object(left_map), Value::Object(right_map)) => {
+            for (key, left_value) in left_map {
+                let len = push_token(path, key);
+                match right_map.get(key) {
+                    Some(right_value) => collect_changes(left_value, right_value, path, changes),
+                    None => changes.push(removed(path, left_value)),
+                }
+                path.truncate(len);
+            }
+            for (key, right_value) in right_map.iter().filter(|(key, _)| !left_map.contains_key(*key)) {
+                let len = push_token(path, key);
+                changes.push(added(path, right_value));
+                path.truncate(len);
+            }
+        },
+        (Value::Array(left_items), Value::Array(right_items)) => {
+            for index in 0..left_items.len().max(right_items.len()) {
+                let len = push_token(path, &index.to_string());
+                match (left_items.get(index), right_items.get(index)) {
+                    (Some(left_value), Some(right_value)) => collect_changes(left_value, right_value, path, changes),
+                    (Some(left_value), None) => changes.push(removed(path, left_value)),
+                    (None, Some(right_value)) => changes.push(added(path, right_value)),
+                    (None, None) => {},
+                }
+                path.truncate(len);
+            }
+        },
+        _ if left != right => changes.push(Change {
+            kind: ChangeKind::Changed,
+            path: path.clone(),
+            old: Some(left.clone()),
+            new: Some(right.clone()),
+        }),
+        _ => {},
+    }
+}
+
+fn added(path: &str, value: &Value) -> Change {
+    Change { kind: ChangeKind::Added, path: path.to_string(), old: None, new: Some(value.clone()) }
+}
+
+fn removed(path: &str, value: &Value) -> Change {
+    Change { kind: ChangeKind::Removed, path: path.to_string(), old: Some(value.clone()), new: None }
+}
+
+// パスにトークンを追加し、追加前の長さを返す（~ と / はエスケープ）
+fn push_token(path: &mut String, token: &str) -> usize {
+    let len = path.len();
+    path.push('/');
+    path.push_str(&token.replace('~', "~0").replace('/', "~1"));
+    len
+}
+
+// RFC 7386 の merge patch を作る（オブジェクト以外は丸ごと置き換え）
+pub fn merge_patch(left: &Value, right: &Value) -> Value {
+    match (left, right) {
+        (Value::Object(left_map), Value::Object(right_map)) => {
+            let mut patch = Map::new();
+            for key in left_map.keys().filter(|key| !right_map.contains_key(*key)) {
+                patch.insert(key.clone(), Value::Null);
+            }
+            for (key, right_value) in right_map {
+                match left_map.get(key) {
+                    Some(left_value) if left_value == right_value => {},
+                    Some(left_value) => {
+                        patch.insert(key.clone(), merge_patch(left_value, right_value));
+                    },
+                    None => {
+                        patch.insert(key.clone(), merge_patch(&Value::Null, right_value));
+                    },
+                }
+            }
+            Value::Object(patch)
+        },
+        // 新しく追加するオブジェクトは null のキーを含められない
+        (_, Value::Object(_)) => merge_patch(&Value::Object(Map::new()), right),
+        _ => right.clone(),
+    }
+}
+
+// 適用するパッチの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchKind {
+    // RFC 6902 JSON Patch（操作の配列）
+    JsonPatch,
+    // RFC 7386 JSON Merge Patch
+    MergePatch,
+}
+
+impl PatchKind {
+    // 指定がなければ配列を JSON Patch、それ以外を merge patch とみなす
+    pub fn detect(patch: &Value) -> Self {
+        if patch.is_array() { PatchKind::JsonPatch } else { PatchKind::MergePatch }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PatchKind::JsonPatch => "json-patch",
+            PatchKind::MergePatch => "merge-patch",
+        }
+    }
+}
+
+impl FromStr for PatchKind {
+    type Err = PatchError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "json-patch" | "jsonpatch" | "rfc6902" => Ok(PatchKind::JsonPatch),
+            "merge-patch" | "mergepatch" | "merge" | "rfc7386" => Ok(PatchKind::MergePatch),
+            other => Err(PatchError::Invalid(format!("Unsupported patch type: {}", other))),
+        }
+    }
+}
+
+// パッチの解析・適用エラー
+#[derive(Debug)]
+pub enum PatchError {
+    // パッチの形式が不正
+    Invalid(String),
+    // 操作の適用に失敗（operation は0始まりの添字）
+    Failed { operation: usize, path: String, message: String },
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatchError::Invalid(message) => write!(f, "{}", message),
+            PatchError::Failed { operation, path, message } => {
+                write!(f, "Operation {} failed at '{}': {}", operation, path, message)
+            },
+        }
+    }
+}
+
+impl std::error::Error for PatchError {}
+
+// パッチを適用する（JSON Patch は途中で失敗すると文書を変更しない）
+pub fn apply(document: &mut Value, patch: &Value, kind: PatchKind) -> Result<(), PatchError> {
+    match kind {
+        PatchKind::JsonPatch => {
+            let patch = serde_json::from_value::<json_patch::Patch>(patch.clone())
+                .map_err(|e| PatchError::Invalid(format!("Invalid JSON Patch: {}", e)))?;
+            tracing::debug!("JSON Patch適用: {}操作", patch.0.len());
+            json_patch::patch(document, &patch).map_err(|e| PatchError::Failed {
+                operation: e.operation,
+                path: e.path.to_string(),
+                message: e.kind.to_string(),
+            })
+        },
+        PatchKind::MergePatch => {
+            json_patch::merge(document, patch);
+            Ok(())
+        },
+    }
+}
@@ -0,0 +1,298 @@
+use anyhow::{Result, anyhow};
+use std::str::FromStr;
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+
+use crate::services::limits::DecodeLimits;
+
+// 矩形切り抜き（x, y, 幅, 高さ）
+#[derive(Debug, Clone, Copy)]
+pub struct CropRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl FromStr for CropRect {
+    type Err = anyhow::Error;
+
+    // "x,y,width,height" 形式を解析
+    fn from_str(s: &str) -> Result<Self> {
+        let values = s
+            .split(',')
+            .map(|v| v.trim().parse::<u32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| anyhow!("Invalid crop rectangle: {}", s))?;
+
+        match values.as_slice() {
+            [x, y, width, height] if *width > 0 && *height > 0 => Ok(CropRect {
+                x: *x,
+                y: *y,
+                width: *width,
+                height: *height,
+            }),
+            _ => Err(anyhow!("Invalid crop rectangle: {}", s)),
+        }
+    }
+}
+
+// アスペクト比（例: 16:9）
+#[derive(Debug, Clone, Copy)]
+pub struct AspectRatio {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl FromStr for AspectRatio {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (w, h) = s
+            .split_once(':')
+            .ok_or_else(|| anyhow!("Invalid aspect ratio: {}", s))?;
+        let width = w.trim().parse::<u32>().map_err(|_| anyhow!("Invalid aspect ratio: {}", s))?;
+        let height = h.trim().parse::<u32>().map_err(|_| anyhow!("Invalid aspect ratio: {}", s))?;
+        if width == 0 || height == 0 {
+            return Err(anyhow!("Invalid aspect ratio: {}", s));
+        }
+        Ok(AspectRatio { width, height })
+    }
+}
+
+// アスペクト比切り抜き時に残す位置
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Gravity {
+    #[default]
+    Center,
+    North,
+    South,
+    East,
+    West,
+    NorthEast,
+    NorthWest,
+    SouthEast,
+    SouthWest,
+}
+
+impl FromStr for Gravity {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "center" | "centre" => Ok(Gravity::Center),
+            "north" | "top" => Ok(Gravity::North),
+            "south" | "bottom" => Ok(Gravity::South),
+            "east" | "right" => Ok(Gravity::East),
+            "west" | "left" => Ok(Gravity::West),
+            "northeast" => Ok(Gravity::NorthEast),
+            "northwest" => Ok(Gravity::NorthWest),
+            "southeast" => Ok(Gravity::SouthEast),
+            "southwest" => Ok(Gravity::SouthWest),
+            _ => Err(anyhow!("Invalid gravity: {}", s)),
+        }
+    }
+}
+
+// 反転方向
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Flip {
+    Horizontal,
+    Vertical,
+    Both,
+}
+
+impl FromStr for Flip {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "horizontal" | "h" => Ok(Flip::Horizontal),
+            "vertical" | "v" => Ok(Flip::Vertical),
+            "both" => Ok(Flip::Both),
+            _ => Err(anyhow!("Invalid flip direction: {}", s)),
+        }
+    }
+}
+
+// 編集内容（切り抜き → アスペクト比切り抜き → 回転 → 反転 の順に適用）
+#[derive(Debug, Clone)]
+pub struct EditOptions {
+    pub crop: Option<CropRect>,
+    pub aspect: Option<AspectRatio>,
+    pub gravity: Gravity,
+    // 時計回りの回転角度（度）
+    pub rotate: f32,
+    // 任意角度回転で生じる余白の色
+    pub background: Rgba<u8>,
+    pub flip: Option<Flip>,
+}
+
+impl Default for EditOptions {
+    fn default() -> Self {
+        EditOptions {
+            crop: None,
+            aspect: None,
+            gravity: Gravity::Center,
+            rotate: 0.0,
+            background: Rgba([255, 255, 255, 255]),
+            flip: None,
+        }
+    }
+}
+
+// 色指定を解析（"#rrggbb"、"#rrggbbaa"、"transparent"、"white"、"black"）
+pub fn parse_color(s: &str) -> Result<Rgba<u8>> {
+    let value = s.trim().to_lowercase();
+    match value.as_str() {
+        "transparent" | "none" => return Ok(Rgba([0, 0, 0, 0])),
+        "white" => return Ok(Rgba([255, 255, 255, 255])),
+        "black" => return Ok(Rgba([0, 0, 0, 255])),
+        _ => {}
+    }
+
+    let hex = value.trim_start_matches('#');
+    if !(hex.len() == 6 || hex.len() == 8) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(anyhow!("Invalid color: {}", s));
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap_or(0);
+    let alpha = if hex.len() == 8 { channel(6) } else { 255 };
+    Ok(Rgba([channel(0), channel(2), channel(4), alpha]))
+}
+
+pub fn apply_edits(img: DynamicImage, options: &EditOptions) -> Result<DynamicImage> {
+    tracing::debug!("画像編集開始: {}x{} {:?}", img.width(), img.height(), options);
+    let mut img = img;
+
+    // 矩形切り抜き
+    if let Some(rect) = options.crop {
+        let (width, height) = img.dimensions();
+        if rect.x >= width || rect.y >= height {
+            return Err(anyhow!(
+                "Crop rectangle is outside of the image ({}x{})", width, height
+            ));
+        }
+        // 画像からはみ出す部分は画像の範囲に収める
+        let crop_width = rect.width.min(width - rect.x);
+        let crop_height = rect.height.min(height - rect.y);
+        tracing::debug!("切り抜き: {}x{}+{}+{}", crop_width, crop_height, rect.x, rect.y);
+        img = img.crop_imm(rect.x, rect.y, crop_width, crop_height);
+    }
+
+    // アスペクト比切り抜き
+    if let Some(aspect) = options.aspect {
+        let (width, height) = img.dimensions();
+        let (crop_width, crop_height) = fit_aspect(width, height, aspect);
+        let (x, y) = gravity_offset(width, height, crop_width, crop_height, options.gravity);
+        tracing::debug!("アスペクト比切り抜き: {}x{}+{}+{}", crop_width, crop_height, x, y);
+        img = img.crop_imm(x, y, crop_width, crop_height);
+    }
+
+    // 回転
+    let degrees = options.rotate.rem_euclid(360.0);
+    if degrees != 0.0 {
+        tracing::debug!("回転: {}度", degrees);
+        img = if degrees == 90.0 {
+            img.rotate90()
+        } else if degrees == 180.0 {
+            img.rotate180()
+        } else if degrees == 270.0 {
+            img.rotate270()
+        } else {
+            DynamicImage::ImageRgba8(rotate_with_background(&img.to_rgba8(), degrees, options.background)?)
+        };
+    }
+
+    // 反転
+    match options.flip {
+        Some(Flip::Horizontal) => img = img.fliph(),
+        Some(Flip::Vertical) => img = img.flipv(),
+        Some(Flip::Both) => img = img.fliph().flipv(),
+        None => {}
+    }
+
+    tracing::debug!("画像編集完了: {}x{}", img.width(), img.height());
+    Ok(img)
+}
+
+// 画像内に収まる最大の指定アスペクト比の領域サイズ
+fn fit_aspect(width: u32, height: u32, aspect: AspectRatio) -> (u32, u32) {
+    let target = aspect.width as f64 / aspect.height as f64;
+    if (width as f64 / height as f64) > target {
+        let crop_width = ((height as f64 * target).round() as u32).clamp(1, width);
+        (crop_width, height)
+    } else {
+        let crop_height = ((width as f64 / target).round() as u32).clamp(1, height);
+        (width, crop_height)
+    }
+}
+
+// gravityに応じた切り抜き位置
+fn gravity_offset(width: u32, height: u32, crop_width: u32, crop_height: u32, gravity: Gravity) -> (u32, u32) {
+    let max_x = width - crop_width;
+    let max_y = height - crop_height;
+    let x = match gravity {
+        Gravity::West | Gravity::NorthWest | Gravity::SouthWest => 0,
+        Gravity::East | Gravity::NorthEast | Gravity::SouthEast => max_x,
+        _ => max_x / 2,
+    };
+    let y = match gravity {
+        Gravity::North | Gravity::NorthEast | Gravity::NorthWest => 0,
+        Gravity::South | Gravity::SouthEast | Gravity::SouthWest => max_y,
+        _ => max_y / 2,
+    };
+    (x, y)
+}
+
+// 任意角度の回転（キャンバスを拡張し、余白を背景色で塗りつぶす）
+// 拡張後のキャンバスは元画像より大きくなるため、確保する前にデコードの上限で確認する
+fn rotate_with_background(src: &RgbaImage, degrees: f32, background: Rgba<u8>) -> Result<RgbaImage> {
+    let (sin, cos) = (degrees as f64).to_radians().sin_cos();
+    let (src_width, src_height) = (src.width() as f64, src.height() as f64);
+
+    // 回転後の外接矩形
+    let out_width = (src_width * cos.abs() + src_height * sin.abs()).ceil().max(1.0) as u32;
+    let out_height = (src_width * sin.abs() + src_height * cos.abs()).ceil().max(1.0) as u32;
+    DecodeLimits::from_config().check(out_width, out_height)?;
+
+    let (src_cx, src_cy) = (src_width / 2.0, src_height / 2.0);
+    let (out_cx, out_cy) = (out_width as f64 / 2.0, out_height as f64 / 2.0);
+
+    Ok(RgbaImage::from_fn(out_width, out_height, |x, y| {
+        // 出力画素の中心を逆回転して元画像の座標を求める
+        let dx = x as f64 + 0.5 - out_cx;
+        let dy = y as f64 + 0.5 - out_cy;
+        let sx = dx * cos + dy * sin + src_cx - 0.5;
+        let sy = -dx * sin + dy * cos + src_cy - 0.5;
+        sample_bilinear(src, sx, sy, background)
+    }))
+}
+
+// バイリニア補間（範囲外は背景色として扱う）
+fn sample_bilinear(src: &RgbaImage, x: f64, y: f64, background: Rgba<u8>) -> Rgba<u8> {
+    let x0 = x.floor();
+    let y0 = y.floor();
+    let fx = x - x0;
+    let fy = y - y0;
+
+    let pixel = |px: f64, py: f64| -> [f64; 4] {
+        let p = if px < 0.0 || py < 0.0 || px >= src.width() as f64 || py >= src.height() as f64 {
+            background
+        } else {
+            *src.get_pixel(px as u32, py as u32)
+        };
+        [p[0] as f64, p[1] as f64, p[2] as f64, p[3] as f64]
+    };
+
+    let p00 = pixel(x0, y0);
+    let p10 = pixel(x0 + 1.0, y0);
+    let p01 = pixel(x0, y0 + 1.0);
+    let p11 = pixel(x0 + 1.0, y0 + 1.0);
+
+    let mut out = [0u8; 4];
+    for (i, channel) in out.iter_mut().enumerate() {
+        let top = p00[i] * (1.0 - fx) + p10[i] * fx;
+        let bottom = p01[i] * (1.0 - fx) + p11[i] * fx;
+        *channel = (top * (1.0 - fy) + bottom * fy).round().clamp(0.0, 255.0) as u8;
+    }
+    Rgba(out)
+}
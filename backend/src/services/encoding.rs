@@ -0,0 +1,242 @@
+use base64::alphabet;
+use base64::engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig};
+use base64::Engine;
+use encoding_rs::{DecoderResult, EncoderResult, Encoding};
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC};
+use std::fmt;
+use std::str::FromStr;
+
+// パーセントエンコーディングでそのまま残す文字（RFC 3986 の非予約文字）
+const UNRESERVED: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'.').remove(b'_').remove(b'~');
+
+// 文字列表現へのエンコード方式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Codec {
+    Base64,
+    // URL・ファイル名で使える Base64（+ / の代わりに - _）
+    Base64Url,
+    Percent,
+    Hex,
+    QuotedPrintable,
+}
+
+impl Codec {
+    pub fn name(self) -> &'static str {
+        match self {
+            Codec::Base64 => "base64",
+            Codec::Base64Url => "base64url",
+            Codec::Percent => "percent",
+            Codec::Hex => "hex",
+            Codec::QuotedPrintable => "quoted-printable",
+        }
+    }
+
+    // padding は Base64 の末尾の = を付けるかどうか（ほかの方式では無視する）
+    pub fn encode(self, data: &[u8], padding: bool) -> String {
+        match self {
+            Codec::Base64 => base64_engine(&alphabet::STANDARD, padding).encode(data),
+            Codec::Base64Url => base64_engine(&alphabet::URL_SAFE, padding).encode(data),
+            Codec::Percent => percent_encoding::percent_encode(data, UNRESERVED).to_string(),
+            Codec::Hex => hex::encode(data),
+            Codec::QuotedPrintable => quoted_printable::encode_to_str(data),
+        }
+    }
+
+    // Base64・16進数は改行などの空白を無視し、Base64 のパディングは有無を問わない
+    pub fn decode(self, input: &str) -> Result<Vec<u8>, EncodingError> {
+        let compact = || input.chars().filter(|c| !c.is_whitespace()).collect::<String>();
+        match self {
+            Codec::Base64 => base64_engine(&alphabet::STANDARD, false)
+                .decode(compact())
+                .map_err(|e| EncodingError::Invalid(format!("Invalid Base64: {}", e))),
+            Codec::Base64Url => base64_engine(&alphabet::URL_SAFE, false)
+                .decode(compact())
+                .map_err(|e| EncodingError::Invalid(format!("Invalid Base64 (URL-safe): {}", e))),
+            Codec::Percent => Ok(percent_encoding::percent_decode_str(input).collect()),
+            Codec::Hex => hex::decode(compact()).map_err(|e| EncodingError::Invalid(format!("Invalid hex: {}", e))),
+            Codec::QuotedPrintable => quoted_printable::decode(input, quoted_printable::ParseMode::Robust)
+                .map_err(|e| EncodingError::Invalid(format!("Invalid quoted-printable: {}", e))),
+        }
+    }
+}
+
+impl FromStr for Codec {
+    type Err = EncodingError;
+
+    fn from_str(s: &str) -> Result<Self, EncodingError> {
+        match s.trim().to_lowercase().as_str() {
+            "base64" => Ok(Codec::Base64),
+            "base64url" | "base64-url" => Ok(Codec::Base64Url),
+            "percent" | "url" => Ok(Codec::Percent),
+            "hex" => Ok(Codec::Hex),
+            "quoted-printable" | "qp" => Ok(Codec::QuotedPrintable),
+            other => Err(EncodingError::Unsupported(format!("Unsupported encoding: {}", other))),
+        }
+    }
+}
+
+fn base64_engine(alphabet: &alphabet::Alphabet, padding: bool) -> GeneralPurpose {
+    let config = GeneralPurposeConfig::new()
+        .with_encode_padding(padding)
+        .with_decode_padding_mode(DecodePaddingMode::Indifferent);
+    GeneralPurpose::new(alphabet, config)
+}
+
+// テキストファイルの文字コード
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Charset {
+    Utf8,
+    // encoding_rs の Shift_JIS は Windows-31J（CP932）の拡張文字も扱う
+    ShiftJis,
+    EucJp,
+    Utf16Le,
+    Utf16Be,
+}
+
+impl Charset {
+    pub fn name(self) -> &'static str {
+        self.encoding().name()
+    }
+
+    fn encoding(self) -> &'static Encoding {
+        match self {
+            Charset::Utf8 => encoding_rs::UTF_8,
+            Charset::ShiftJis => encoding_rs::SHIFT_JIS,
+            Charset::EucJp => encoding_rs::EUC_JP,
+            Charset::Utf16Le => encoding_rs::UTF_16LE,
+            Charset::Utf16Be => encoding_rs::UTF_16BE,
+        }
+    }
+
+    fn bom(self) -> &'static [u8] {
+        match self {
+            Charset::Utf8 => b"\xEF\xBB\xBF",
+            Charset::Utf16Le => b"\xFF\xFE",
+            Charset::Utf16Be => b"\xFE\xFF",
+            Charset::ShiftJis | Charset::EucJp => b"",
+        }
+    }
+
+    // テキストをこの文字コードのバイト列にする（表せない文字があればエラー、BOM は UTF-8・UTF-16 のみ）
+    pub fn encode(self, text: &str, bom: bool) -> Result<Vec<u8>, EncodingError> {
+        let mut output = Vec::new();
+        if bom {
+            output.extend_from_slice(self.bom());
+        }
+        match self {
+            Charset::Utf8 => output.extend_from_slice(text.as_bytes()),
+            // encoding_rs は UTF-16 へのエンコードに対応していないので自前で変換する
+            Charset::Utf16Le => output.extend(text.encode_utf16().flat_map(u16::to_le_bytes)),
+            Charset::Utf16Be => output.extend(text.encode_utf16().flat_map(u16::to_be_bytes)),
+            Charset::ShiftJis | Charset::EucJp => {
+                let mut encoder = self.encoding().new_encoder();
+                let capacity = encoder.max_buffer_length_from_utf8_without_replacement(text.len()).unwrap_or(text.len() * 2);
+                output.reserve(capacity);
+                let (result, read) = encoder.encode_from_utf8_to_vec_without_replacement(text, &mut output, true);
+                if let EncoderResult::Unmappable(character) = result {
+                    // read は表せない文字の直後までの長さ
+                    let start = read - character.len_utf8();
+                    let (line, column) = line_column(&text[..start]);
+                    return Err(EncodingError::Unmappable { charset: self, character, line, column });
+                }
+            },
+        }
+        Ok(output)
+    }
+
+    // バイト列をこの文字コードのテキストとして読む（先頭の BOM は取り除く）
+    pub fn decode(self, data: &[u8]) -> Result<String, EncodingError> {
+        let data = data.strip_prefix(self.bom()).unwrap_or(data);
+        let mut decoder = self.encoding().new_decoder_without_bom_handling();
+        let capacity = decoder.max_utf8_buffer_length_without_replacement(data.len()).unwrap_or(data.len() * 3);
+        let mut text = String::with_capacity(capacity);
+        let (result, read) = decoder.decode_to_string_without_replacement(data, &mut text, true);
+        match result {
+            DecoderResult::Malformed(length, extra) => {
+                let offset = read - length as usize - extra as usize;
+                Err(EncodingError::Malformed { charset: self, offset })
+            },
+            _ => Ok(text),
+        }
+    }
+
+    // 文字コードを推定する（BOM → UTF-8 → Shift_JIS・EUC-JP の順、どれでも読めなければ None）
+    pub fn detect(data: &[u8]) -> Option<Charset> {
+        if let Some((encoding, _)) = Encoding::for_bom(data) {
+            return [Charset::Utf8, Charset::Utf16Le, Charset::Utf16Be]
+                .into_iter()
+                .find(|c| c.encoding() == encoding)
+                .filter(|c| c.decode(data).is_ok());
+        }
+        if std::str::from_utf8(data).is_ok() {
+            return Some(Charset::Utf8);
+        }
+        // 両方で読める場合は、ひらがな・カタカナが多く半角カナが少ない方を選ぶ（同点なら後ろの Shift_JIS）
+        [Charset::EucJp, Charset::ShiftJis]
+            .into_iter()
+            .filter_map(|charset| charset.decode(data).ok().map(|text| (charset, japanese_score(&text))))
+            .max_by_key(|(_, score)| *score)
+            .map(|(charset, _)| charset)
+    }
+}
+
+impl FromStr for Charset {
+    type Err = EncodingError;
+
+    fn from_str(s: &str) -> Result<Self, EncodingError> {
+        match s.trim().to_lowercase().replace('_', "-").as_str() {
+            "utf-8" | "utf8" => Ok(Charset::Utf8),
+            "shift-jis" | "sjis" | "cp932" | "windows-31j" => Ok(Charset::ShiftJis),
+            "euc-jp" | "eucjp" => Ok(Charset::EucJp),
+            "utf-16" | "utf-16le" | "utf16le" => Ok(Charset::Utf16Le),
+            "utf-16be" | "utf16be" => Ok(Charset::Utf16Be),
+            other => Err(EncodingError::Unsupported(format!("Unsupported charset: {}", other))),
+        }
+    }
+}
+
+fn japanese_score(text: &str) -> i64 {
+    text.chars()
+        .map(|c| match c {
+            '\u{3040}'..='\u{30FF}' => 1,
+            '\u{FF61}'..='\u{FF9F}' => -1,
+            _ => 0,
+        })
+        .sum()
+}
+
+// 1始まりの行・列（列は文字数）
+fn line_column(before: &str) -> (usize, usize) {
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+    (line, column)
+}
+
+#[derive(Debug)]
+pub enum EncodingError {
+    // Base64・16進数などの形式が不正
+    Invalid(String),
+    // 指定した文字コードで表せない文字
+    Unmappable { charset: Charset, character: char, line: usize, column: usize },
+    // 指定した文字コードとして読めないバイト列（offset は0始まりのバイト位置）
+    Malformed { charset: Charset, offset: usize },
+    Unsupported(String),
+}
+
+impl fmt::Display for EncodingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncodingError::Invalid(message) | EncodingError::Unsupported(message) => write!(f, "{}", message),
+            EncodingError::Unmappable { charset, character, line, column } => write!(
+                f,
+                "'{}' (U+{:04X}) at line {}, column {} cannot be represented in {}",
+                character, *character as u32, line, column, charset.name()
+            ),
+            EncodingError::Malformed { charset, offset } => {
+                write!(f, "Invalid {} byte sequence at offset {}", charset.name(), offset)
+            },
+        }
+    }
+}
+
+impl std::error::Error for EncodingError {}
@@ -0,0 +1,212 @@
+use anyhow::{Result, anyhow};
+use magick_rust::{MagickWand, PixelWand};
+use serde::Serialize;
+use std::sync::OnceLock;
+
+// エンコード・デコードを担当するライブラリ
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    // imageクレート
+    Image,
+    // ImageMagick
+    ImageMagick,
+    // resvg（SVGのラスタライズ）
+    Resvg,
+}
+
+// 品質指定の範囲
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct QualityRange {
+    pub min: u8,
+    pub max: u8,
+    pub default: u8,
+}
+
+// 出力フォーマットの能力
+#[derive(Debug, Serialize)]
+pub struct OutputFormat {
+    pub id: &'static str,
+    pub extension: &'static str,
+    pub mime_type: &'static str,
+    pub encoder: Backend,
+    // ImageMagickでのフォーマット名
+    #[serde(skip)]
+    pub magick_format: &'static str,
+    pub lossy: bool,
+    pub lossless: bool,
+    pub alpha: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quality: Option<QualityRange>,
+    // 選択可能な圧縮方式（先頭がデフォルト）
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    pub compressions: &'static [&'static str],
+    // 出力できる最大の辺の長さ
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_dimension: Option<u32>,
+}
+
+// 入力フォーマットの能力
+#[derive(Debug, Serialize)]
+pub struct InputFormat {
+    pub id: &'static str,
+    pub extensions: &'static [&'static str],
+    pub mime_type: &'static str,
+    pub decoder: Backend,
+}
+
+const DEFAULT_QUALITY: QualityRange = QualityRange { min: 1, max: 100, default: 90 };
+
+pub const OUTPUT_FORMATS: &[OutputFormat] = &[
+    OutputFormat {
+        id: "jpeg", extension: "jpg", mime_type: "image/jpeg", encoder: Backend::Image, magick_format: "JPEG",
+        lossy: true, lossless: false, alpha: false, quality: Some(DEFAULT_QUALITY), compressions: &[], max_dimension: None,
+    },
+    OutputFormat {
+        id: "png", extension: "png", mime_type: "image/png", encoder: Backend::Image, magick_format: "PNG",
+        lossy: false, lossless: true, alpha: true, quality: None, compressions: &[], max_dimension: None,
+    },
+    OutputFormat {
+        id: "webp", extension: "webp", mime_type: "image/webp", encoder: Backend::Image, magick_format: "WEBP",
+        lossy: false, lossless: true, alpha: true, quality: None, compressions: &[], max_dimension: None,
+    },
+    OutputFormat {
+        id: "avif", extension: "avif", mime_type: "image/avif", encoder: Backend::ImageMagick, magick_format: "AVIF",
+        lossy: true, lossless: false, alpha: true, quality: Some(DEFAULT_QUALITY), compressions: &[], max_dimension: None,
+    },
+    OutputFormat {
+        id: "gif", extension: "gif", mime_type: "image/gif", encoder: Backend::Image, magick_format: "GIF",
+        lossy: false, lossless: false, alpha: true, quality: None, compressions: &[], max_dimension: None,
+    },
+    OutputFormat {
+        id: "bmp", extension: "bmp", mime_type: "image/bmp", encoder: Backend::Image, magick_format: "BMP",
+        lossy: false, lossless: true, alpha: true, quality: None, compressions: &[], max_dimension: None,
+    },
+    OutputFormat {
+        id: "tiff", extension: "tiff", mime_type: "image/tiff", encoder: Backend::ImageMagick, magick_format: "TIFF",
+        lossy: false, lossless: true, alpha: true, quality: None, compressions: &["lzw", "zip", "jpeg", "none"], max_dimension: None,
+    },
+    OutputFormat {
+        id: "ico", extension: "ico", mime_type: "image/x-icon", encoder: Backend::Image, magick_format: "ICO",
+        lossy: false, lossless: true, alpha: true, quality: None, compressions: &[], max_dimension: Some(256),
+    },
+    OutputFormat {
+        id: "heic", extension: "heic", mime_type: "image/heic", encoder: Backend::ImageMagick, magick_format: "HEIC",
+        lossy: true, lossless: false, alpha: true, quality: Some(DEFAULT_QUALITY), compressions: &[], max_dimension: None,
+    },
+    OutputFormat {
+        id: "jxl", extension: "jxl", mime_type: "image/jxl", encoder: Backend::ImageMagick, magick_format: "JXL",
+        lossy: true, lossless: true, alpha: true, quality: Some(DEFAULT_QUALITY), compressions: &[], max_dimension: None,
+    },
+];
+
+pub const INPUT_FORMATS: &[InputFormat] = &[
+    InputFormat { id: "jpeg", extensions: &["jpg", "jpeg"], mime_type: "image/jpeg", decoder: Backend::Image },
+    InputFormat { id: "png", extensions: &["png"], mime_type: "image/png", decoder: Backend::Image },
+    InputFormat { id: "webp", extensions: &["webp"], mime_type: "image/webp", decoder: Backend::Image },
+    InputFormat { id: "gif", extensions: &["gif"], mime_type: "image/gif", decoder: Backend::Image },
+    InputFormat { id: "bmp", extensions: &["bmp"], mime_type: "image/bmp", decoder: Backend::Image },
+    InputFormat { id: "tiff", extensions: &["tif", "tiff"], mime_type: "image/tiff", decoder: Backend::Image },
+    InputFormat { id: "ico", extensions: &["ico"], mime_type: "image/x-icon", decoder: Backend::Image },
+    InputFormat { id: "avif", extensions: &["avif"], mime_type: "image/avif", decoder: Backend::ImageMagick },
+    InputFormat { id: "heic", extensions: &["heic", "heif"], mime_type: "image/heic", decoder: Backend::ImageMagick },
+    InputFormat { id: "jxl", extensions: &["jxl"], mime_type: "image/jxl", decoder: Backend::ImageMagick },
+    InputFormat { id: "svg", extensions: &["svg", "svgz"], mime_type: "image/svg+xml", decoder: Backend::Resvg },
+];
+
+// フォーマット名（別名を含む）から出力フォーマットを取得
+pub fn output_format(name: &str) -> Option<&'static OutputFormat> {
+    let name = name.trim().to_lowercase();
+    let id = match name.as_str() {
+        "jpg" => "jpeg",
+        "tif" => "tiff",
+        "heif" => "heic",
+        "jpegxl" | "jpeg-xl" => "jxl",
+        id => id,
+    };
+    OUTPUT_FORMATS.iter().find(|f| f.id == id)
+}
+
+// 拡張子から入力フォーマットを取得
+pub fn input_format(extension: &str) -> Option<&'static InputFormat> {
+    let extension = extension.trim().to_lowercase();
+    INPUT_FORMATS.iter().find(|f| f.extensions.contains(&extension.as_str()))
+}
+
+// 変換前に入力・出力・オプションの組み合わせを検証する
+// 拡張子がない場合は内容から形式を判定するため入力側の検証は行わない
+pub fn check_conversion(input_ext: &str, output: &str, compression: Option<&str>) -> Result<&'static OutputFormat> {
+    let spec = output_format(output).ok_or_else(|| anyhow!("Unsupported output format: {}", output))?;
+
+    // ImageMagickで書き出す形式は、リンクされているデリゲートが対応している場合のみ受け付ける
+    if spec.encoder == Backend::ImageMagick && !magick_supports(spec.magick_format, true) {
+        return Err(anyhow!("Output format {} is not available on this server", spec.id));
+    }
+
+    if !input_ext.is_empty() {
+        let input = input_format(input_ext).ok_or_else(|| anyhow!("Unsupported input format: {}", input_ext))?;
+
+        // ImageMagickで読み込む形式も、デリゲートが読み込みに対応している場合のみ受け付ける
+        let readable = input.decoder != Backend::ImageMagick
+            || output_format(input.id).is_some_and(|f| magick_supports(f.magick_format, false));
+        if !readable {
+            return Err(anyhow!("Input format {} is not available on this server", input.id));
+        }
+    }
+
+    if let Some(compression) = compression {
+        if !spec.compressions.contains(&compression) {
+            return Err(anyhow!("Unsupported compression '{}' for {}", compression, spec.id));
+        }
+    }
+
+    Ok(spec)
+}
+
+// ImageMagickのデリゲート（コーダー）の対応状況
+#[derive(Debug, Clone, Serialize)]
+pub struct Delegate {
+    pub format: &'static str,
+    pub read: bool,
+    pub write: bool,
+}
+
+static MAGICK_DELEGATES: OnceLock<Vec<Delegate>> = OnceLock::new();
+
+// リンクされているImageMagickで実際に読み書きできる形式（初回のみ検査）
+pub fn magick_delegates() -> &'static [Delegate] {
+    MAGICK_DELEGATES.get_or_init(|| {
+        OUTPUT_FORMATS.iter().map(|f| probe_delegate(f.magick_format)).collect()
+    })
+}
+
+// ImageMagickのデリゲートで指定した方向の処理ができるか
+pub fn magick_supports(magick_format: &str, write: bool) -> bool {
+    magick_delegates()
+        .iter()
+        .any(|d| d.format == magick_format && if write { d.write } else { d.read })
+}
+
+// 1x1の画像を書き出し・読み込みして対応状況を調べる
+fn probe_delegate(format: &'static str) -> Delegate {
+    let wand = MagickWand::new();
+    let mut background = PixelWand::new();
+    let encoded = background
+        .set_color("white")
+        .and_then(|_| wand.new_image(1, 1, &background))
+        .and_then(|_| wand.write_image_blob(format))
+        .ok()
+        .filter(|data| !data.is_empty());
+
+    let read = encoded
+        .as_ref()
+        .map(|data| MagickWand::new().read_image_blob(data).is_ok())
+        .unwrap_or(false);
+
+    tracing::debug!("ImageMagickデリゲート {}: 読み込み={}, 書き出し={}", format, read, encoded.is_some());
+    Delegate {
+        format,
+        read,
+        write: encoded.is_some(),
+    }
+}
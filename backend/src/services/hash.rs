@@ -0,0 +1,270 @@
+use anyhow::{Result, anyhow};
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use md5::Md5;
+use serde::{Serialize, Serializer};
+use sha1::Sha1;
+use sha2::{Digest as _, Sha256, Sha512};
+use std::str::FromStr;
+use subtle::ConstantTimeEq;
+
+// ハッシュアルゴリズム
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Algorithm {
+    Md5,
+    Sha1,
+    Sha256,
+    Sha512,
+    Blake3,
+    Crc32,
+}
+
+impl Algorithm {
+    pub fn name(self) -> &'static str {
+        match self {
+            Algorithm::Md5 => "md5",
+            Algorithm::Sha1 => "sha1",
+            Algorithm::Sha256 => "sha256",
+            Algorithm::Sha512 => "sha512",
+            Algorithm::Blake3 => "blake3",
+            Algorithm::Crc32 => "crc32",
+        }
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = anyhow::Error;
+
+    // "SHA-256" のような表記も受け付ける
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().replace(['-', '_'], "").as_str() {
+            "md5" => Ok(Algorithm::Md5),
+            "sha1" => Ok(Algorithm::Sha1),
+            "sha256" => Ok(Algorithm::Sha256),
+            "sha512" => Ok(Algorithm::Sha512),
+            "blake3" => Ok(Algorithm::Blake3),
+            "crc32" => Ok(Algorithm::Crc32),
+            other => Err(anyhow!("Unsupported algorithm: {}", other)),
+        }
+    }
+}
+
+// HMAC の鍵の表記
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum KeyEncoding {
+    #[default]
+    Utf8,
+    Hex,
+    Base64,
+}
+
+impl FromStr for KeyEncoding {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "utf8" | "utf-8" | "text" => Ok(KeyEncoding::Utf8),
+            "hex" => Ok(KeyEncoding::Hex),
+            "base64" => Ok(KeyEncoding::Base64),
+            other => Err(anyhow!("Unsupported key encoding: {}", other)),
+        }
+    }
+}
+
+impl KeyEncoding {
+    pub fn decode(self, key: &str) -> Result<Vec<u8>> {
+        match self {
+            KeyEncoding::Utf8 => Ok(key.as_bytes().to_vec()),
+            KeyEncoding::Hex => hex::decode(key.trim()).map_err(|e| anyhow!("Invalid hex key: {}", e)),
+            KeyEncoding::Base64 => base64::engine::general_purpose::STANDARD
+                .decode(key.trim())
+                .map_err(|e| anyhow!("Invalid base64 key: {}", e)),
+        }
+    }
+}
+
+// HMAC の計算途中の状態（BLAKE3・CRC32 は HMAC に使えない）
+enum HmacState {
+    Md5(Hmac<Md5>),
+    Sha1(Hmac<Sha1>),
+    Sha256(Hmac<Sha256>),
+    Sha512(Hmac<Sha512>),
+}
+
+impl HmacState {
+    fn new(algorithm: Algorithm, key: &[u8]) -> Result<Self> {
+        // HMAC は任意の長さの鍵を受け付けるので new_from_slice は失敗しない
+        let invalid = |_| anyhow!("Invalid HMAC key");
+        match algorithm {
+            Algorithm::Md5 => Ok(HmacState::Md5(Hmac::new_from_slice(key).map_err(invalid)?)),
+            Algorithm::Sha1 => Ok(HmacState::Sha1(Hmac::new_from_slice(key).map_err(invalid)?)),
+            Algorithm::Sha256 => Ok(HmacState::Sha256(Hmac::new_from_slice(key).map_err(invalid)?)),
+            Algorithm::Sha512 => Ok(HmacState::Sha512(Hmac::new_from_slice(key).map_err(invalid)?)),
+            other => Err(anyhow!("HMAC is not supported for {}", other.name())),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            HmacState::Md5(mac) => mac.update(data),
+            HmacState::Sha1(mac) => mac.update(data),
+            HmacState::Sha256(mac) => mac.update(data),
+            HmacState::Sha512(mac) => mac.update(data),
+        }
+    }
+
+    fn finalize(self) -> Digest {
+        let (algorithm, value) = match self {
+            HmacState::Md5(mac) => (Algorithm::Md5, mac.finalize().into_bytes().to_vec()),
+            HmacState::Sha1(mac) => (Algorithm::Sha1, mac.finalize().into_bytes().to_vec()),
+            HmacState::Sha256(mac) => (Algorithm::Sha256, mac.finalize().into_bytes().to_vec()),
+            HmacState::Sha512(mac) => (Algorithm::Sha512, mac.finalize().into_bytes().to_vec()),
+        };
+        Digest { algorithm, value }
+    }
+}
+
+// ダイジェスト（JSON では16進数の文字列になる）
+#[derive(Debug, Clone, Serialize)]
+pub struct Digest {
+    pub algorithm: Algorithm,
+    #[serde(serialize_with = "serialize_hex")]
+    pub value: Vec<u8>,
+}
+
+fn serialize_hex<S: Serializer>(value: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&hex::encode(value))
+}
+
+fn serialize_digests<S: Serializer>(digests: &[Digest], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(digests.iter().map(|digest| (digest.algorithm.name(), hex::encode(&digest.value))))
+}
+
+// 1つの入力のハッシュ値
+#[derive(Debug, Serialize)]
+pub struct Digests {
+    pub size: u64,
+    // アルゴリズム名をキーにしたオブジェクトとして返す
+    #[serde(serialize_with = "serialize_digests")]
+    pub digests: Vec<Digest>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hmac: Option<Digest>,
+}
+
+// すべてのアルゴリズムのハッシュ値を同時に計算する（データはチャンクごとに渡す）
+pub struct Hasher {
+    size: u64,
+    md5: Md5,
+    sha1: Sha1,
+    sha256: Sha256,
+    sha512: Sha512,
+    blake3: blake3::Hasher,
+    crc32: crc32fast::Hasher,
+    hmac: Option<HmacState>,
+}
+
+impl Hasher {
+    pub fn new(hmac: Option<(Algorithm, &[u8])>) -> Result<Self> {
+        Ok(Hasher {
+            size: 0,
+            md5: Md5::new(),
+            sha1: Sha1::new(),
+            sha256: Sha256::new(),
+            sha512: Sha512::new(),
+            blake3: blake3::Hasher::new(),
+            crc32: crc32fast::Hasher::new(),
+            hmac: hmac.map(|(algorithm, key)| HmacState::new(algorithm, key)).transpose()?,
+        })
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.size += data.len() as u64;
+        self.md5.update(data);
+        self.sha1.update(data);
+        self.sha256.update(data);
+        self.sha512.update(data);
+        self.blake3.update(data);
+        self.crc32.update(data);
+        if let Some(hmac) = &mut self.hmac {
+            hmac.update(data);
+        }
+    }
+
+    pub fn finalize(self) -> Digests {
+        let digests = vec![
+            Digest { algorithm: Algorithm::Md5, value: self.md5.finalize().to_vec() },
+            Digest { algorithm: Algorithm::Sha1, value: self.sha1.finalize().to_vec() },
+            Digest { algorithm: Algorithm::Sha256, value: self.sha256.finalize().to_vec() },
+            Digest { algorithm: Algorithm::Sha512, value: self.sha512.finalize().to_vec() },
+            Digest { algorithm: Algorithm::Blake3, value: self.blake3.finalize().as_bytes().to_vec() },
+            Digest { algorithm: Algorithm::Crc32, value: self.crc32.finalize().to_be_bytes().to_vec() },
+        ];
+        Digests { size: self.size, digests, hmac: self.hmac.map(HmacState::finalize) }
+    }
+}
+
+// 照合する値（"sha256=..." のようにアルゴリズムを指定した場合はそのアルゴリズムだけと比べる）
+#[derive(Debug, Clone)]
+pub struct Expected {
+    pub algorithm: Option<Algorithm>,
+    pub value: Vec<u8>,
+}
+
+impl FromStr for Expected {
+    type Err = anyhow::Error;
+
+    // 16進数（大文字・小文字を問わない）か base64 で指定する
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let (algorithm, value) = match s.split_once('=') {
+            Some((prefix, rest)) if prefix.parse::<Algorithm>().is_ok() => (prefix.parse().ok(), rest.trim()),
+            _ => (None, s),
+        };
+        let value = if !value.is_empty() && value.len() % 2 == 0 && value.bytes().all(|b| b.is_ascii_hexdigit()) {
+            hex::decode(value)?
+        } else {
+            base64::engine::general_purpose::STANDARD
+                .decode(value)
+                .map_err(|_| anyhow!("Expected digest must be hex or base64"))?
+        };
+        if value.is_empty() {
+            return Err(anyhow!("Expected digest is empty"));
+        }
+        Ok(Expected { algorithm, value })
+    }
+}
+
+// 照合結果（どのアルゴリズムのダイジェストと一致したか）
+#[derive(Debug, Serialize)]
+pub struct Verification {
+    pub matched: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub algorithm: Option<Algorithm>,
+    pub hmac: bool,
+}
+
+impl Digests {
+    // 期待値と定数時間で比較する（HMAC を優先し、途中で打ち切らずにすべて比べる）
+    pub fn verify(&self, expected: &Expected) -> Verification {
+        let candidates = self
+            .hmac
+            .iter()
+            .map(|digest| (digest, true))
+            .chain(self.digests.iter().map(|digest| (digest, false)))
+            .filter(|(digest, _)| expected.algorithm.is_none_or(|algorithm| algorithm == digest.algorithm));
+
+        let mut found = None;
+        for (digest, hmac) in candidates {
+            let equal = digest.value.len() == expected.value.len() && bool::from(digest.value.ct_eq(&expected.value));
+            if equal && found.is_none() {
+                found = Some((digest.algorithm, hmac));
+            }
+        }
+
+        match found {
+            Some((algorithm, hmac)) => Verification { matched: true, algorithm: Some(algorithm), hmac },
+            None => Verification { matched: false, algorithm: None, hmac: false },
+        }
+    }
+}
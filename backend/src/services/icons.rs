@@ -0,0 +1,184 @@
+use anyhow::{Result, anyhow};
+use std::io::{Cursor, Write};
+use image::{ColorType, DynamicImage, GenericImageView, ImageOutputFormat, Rgba, RgbaImage};
+use image::codecs::ico::{IcoEncoder, IcoFrame};
+use image::imageops::{self, FilterType};
+use serde_json::json;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+// favicon.ico に含める解像度
+pub const FAVICON_SIZES: [u32; 3] = [16, 32, 48];
+// Apple touch icon の解像度（iPhone / iPad Pro / iPad）
+const APPLE_TOUCH_SIZES: [u32; 3] = [180, 167, 152];
+// PWA用アイコンの解像度
+const PWA_SIZES: [u32; 2] = [192, 512];
+// マスカブルアイコンの解像度
+const MASKABLE_SIZE: u32 = 512;
+
+// アイコンパッケージの設定
+#[derive(Debug, Clone)]
+pub struct IconOptions {
+    pub name: String,
+    pub short_name: String,
+    pub theme_color: String,
+    pub background_color: Rgba<u8>,
+    // マスカブルアイコンのセーフゾーン（中央の円の直径の割合、既定は80%）
+    pub safe_zone: f32,
+}
+
+impl Default for IconOptions {
+    fn default() -> Self {
+        IconOptions {
+            name: "App".to_string(),
+            short_name: "App".to_string(),
+            theme_color: "#ffffff".to_string(),
+            background_color: Rgba([255, 255, 255, 255]),
+            safe_zone: 0.8,
+        }
+    }
+}
+
+// 複数解像度を含むICOをエンコード
+pub fn encode_ico(img: &DynamicImage, sizes: &[u32]) -> Result<Vec<u8>> {
+    let mut pngs = Vec::new();
+    for &size in sizes {
+        if size == 0 || size > 256 {
+            return Err(anyhow!("ICO size must be between 1 and 256: {}", size));
+        }
+        let resized = img.resize_exact(size, size, FilterType::Lanczos3).to_rgba8();
+        pngs.push((size, resized.into_raw()));
+    }
+
+    let frames = pngs
+        .iter()
+        .map(|(size, raw)| IcoFrame::as_png(raw, *size, *size, ColorType::Rgba8))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| anyhow!("Failed to encode ICO frame: {}", e))?;
+
+    let mut buffer = Vec::new();
+    IcoEncoder::new(&mut buffer)
+        .encode_images(&frames)
+        .map_err(|e| anyhow!("Failed to encode ICO: {}", e))?;
+    Ok(buffer)
+}
+
+// favicon・Apple touch icon・PWAアイコン・マスカブルアイコン・manifest.jsonを含むZIPを生成
+pub fn generate_icon_package(source: &DynamicImage, options: &IconOptions) -> Result<Vec<u8>> {
+    let source = square(source);
+    tracing::debug!("アイコン生成元: {}x{}", source.width(), source.height());
+
+    let mut files: Vec<(String, Vec<u8>)> = Vec::new();
+
+    // favicon
+    files.push(("favicon.ico".to_string(), encode_ico(&source, &FAVICON_SIZES)?));
+    for size in [16, 32] {
+        files.push((format!("favicon-{}x{}.png", size, size), encode_png(&resize(&source, size))?));
+    }
+
+    // Apple touch icon は透過部分が黒く表示されるため背景色で塗りつぶす
+    for size in APPLE_TOUCH_SIZES {
+        let icon = flatten(&resize(&source, size), options.background_color);
+        let name = if size == 180 {
+            "apple-touch-icon.png".to_string()
+        } else {
+            format!("apple-touch-icon-{}x{}.png", size, size)
+        };
+        files.push((name, encode_png(&icon)?));
+    }
+
+    // PWAアイコン
+    for size in PWA_SIZES {
+        files.push((format!("icon-{}x{}.png", size, size), encode_png(&resize(&source, size))?));
+    }
+
+    // マスカブルアイコン（セーフゾーン内に収まるよう余白を付ける）
+    let maskable = maskable_icon(&source, MASKABLE_SIZE, options.safe_zone, options.background_color);
+    files.push((format!("maskable-icon-{}x{}.png", MASKABLE_SIZE, MASKABLE_SIZE), encode_png(&maskable)?));
+
+    let manifest = manifest_fragment(options);
+    files.push(("manifest.json".to_string(), serde_json::to_vec_pretty(&manifest)?));
+
+    write_zip(&files)
+}
+
+// manifest.json の icons などのフラグメント
+pub fn manifest_fragment(options: &IconOptions) -> serde_json::Value {
+    let mut icons: Vec<serde_json::Value> = PWA_SIZES
+        .iter()
+        .map(|size| json!({
+            "src": format!("/icon-{}x{}.png", size, size),
+            "sizes": format!("{}x{}", size, size),
+            "type": "image/png",
+            "purpose": "any",
+        }))
+        .collect();
+    icons.push(json!({
+        "src": format!("/maskable-icon-{}x{}.png", MASKABLE_SIZE, MASKABLE_SIZE),
+        "sizes": format!("{}x{}", MASKABLE_SIZE, MASKABLE_SIZE),
+        "type": "image/png",
+        "purpose": "maskable",
+    }));
+
+    let [r, g, b, _] = options.background_color.0;
+    json!({
+        "name": options.name,
+        "short_name": options.short_name,
+        "icons": icons,
+        "theme_color": options.theme_color,
+        "background_color": format!("#{:02x}{:02x}{:02x}", r, g, b),
+        "display": "standalone",
+    })
+}
+
+// 正方形でない画像は透明の余白を付けて正方形にする
+fn square(img: &DynamicImage) -> DynamicImage {
+    let (width, height) = img.dimensions();
+    if width == height {
+        return img.clone();
+    }
+    let size = width.max(height);
+    let mut canvas = RgbaImage::from_pixel(size, size, Rgba([0, 0, 0, 0]));
+    imageops::overlay(&mut canvas, &img.to_rgba8(), ((size - width) / 2) as i64, ((size - height) / 2) as i64);
+    DynamicImage::ImageRgba8(canvas)
+}
+
+fn resize(img: &DynamicImage, size: u32) -> DynamicImage {
+    img.resize_exact(size, size, FilterType::Lanczos3)
+}
+
+// 背景色の上に合成して不透明にする
+fn flatten(img: &DynamicImage, background: Rgba<u8>) -> DynamicImage {
+    let (width, height) = img.dimensions();
+    let mut canvas = RgbaImage::from_pixel(width, height, Rgba([background[0], background[1], background[2], 255]));
+    imageops::overlay(&mut canvas, &img.to_rgba8(), 0, 0);
+    DynamicImage::ImageRgba8(canvas)
+}
+
+// ロゴ（正方形）の四隅がセーフゾーンの円に収まるよう、一辺を直径の 1/√2 にする
+fn maskable_icon(img: &DynamicImage, size: u32, safe_zone: f32, background: Rgba<u8>) -> DynamicImage {
+    let inner = ((size as f32 * safe_zone.clamp(0.1, 1.0) / 2f32.sqrt()).round() as u32).max(1);
+    let logo = img.resize_exact(inner, inner, FilterType::Lanczos3).to_rgba8();
+    let mut canvas = RgbaImage::from_pixel(size, size, Rgba([background[0], background[1], background[2], 255]));
+    let offset = ((size - inner) / 2) as i64;
+    imageops::overlay(&mut canvas, &logo, offset, offset);
+    DynamicImage::ImageRgba8(canvas)
+}
+
+fn encode_png(img: &DynamicImage) -> Result<Vec<u8>> {
+    let mut buffer = Cursor::new(Vec::new());
+    img.write_to(&mut buffer, ImageOutputFormat::Png)
+        .map_err(|e| anyhow!("Failed to encode PNG: {}", e))?;
+    Ok(buffer.into_inner())
+}
+
+fn write_zip(files: &[(String, Vec<u8>)]) -> Result<Vec<u8>> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+    for (name, data) in files {
+        tracing::debug!("ZIPに追加: {} ({} バイト)", name, data.len());
+        zip.start_file(name.as_str(), options)?;
+        zip.write_all(data)?;
+    }
+    Ok(zip.finish()?.into_inner())
+}
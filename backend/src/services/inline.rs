@@ -0,0 +1,57 @@
+use base64::Engine;
+use serde::Serialize;
+
+use crate::services::limits;
+
+// CSS・HTML にそのまま貼り付けられる画像の埋め込み表現
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InlineImage {
+    pub data_uri: String,
+    // background-image の宣言
+    pub css: String,
+    // <img> 要素（サイズが分かる場合は width・height を付ける）
+    pub img: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+}
+
+// 画像データからデータURIとスニペットを作る
+pub fn build(data: &[u8], mime_type: &str, alt: &str) -> InlineImage {
+    let data_uri = format!("data:{};base64,{}", mime_type, base64::engine::general_purpose::STANDARD.encode(data));
+    let dimensions = limits::probe_dimensions(data);
+
+    let css = format!("background-image: url(\"{}\");", data_uri);
+    let size_attributes = dimensions
+        .map(|(width, height)| format!(" width=\"{}\" height=\"{}\"", width, height))
+        .unwrap_or_default();
+    let img = format!("<img src=\"{}\" alt=\"{}\"{}>", data_uri, escape_attribute(alt), size_attributes);
+
+    InlineImage {
+        data_uri,
+        css,
+        img,
+        width: dimensions.map(|(width, _)| width),
+        height: dimensions.map(|(_, height)| height),
+    }
+}
+
+fn escape_attribute(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+// データURIが埋め込みの目安サイズを超えた場合の警告
+pub fn budget_warning(length: usize, budget: usize) -> Option<String> {
+    (length > budget).then(|| {
+        format!(
+            "The data URI is {} bytes, which exceeds the inline budget of {} bytes; consider serving the image as a separate file",
+            length, budget
+        )
+    })
+}
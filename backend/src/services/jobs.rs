@@ -0,0 +1,381 @@
+use base64::Engine;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::{broadcast, OwnedSemaphorePermit, Semaphore};
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+use crate::config;
+use crate::services::converter::Stage;
+use crate::services::limits::LimitError;
+use crate::services::pool::{self, PoolError};
+
+// 1ファイル分の処理（ブロッキングスレッドで実行し、デコード・エンコードの開始を3番目の引数に通知する）
+pub type Processor = Arc<dyn Fn(&str, &[u8], &dyn Fn(Stage)) -> anyhow::Result<FileOutput> + Send + Sync>;
+
+// 処理結果のファイル
+pub struct FileOutput {
+    pub name: String,
+    pub mime_type: &'static str,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    // すべてのファイルの処理が終わった（失敗したファイルを含む）
+    Completed,
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileStatus {
+    // 受け付けて処理を待っている
+    Received,
+    Decoding,
+    Encoding,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+impl FileStatus {
+    pub fn name(self) -> &'static str {
+        match self {
+            FileStatus::Received => "received",
+            FileStatus::Decoding => "decoding",
+            FileStatus::Encoding => "encoding",
+            FileStatus::Done => "done",
+            FileStatus::Failed => "failed",
+            FileStatus::Cancelled => "cancelled",
+        }
+    }
+}
+
+impl From<Stage> for FileStatus {
+    fn from(stage: Stage) -> Self {
+        match stage {
+            Stage::Decoding => FileStatus::Decoding,
+            Stage::Encoding => FileStatus::Encoding,
+        }
+    }
+}
+
+// 処理済みファイル（画像変換のレスポンスと同じ項目）
+#[derive(Debug, Clone, Serialize)]
+pub struct FileResult {
+    pub name: String,
+    pub url: String,
+    pub size: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileError {
+    pub code: &'static str,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileProgress {
+    pub index: usize,
+    pub original_name: String,
+    pub original_size: usize,
+    pub status: FileStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<FileResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<FileError>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Progress {
+    pub total: usize,
+    pub done: usize,
+    pub failed: usize,
+    pub cancelled: usize,
+    // 処理が終わったファイルの割合（0〜100）
+    pub percent: u8,
+}
+
+// GET /jobs/{id} で返すジョブの状態
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobSnapshot {
+    pub id: Uuid,
+    pub operation: &'static str,
+    pub status: JobStatus,
+    // 作成日時（UNIX時間、秒）
+    pub created_at: u64,
+    pub progress: Progress,
+    pub files: Vec<FileProgress>,
+}
+
+// 1ファイルの状態が変わったときのイベント
+#[derive(Debug, Clone, Serialize)]
+pub struct FileEvent {
+    #[serde(flatten)]
+    pub file: FileProgress,
+    pub progress: Progress,
+}
+
+// ジョブが終わったときのイベント
+#[derive(Debug, Clone, Serialize)]
+pub struct FinishedEvent {
+    pub status: JobStatus,
+    pub progress: Progress,
+}
+
+// GET /jobs/{id}/events で配信する進捗イベント
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum JobEvent {
+    File(FileEvent),
+    Finished(FinishedEvent),
+}
+
+impl JobEvent {
+    // SSE のイベント名（ファイルの状態名、ジョブの終了は "end"）
+    pub fn name(&self) -> &'static str {
+        match self {
+            JobEvent::File(event) => event.file.status.name(),
+            JobEvent::Finished(_) => "end",
+        }
+    }
+}
+
+struct JobState {
+    status: JobStatus,
+    files: Vec<FileProgress>,
+    finished_at: Option<Instant>,
+}
+
+pub struct Job {
+    pub id: Uuid,
+    pub operation: &'static str,
+    created_at: u64,
+    cancel: CancellationToken,
+    state: Mutex<JobState>,
+    events: broadcast::Sender<JobEvent>,
+}
+
+fn progress(files: &[FileProgress]) -> Progress {
+    let count = |status| files.iter().filter(|f| f.status == status).count();
+    let total = files.len();
+    let (done, failed, cancelled) = (count(FileStatus::Done), count(FileStatus::Failed), count(FileStatus::Cancelled));
+    let percent = ((done + failed + cancelled) * 100).checked_div(total).unwrap_or(100) as u8;
+    Progress { total, done, failed, cancelled, percent }
+}
+
+impl Job {
+    pub fn snapshot(&self) -> JobSnapshot {
+        let state = self.state.lock().unwrap();
+        JobSnapshot {
+            id: self.id,
+            operation: self.operation,
+            status: state.status,
+            created_at: self.created_at,
+            progress: progress(&state.files),
+            files: state.files.clone(),
+        }
+    }
+
+    // 以降のイベントを受け取る（replay より先に呼び出すと取りこぼしがない）
+    pub fn subscribe(&self) -> broadcast::Receiver<JobEvent> {
+        self.events.subscribe()
+    }
+
+    // 現在の状態をイベントとして返す（途中から購読した場合や、イベントを取りこぼした場合に使う）
+    pub fn replay(&self) -> Vec<JobEvent> {
+        let state = self.state.lock().unwrap();
+        let progress = progress(&state.files);
+        let mut events = state
+            .files
+            .iter()
+            .map(|file| JobEvent::File(FileEvent { file: file.clone(), progress: progress.clone() }))
+            .collect::<Vec<_>>();
+        if state.finished_at.is_some() {
+            events.push(JobEvent::Finished(FinishedEvent { status: state.status, progress }));
+        }
+        events
+    }
+
+    // 待機中のファイルを取り消す（処理中のファイルは最後まで処理する）
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+    }
+
+    fn update(&self, index: usize, status: FileStatus, result: Option<FileResult>, error: Option<FileError>) {
+        let mut state = self.state.lock().unwrap();
+        if status != FileStatus::Cancelled && state.status == JobStatus::Queued {
+            state.status = JobStatus::Running;
+        }
+        let file = &mut state.files[index];
+        file.status = status;
+        file.result = result;
+        file.error = error;
+
+        // 購読者がいない場合の送信エラーは無視する
+        let event = FileEvent { file: file.clone(), progress: progress(&state.files) };
+        let _ = self.events.send(JobEvent::File(event));
+    }
+
+    fn finish(&self) -> JobStatus {
+        let mut state = self.state.lock().unwrap();
+        state.status = if self.cancel.is_cancelled() { JobStatus::Cancelled } else { JobStatus::Completed };
+        state.finished_at = Some(Instant::now());
+        let _ = self.events.send(JobEvent::Finished(FinishedEvent { status: state.status, progress: progress(&state.files) }));
+        state.status
+    }
+
+    fn expired(&self, ttl: Duration) -> bool {
+        self.state.lock().unwrap().finished_at.is_some_and(|finished| finished.elapsed() > ttl)
+    }
+}
+
+#[derive(Debug)]
+pub enum JobError {
+    // 保持しているジョブが上限に達した
+    TooManyJobs(usize),
+}
+
+impl fmt::Display for JobError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JobError::TooManyJobs(max) => write!(f, "Too many jobs in progress (max {}), try again later", max),
+        }
+    }
+}
+
+impl std::error::Error for JobError {}
+
+// ジョブの一覧と、全ジョブで共有するファイル処理の同時実行数
+pub struct JobStore {
+    jobs: Mutex<HashMap<Uuid, Arc<Job>>>,
+    permits: Arc<Semaphore>,
+}
+
+static STORE: OnceLock<JobStore> = OnceLock::new();
+
+pub fn store() -> &'static JobStore {
+    STORE.get_or_init(|| JobStore {
+        jobs: Mutex::new(HashMap::new()),
+        permits: Arc::new(Semaphore::new(config::get().job_concurrency.max(1))),
+    })
+}
+
+impl JobStore {
+    // ジョブを登録してバックグラウンドで処理を始める
+    pub fn submit(&self, operation: &'static str, files: Vec<(String, Vec<u8>)>, processor: Processor) -> Result<Arc<Job>, JobError> {
+        let config = config::get();
+        let mut jobs = self.jobs.lock().unwrap();
+
+        // 保持期間を過ぎた完了済みのジョブを削除
+        let ttl = Duration::from_secs(config.job_ttl_secs);
+        jobs.retain(|_, job| !job.expired(ttl));
+        if jobs.len() >= config.max_jobs {
+            return Err(JobError::TooManyJobs(config.max_jobs));
+        }
+
+        let created_at = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        let progress = files
+            .iter()
+            .enumerate()
+            .map(|(index, (name, data))| FileProgress {
+                index,
+                original_name: name.clone(),
+                original_size: data.len(),
+                status: FileStatus::Received,
+                result: None,
+                error: None,
+            })
+            .collect();
+        // ファイルごとに数件のイベントが出るので、遅い購読者でも取りこぼしにくい大きさにする
+        let (events, _) = broadcast::channel(files.len() * 4 + 16);
+        let job = Arc::new(Job {
+            id: Uuid::new_v4(),
+            operation,
+            created_at,
+            cancel: CancellationToken::new(),
+            state: Mutex::new(JobState { status: JobStatus::Queued, files: progress, finished_at: None }),
+            events,
+        });
+        jobs.insert(job.id, job.clone());
+        tracing::info!("ジョブ登録: {} ({}, {}ファイル, 保持中{}件)", job.id, operation, files.len(), jobs.len());
+
+        tokio::spawn(run(job.clone(), files, processor, self.permits.clone()));
+        Ok(job)
+    }
+
+    pub fn get(&self, id: Uuid) -> Option<Arc<Job>> {
+        self.jobs.lock().unwrap().get(&id).cloned()
+    }
+}
+
+// ジョブ内のファイルを順番に処理枠へ割り当て、すべて終わったらジョブを完了にする
+async fn run(job: Arc<Job>, files: Vec<(String, Vec<u8>)>, processor: Processor, permits: Arc<Semaphore>) {
+    let mut tasks = Vec::new();
+    for (index, (name, data)) in files.into_iter().enumerate() {
+        // 処理枠が空くまで待つ（その間に取り消されたら残りのファイルは処理しない）
+        let permit = tokio::select! {
+            biased;
+            _ = job.cancel.cancelled() => None,
+            permit = permits.clone().acquire_owned() => permit.ok(),
+        };
+        match permit.filter(|_| !job.cancel.is_cancelled()) {
+            Some(permit) => tasks.push(tokio::spawn(process_file(job.clone(), index, name, data, processor.clone(), permit))),
+            None => job.update(index, FileStatus::Cancelled, None, None),
+        }
+    }
+    for task in tasks {
+        if let Err(e) = task.await {
+            tracing::error!("ジョブのタスクが異常終了: {} ({})", job.id, e);
+        }
+    }
+    let status = job.finish();
+    tracing::info!("ジョブ完了: {} ({:?})", job.id, status);
+}
+
+async fn process_file(job: Arc<Job>, index: usize, name: String, data: Vec<u8>, processor: Processor, permit: OwnedSemaphorePermit) {
+    tracing::info!("ジョブ {} のファイル {} の処理開始: {}", job.id, index + 1, name);
+    let file_name = name.clone();
+    let progress_job = job.clone();
+    let result = pool::pool()
+        .run_queued(move || processor(&file_name, &data, &|stage| progress_job.update(index, stage.into(), None, None)))
+        .await;
+    drop(permit);
+
+    match result {
+        Ok(Ok(output)) => {
+            let url = format!(
+                "data:{};base64,{}",
+                output.mime_type,
+                base64::engine::general_purpose::STANDARD.encode(&output.data)
+            );
+            let result = FileResult { name: output.name, url, size: output.data.len() };
+            job.update(index, FileStatus::Done, Some(result), None);
+        },
+        Ok(Err(e)) => {
+            tracing::error!("ジョブ {} のファイル処理エラー - ファイル: '{}', エラー: {:?}", job.id, name, e);
+            // デコードの上限を超えた場合は同期処理と同じエラーコードを返す
+            let code = e.downcast_ref::<LimitError>().map_or("processing_failed", LimitError::code);
+            let error = FileError { code, message: e.to_string() };
+            job.update(index, FileStatus::Failed, None, Some(error));
+        },
+        Err(e) => {
+            tracing::error!("ジョブ {} のファイル処理が異常終了 - ファイル: '{}', エラー: {}", job.id, name, e);
+            let message = match e {
+                PoolError::Panicked(_) => "Processing aborted unexpectedly".to_string(),
+                e => e.to_string(),
+            };
+            let error = FileError { code: "internal_error", message };
+            job.update(index, FileStatus::Failed, None, Some(error));
+        },
+    }
+}
@@ -0,0 +1,401 @@
+use std::fmt;
+use std::str::FromStr;
+use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::Serialize;
+use serde_json::{Map, Number, Value};
+
+// 入出力のデータ形式
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DataFormat {
+    Json,
+    Yaml,
+    Toml,
+    // オブジェクトの配列のみ
+    Csv,
+}
+
+impl DataFormat {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DataFormat::Json => "JSON",
+            DataFormat::Yaml => "YAML",
+            DataFormat::Toml => "TOML",
+            DataFormat::Csv => "CSV",
+        }
+    }
+}
+
+impl FromStr for DataFormat {
+    type Err = DocumentError;
+
+    fn from_str(s: &str) -> Result<Self, DocumentError> {
+        match s.trim().to_lowercase().as_str() {
+            "json" => Ok(DataFormat::Json),
+            "yaml" | "yml" => Ok(DataFormat::Yaml),
+            "toml" => Ok(DataFormat::Toml),
+            "csv" => Ok(DataFormat::Csv),
+            _ => Err(DocumentError::Unsupported(format!("Unsupported format: {}", s))),
+        }
+    }
+}
+
+// JSON出力のインデント
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Indent {
+    Spaces(usize),
+    Tab,
+}
+
+impl Default for Indent {
+    fn default() -> Self {
+        Indent::Spaces(2)
+    }
+}
+
+impl FromStr for Indent {
+    type Err = DocumentError;
+
+    // "tab"・"4"・"space4"（フロントエンドのインデント指定）を受け付ける
+    fn from_str(s: &str) -> Result<Self, DocumentError> {
+        let s = s.trim().to_lowercase();
+        if s == "tab" {
+            return Ok(Indent::Tab);
+        }
+        s.trim_start_matches("space")
+            .parse::<usize>()
+            .ok()
+            .filter(|&n| n <= 8)
+            .map(Indent::Spaces)
+            .ok_or_else(|| DocumentError::Unsupported(format!("Invalid indent: {}", s)))
+    }
+}
+
+impl Indent {
+    fn as_bytes(&self) -> Vec<u8> {
+        match self {
+            Indent::Tab => b"\t".to_vec(),
+            Indent::Spaces(n) => vec![b' '; *n],
+        }
+    }
+}
+
+// 整形・変換の設定
+#[derive(Debug, Clone)]
+pub struct FormatOptions {
+    pub from: DataFormat,
+    pub to: DataFormat,
+    // JSON出力を1行にする
+    pub minify: bool,
+    // オブジェクトのキーを辞書順に並べる（指定がなければ入力の順序を保つ）
+    pub sort_keys: bool,
+    pub indent: Indent,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            from: DataFormat::Json,
+            to: DataFormat::Json,
+            minify: false,
+            sort_keys: false,
+            indent: Indent::default(),
+        }
+    }
+}
+
+// 文書の解析・変換エラー
+#[derive(Debug)]
+pub enum DocumentError {
+    // 構文エラー（行・列は1始まり）
+    Syntax {
+        format: DataFormat,
+        message: String,
+        line: Option<usize>,
+        column: Option<usize>,
+    },
+    // 出力形式で表現できない値
+    Conversion(String),
+    // 未対応の形式・オプション
+    Unsupported(String),
+}
+
+impl fmt::Display for DocumentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DocumentError::Syntax { format, message, line: Some(line), column: Some(column) } => {
+                write!(f, "Invalid {} at line {}, column {}: {}", format.name(), line, column, message)
+            },
+            DocumentError::Syntax { format, message, .. } => write!(f, "Invalid {}: {}", format.name(), message),
+            DocumentError::Conversion(message) | DocumentError::Unsupported(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for DocumentError {}
+
+// 入力を解析して整形・変換する
+pub fn format_document(input: &str, options: &FormatOptions) -> Result<String, DocumentError> {
+    tracing::debug!("文書の整形開始: {} バイト, {:?}", input.len(), options);
+    let mut value = parse(input, options.from)?;
+    if options.sort_keys {
+        sort_keys(&mut value);
+    }
+    serialize(&value, options)
+}
+
+// 指定形式の文字列を解析
+pub fn parse(input: &str, format: DataFormat) -> Result<Value, DocumentError> {
+    match format {
+        DataFormat::Json => parse_json(input),
+        DataFormat::Yaml => serde_yaml::from_str::<Value>(input).map_err(|e| {
+            let location = e.location();
+            syntax_error(format, e.to_string(), location.as_ref().map(|l| l.line()), location.as_ref().map(|l| l.column()))
+        }),
+        DataFormat::Toml => toml::from_str::<Value>(input).map_err(|e| {
+            let (line, column) = e.span().map(|span| line_column(input, span.start)).unzip();
+            syntax_error(format, e.message().to_string(), line, column)
+        }),
+        DataFormat::Csv => parse_csv(input),
+    }
+}
+
+// 厳密なJSONの解析（重複したキーもエラーにする）
+pub fn parse_json(input: &str) -> Result<Value, DocumentError> {
+    let mut deserializer = serde_json::Deserializer::from_str(input);
+    let value = StrictValue::deserialize(&mut deserializer)
+        .and_then(|value| deserializer.end().map(|_| value.0))
+        .map_err(|e| syntax_error(DataFormat::Json, json_error_message(&e), Some(e.line()), Some(e.column())))?;
+    Ok(value)
+}
+
+// 読み込みながらJSONを解析する（入力全体を文字列として保持しない）
+pub fn parse_json_reader<R: std::io::Read>(reader: R) -> Result<Value, DocumentError> {
+    let mut deserializer = serde_json::Deserializer::from_reader(std::io::BufReader::new(reader));
+    let value = StrictValue::deserialize(&mut deserializer)
+        .and_then(|value| deserializer.end().map(|_| value.0))
+        .map_err(|e| syntax_error(DataFormat::Json, json_error_message(&e), Some(e.line()), Some(e.column())))?;
+    Ok(value)
+}
+
+// serde_json のメッセージから位置情報を除く（位置は別の項目で返す）
+fn json_error_message(e: &serde_json::Error) -> String {
+    let message = e.to_string();
+    match message.rfind(" at line ") {
+        Some(pos) => message[..pos].to_string(),
+        None => message,
+    }
+}
+
+fn syntax_error(format: DataFormat, message: String, line: Option<usize>, column: Option<usize>) -> DocumentError {
+    DocumentError::Syntax { format, message, line, column }
+}
+
+// バイト位置を1始まりの行・列に変換
+fn line_column(input: &str, offset: usize) -> (usize, usize) {
+    let before = &input[..offset.min(input.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().map(|l| l.chars().count()).unwrap_or(0) + 1;
+    (line, column)
+}
+
+// 値を指定形式の文字列に変換
+pub fn serialize(value: &Value, options: &FormatOptions) -> Result<String, DocumentError> {
+    match options.to {
+        DataFormat::Json => {
+            if options.minify {
+                return serde_json::to_string(value).map_err(|e| DocumentError::Conversion(e.to_string()));
+            }
+            let indent = options.indent.as_bytes();
+            let formatter = serde_json::ser::PrettyFormatter::with_indent(&indent);
+            let mut output = Vec::new();
+            let mut serializer = serde_json::Serializer::with_formatter(&mut output, formatter);
+            value.serialize(&mut serializer).map_err(|e| DocumentError::Conversion(e.to_string()))?;
+            String::from_utf8(output).map_err(|e| DocumentError::Conversion(e.to_string()))
+        },
+        DataFormat::Yaml => serde_yaml::to_string(value).map_err(|e| DocumentError::Conversion(e.to_string())),
+        DataFormat::Toml => {
+            if !value.is_object() {
+                return Err(DocumentError::Conversion("TOML requires an object at the top level".to_string()));
+            }
+            if contains_null(value) {
+                return Err(DocumentError::Conversion("TOML cannot represent null values".to_string()));
+            }
+            toml::to_string_pretty(value).map_err(|e| DocumentError::Conversion(e.to_string()))
+        },
+        DataFormat::Csv => write_csv(value),
+    }
+}
+
+// オブジェクトのキーを再帰的に辞書順に並べる
+pub fn sort_keys(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            let mut entries = std::mem::take(map).into_iter().collect::<Vec<_>>();
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+            for (key, mut child) in entries {
+                sort_keys(&mut child);
+                map.insert(key, child);
+            }
+        },
+        Value::Array(items) => items.iter_mut().for_each(sort_keys),
+        _ => {},
+    }
+}
+
+fn contains_null(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::Array(items) => items.iter().any(contains_null),
+        Value::Object(map) => map.values().any(contains_null),
+        _ => false,
+    }
+}
+
+// CSVをオブジェクトの配列として読み込む（1行目はヘッダー、数値・真偽値は型を推定）
+fn parse_csv(input: &str) -> Result<Value, DocumentError> {
+    let mut reader = csv::ReaderBuilder::new().flexible(false).from_reader(input.as_bytes());
+    let headers = reader.headers().map_err(csv_error)?.clone();
+
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(csv_error)?;
+        let row = headers
+            .iter()
+            .zip(record.iter())
+            .map(|(key, field)| (key.to_string(), infer_csv_value(field)))
+            .collect::<Map<String, Value>>();
+        rows.push(Value::Object(row));
+    }
+    Ok(Value::Array(rows))
+}
+
+fn csv_error(e: csv::Error) -> DocumentError {
+    let line = e.position().map(|p| p.line() as usize);
+    let message = match e.kind() {
+        csv::ErrorKind::UnequalLengths { expected_len, len, .. } => {
+            format!("expected {} fields but found {}", expected_len, len)
+        },
+        _ => e.to_string(),
+    };
+    syntax_error(DataFormat::Csv, message, line, line.map(|_| 1))
+}
+
+fn infer_csv_value(field: &str) -> Value {
+    match field {
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        _ => {
+            // 先頭の0などが失われないよう、元の表記に戻る数値のみ変換する
+            if let Ok(n) = field.parse::<i64>() {
+                if n.to_string() == field {
+                    return Value::Number(n.into());
+                }
+            }
+            if let Some(n) = field.parse::<f64>().ok().and_then(Number::from_f64) {
+                if n.to_string() == field {
+                    return Value::Number(n);
+                }
+            }
+            Value::String(field.to_string())
+        },
+    }
+}
+
+// オブジェクトの配列をCSVに変換（列は最初に現れた順、入れ子の値はJSON文字列）
+fn write_csv(value: &Value) -> Result<String, DocumentError> {
+    let rows = value
+        .as_array()
+        .filter(|rows| rows.iter().all(Value::is_object))
+        .ok_or_else(|| DocumentError::Conversion("CSV output requires an array of objects".to_string()))?;
+
+    let mut columns: Vec<&str> = Vec::new();
+    for row in rows.iter().filter_map(Value::as_object) {
+        for key in row.keys() {
+            if !columns.contains(&key.as_str()) {
+                columns.push(key);
+            }
+        }
+    }
+
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    let csv_failed = |e: csv::Error| DocumentError::Conversion(e.to_string());
+    writer.write_record(&columns).map_err(csv_failed)?;
+    for row in rows.iter().filter_map(Value::as_object) {
+        let record = columns.iter().map(|column| match row.get(*column) {
+            None | Some(Value::Null) => String::new(),
+            Some(Value::String(s)) => s.clone(),
+            Some(other) => other.to_string(),
+        });
+        writer.write_record(record).map_err(csv_failed)?;
+    }
+    let output = writer.into_inner().map_err(|e| DocumentError::Conversion(e.to_string()))?;
+    String::from_utf8(output).map_err(|e| DocumentError::Conversion(e.to_string()))
+}
+
+// 重複したキーを拒否する serde_json::Value
+struct StrictValue(Value);
+
+impl<'de> Deserialize<'de> for StrictValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(StrictVisitor).map(StrictValue)
+    }
+}
+
+struct StrictVisitor;
+
+impl<'de> Visitor<'de> for StrictVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("any valid JSON value")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Value, E> {
+        Ok(Value::Bool(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Value, E> {
+        Ok(Value::Number(v.into()))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Value, E> {
+        Ok(Value::Number(v.into()))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Value, E> {
+        Ok(Number::from_f64(v).map(Value::Number).unwrap_or(Value::Null))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Value, E> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Value, E> {
+        Ok(Value::String(v))
+    }
+
+    fn visit_unit<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut items = Vec::new();
+        while let Some(StrictValue(item)) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(Value::Array(items))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<Value, A::Error> {
+        let mut map = Map::new();
+        while let Some(key) = access.next_key::<String>()? {
+            if map.contains_key(&key) {
+                return Err(de::Error::custom(format!("duplicate key \"{}\"", key)));
+            }
+            let StrictValue(value) = access.next_value()?;
+            map.insert(key, value);
+        }
+        Ok(Value::Object(map))
+    }
+}
@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+// フロントエンドの typing/data/keyboard-layouts.ts から移植したキー配列
+// （WINDOWS と MAC で文字キーは同じなので1つにまとめ、最下段の修飾キーの段は除く）
+const US_ROWS: [&[&str]; 4] = [
+    &["`", "1", "2", "3", "4", "5", "6", "7", "8", "9", "0", "-", "=", "Backspace"],
+    &["Tab", "q", "w", "e", "r", "t", "y", "u", "i", "o", "p", "[", "]", "\\"],
+    &["Caps", "a", "s", "d", "f", "g", "h", "j", "k", "l", ";", "'", "Enter"],
+    &["Shift", "z", "x", "c", "v", "b", "n", "m", ",", ".", "/", "Shift"],
+];
+
+const JP_ROWS: [&[&str]; 4] = [
+    &["半角/全角", "1", "2", "3", "4", "5", "6", "7", "8", "9", "0", "-", "^", "\\", "Backspace"],
+    &["Tab", "q", "w", "e", "r", "t", "y", "u", "i", "o", "p", "@", "[", "Enter"],
+    &["Caps", "a", "s", "d", "f", "g", "h", "j", "k", "l", ";", ":", "]"],
+    &["Shift", "z", "x", "c", "v", "b", "n", "m", ",", ".", "/", "\\", "Shift"],
+];
+
+// Shift を押したときの文字（英字は大文字になるので除く）
+const US_SHIFTED: &[(char, char)] = &[
+    ('`', '~'), ('1', '!'), ('2', '@'), ('3', '#'), ('4', '$'), ('5', '%'), ('6', '^'), ('7', '&'),
+    ('8', '*'), ('9', '('), ('0', ')'), ('-', '_'), ('=', '+'), ('[', '{'), (']', '}'), ('\\', '|'),
+    (';', ':'), ('\'', '"'), (',', '<'), ('.', '>'), ('/', '?'),
+];
+
+const JP_SHIFTED: &[(char, char)] = &[
+    ('1', '!'), ('2', '"'), ('3', '#'), ('4', '$'), ('5', '%'), ('6', '&'), ('7', '\''), ('8', '('),
+    ('9', ')'), ('-', '='), ('^', '~'), ('\\', '|'), ('@', '`'), ('[', '{'), (';', '+'), (':', '*'),
+    (']', '}'), (',', '<'), ('.', '>'), ('/', '?'), ('\\', '_'),
+];
+
+// 各段の左端からのずれ（キー幅単位、Tab・Caps・Shift の幅の違い）
+const ROW_OFFSETS: [f64; 4] = [0.0, 0.5, 0.75, 1.25];
+
+// 隣接するキーへの方向（段の差・左右）
+pub type Direction = (i8, i8);
+
+// キーボード上のキーの隣接関係
+#[derive(Debug)]
+pub struct KeyboardGraph {
+    pub name: &'static str,
+    adjacency: HashMap<char, Vec<(char, Direction)>>,
+    shifted: HashMap<char, char>,
+}
+
+impl KeyboardGraph {
+    fn build(name: &'static str, rows: &[&[&str]], shifted: &[(char, char)]) -> Self {
+        // 1文字のキーだけを位置付きで集める（JP配列の \ のように同じ文字が2か所にある場合もある）
+        let keys = rows
+            .iter()
+            .enumerate()
+            .flat_map(|(row, keys)| {
+                keys.iter().enumerate().filter_map(move |(index, key)| {
+                    let mut chars = key.chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(c), None) => Some((c, row as i8, index as f64 + ROW_OFFSETS[row])),
+                        _ => None,
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut adjacency: HashMap<char, Vec<(char, Direction)>> = HashMap::new();
+        for &(a, row_a, x_a) in &keys {
+            for &(b, row_b, x_b) in &keys {
+                let dx = x_b - x_a;
+                let neighbour = match row_b - row_a {
+                    0 => (dx.abs() - 1.0).abs() < f64::EPSILON,
+                    -1 | 1 => dx.abs() < 1.0,
+                    _ => false,
+                };
+                if neighbour && a != b {
+                    let entry = adjacency.entry(a).or_default();
+                    if !entry.iter().any(|(c, _)| *c == b) {
+                        entry.push((b, (row_b - row_a, dx.signum() as i8)));
+                    }
+                }
+            }
+        }
+
+        KeyboardGraph {
+            name,
+            adjacency,
+            shifted: shifted.iter().map(|&(base, shifted)| (shifted, base)).collect(),
+        }
+    }
+
+    // Shift を押して入力した文字を元のキーの文字に戻す（戻り値の bool は Shift の有無）
+    pub fn normalize(&self, c: char) -> (char, bool) {
+        if c.is_ascii_uppercase() {
+            (c.to_ascii_lowercase(), true)
+        } else if let Some(base) = self.shifted.get(&c) {
+            (*base, true)
+        } else {
+            (c, false)
+        }
+    }
+
+    // 2つの文字が隣り合うキーなら方向を返す
+    pub fn direction(&self, from: char, to: char) -> Option<Direction> {
+        let (from, _) = self.normalize(from);
+        let (to, _) = self.normalize(to);
+        self.adjacency.get(&from)?.iter().find(|(c, _)| *c == to).map(|(_, direction)| *direction)
+    }
+
+    pub fn key_count(&self) -> usize {
+        self.adjacency.len()
+    }
+
+    pub fn average_degree(&self) -> f64 {
+        if self.adjacency.is_empty() {
+            return 0.0;
+        }
+        self.adjacency.values().map(Vec::len).sum::<usize>() as f64 / self.adjacency.len() as f64
+    }
+}
+
+static GRAPHS: OnceLock<Vec<KeyboardGraph>> = OnceLock::new();
+
+// US配列・JIS配列の隣接関係
+pub fn graphs() -> &'static [KeyboardGraph] {
+    GRAPHS.get_or_init(|| {
+        vec![
+            KeyboardGraph::build("us", &US_ROWS, US_SHIFTED),
+            KeyboardGraph::build("jp", &JP_ROWS, JP_SHIFTED),
+        ]
+    })
+}
@@ -0,0 +1,200 @@
+use anyhow::Result;
+use image::io::Reader;
+use image::{DynamicImage, ImageFormat, ImageResult};
+use magick_rust::{MagickWand, ResourceType};
+use std::fmt;
+use std::io::{BufRead, Cursor, Seek};
+
+use crate::config;
+
+// 展開後の1画素あたりのバイト数の見積もり（RGBA 8bit）
+const BYTES_PER_PIXEL: u64 = 4;
+
+// 画像をデコードするときの上限（image クレート・mozjpeg・ImageMagick・resvg で共通、値は config.rs で設定する）
+#[derive(Debug, Clone, Copy)]
+pub struct DecodeLimits {
+    pub max_pixels: u64,
+    pub max_dimension: u32,
+    pub max_memory_bytes: u64,
+}
+
+impl DecodeLimits {
+    pub fn from_config() -> Self {
+        let config = config::get();
+        DecodeLimits {
+            max_pixels: config.max_pixels,
+            max_dimension: config.max_image_dimension,
+            max_memory_bytes: config.max_decode_memory_bytes,
+        }
+    }
+
+    // ヘッダーから読んだサイズを、展開する前に確認する
+    pub fn check(&self, width: u32, height: u32) -> Result<(), LimitError> {
+        if width > self.max_dimension || height > self.max_dimension {
+            return Err(LimitError::DimensionsTooLarge { width, height, max: self.max_dimension });
+        }
+        let pixels = width as u64 * height as u64;
+        if pixels > self.max_pixels {
+            return Err(LimitError::TooManyPixels { width, height, max: self.max_pixels });
+        }
+        if pixels * BYTES_PER_PIXEL > self.max_memory_bytes {
+            return Err(LimitError::TooMuchMemory { required: pixels * BYTES_PER_PIXEL, max: self.max_memory_bytes });
+        }
+        Ok(())
+    }
+
+    // image クレートのデコーダーに渡す上限
+    fn image_limits(&self) -> image::io::Limits {
+        let mut limits = image::io::Limits::default();
+        limits.max_image_width = Some(self.max_dimension);
+        limits.max_image_height = Some(self.max_dimension);
+        limits.max_alloc = Some(self.max_memory_bytes);
+        limits
+    }
+}
+
+// ImageMagick のリソース上限を設定する（起動時に一度呼び出す、policy.xml より大きな値は無視される）
+pub fn apply_magick_limits() {
+    let limits = DecodeLimits::from_config();
+    let resources = [
+        (ResourceType::Width, limits.max_dimension as u64),
+        (ResourceType::Height, limits.max_dimension as u64),
+        (ResourceType::Area, limits.max_pixels),
+        (ResourceType::Memory, limits.max_memory_bytes),
+    ];
+    for (resource, value) in resources {
+        if let Err(e) = MagickWand::set_resource_limit(resource, value) {
+            tracing::warn!("ImageMagickのリソース上限の設定に失敗: {:?} = {}, エラー: {}", resource, value, e);
+        }
+    }
+    tracing::info!("デコード上限: {:?}", limits);
+}
+
+// メモリ上の画像を、ヘッダーでサイズを確認してからデコードする（format が None の場合は内容から推定）
+pub fn decode_memory(data: &[u8], format: Option<ImageFormat>) -> Result<DynamicImage> {
+    decode_checked(|| match format {
+        Some(format) => Ok(Reader::with_format(Cursor::new(data), format)),
+        None => Ok(Reader::new(Cursor::new(data)).with_guessed_format()?),
+    })
+}
+
+// ファイルの画像を、ヘッダーでサイズを確認してからデコードする（image::open と同じく拡張子から形式を決める）
+pub fn decode_file(path: &str) -> Result<DynamicImage> {
+    decode_checked(|| Ok(Reader::open(path)?))
+}
+
+fn decode_checked<R: BufRead + Seek>(open: impl Fn() -> ImageResult<Reader<R>>) -> Result<DynamicImage> {
+    let limits = DecodeLimits::from_config();
+    let (width, height) = open()?.into_dimensions()?;
+    limits.check(width, height)?;
+
+    let mut reader = open()?;
+    reader.limits(limits.image_limits());
+    Ok(reader.decode()?)
+}
+
+// ImageMagick で読み込む前に、画素を展開せずにサイズだけを読んで確認する
+// サイズを読めない場合はここでは確認せず、ImageMagick のリソース上限に任せる
+pub fn ping_magick(ping: impl FnOnce(&MagickWand) -> Result<(), magick_rust::MagickError>) -> Result<(), LimitError> {
+    let wand = MagickWand::new();
+    if let Err(e) = ping(&wand) {
+        tracing::debug!("ImageMagickでサイズを取得できませんでした: {}", e);
+        return Ok(());
+    }
+    let (width, height) = (wand.get_image_width() as u32, wand.get_image_height() as u32);
+    tracing::debug!("ImageMagickで取得したサイズ: {}x{}", width, height);
+    DecodeLimits::from_config().check(width, height)
+}
+
+// アップロードを受け付ける上限
+#[derive(Debug, Clone, Copy)]
+pub struct UploadLimits {
+    pub max_file_bytes: usize,
+    pub max_files: usize,
+    pub decode: DecodeLimits,
+}
+
+impl UploadLimits {
+    pub fn from_config() -> Self {
+        let config = config::get();
+        UploadLimits {
+            max_file_bytes: config.max_file_bytes,
+            max_files: config.max_files,
+            decode: DecodeLimits::from_config(),
+        }
+    }
+
+    // count は受け取ろうとしているファイルを含めた数
+    pub fn check_file_count(&self, count: usize) -> Result<(), LimitError> {
+        if count > self.max_files {
+            return Err(LimitError::TooManyFiles { max: self.max_files });
+        }
+        Ok(())
+    }
+
+    pub fn check_file_size(&self, size: usize) -> Result<(), LimitError> {
+        if size > self.max_file_bytes {
+            return Err(LimitError::FileTooLarge { max: self.max_file_bytes });
+        }
+        Ok(())
+    }
+
+    // 小さなファイルに巨大なサイズが書かれた画像（解凍爆弾）を、変換を始める前に拒否する
+    pub fn check_dimensions(&self, width: u32, height: u32) -> Result<(), LimitError> {
+        self.decode.check(width, height)
+    }
+}
+
+// ヘッダーだけを読んで画像のサイズを取得する（途中までのデータでもヘッダーが揃っていれば取得でき、image クレートで読めない形式は None）
+pub fn probe_dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    image::io::Reader::new(Cursor::new(data)).with_guessed_format().ok()?.into_dimensions().ok()
+}
+
+#[derive(Debug)]
+pub enum LimitError {
+    FileTooLarge { max: usize },
+    TooManyFiles { max: usize },
+    TooManyPixels { width: u32, height: u32, max: u64 },
+    DimensionsTooLarge { width: u32, height: u32, max: u32 },
+    // 展開に必要なメモリの見積もりが上限を超えた
+    TooMuchMemory { required: u64, max: u64 },
+    // 圧縮されたストリーム（PDFのFlateDecodeなど）の展開後のサイズが上限を超えた
+    InflatedTooLarge { max: u64 },
+}
+
+impl fmt::Display for LimitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LimitError::FileTooLarge { max } => write!(f, "File exceeds the maximum size of {} bytes", max),
+            LimitError::TooManyFiles { max } => write!(f, "Too many files (max {} per request)", max),
+            LimitError::TooManyPixels { width, height, max } => {
+                write!(f, "Image is {}x{} pixels, which exceeds the limit of {} pixels", width, height, max)
+            },
+            LimitError::DimensionsTooLarge { width, height, max } => {
+                write!(f, "Image is {}x{} pixels, which exceeds the maximum width and height of {} pixels", width, height, max)
+            },
+            LimitError::TooMuchMemory { required, max } => {
+                write!(f, "Decoding the image needs about {} bytes of memory, which exceeds the limit of {} bytes", required, max)
+            },
+            LimitError::InflatedTooLarge { max } => {
+                write!(f, "A compressed stream expands to more than {} bytes", max)
+            },
+        }
+    }
+}
+
+impl LimitError {
+    // APIのエラーコード（同期処理のレスポンスとジョブのファイル単位のエラーで共通）
+    pub fn code(&self) -> &'static str {
+        match self {
+            LimitError::FileTooLarge { .. } => "file_too_large",
+            LimitError::TooManyFiles { .. } => "too_many_files",
+            LimitError::TooManyPixels { .. } => "too_many_pixels",
+            LimitError::DimensionsTooLarge { .. } => "dimensions_too_large",
+            LimitError::TooMuchMemory { .. } => "too_much_memory",
+            LimitError::InflatedTooLarge { .. } => "inflated_too_large",
+        }
+    }
+}
+
+impl std::error::Error for LimitError {}
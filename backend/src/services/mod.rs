@@ -1,9 +1,32 @@
 pub mod converter;
 pub mod compressor;
+pub mod diff;
+pub mod editor;
+pub mod encoding;
+pub mod formats;
+pub mod hash;
+pub mod icons;
+pub mod inline;
+pub mod jobs;
+pub mod json;
+pub mod keyboard;
+pub mod limits;
+pub mod password;
+pub mod pdf;
+pub mod pool;
+pub mod query;
+pub mod responsive;
+pub mod schema;
+pub mod strength;
+pub mod svg;
+pub mod thumbnail;
 
 #[cfg(test)]
 mod tests {
-    use super::converter;
+    use super::{converter, diff, editor, encoding, formats, hash, icons, inline, jobs, json, limits, password, pdf, pool, query, responsive, schema, strength, svg, thumbnail};
+    use base64::Engine;
+    use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+    use serde_json::{json, Value};
     use std::fs;
     use std::path::PathBuf;
 
@@ -26,11 +49,14 @@ mod tests {
         assert!(input_jpg.exists(), "テスト用入力ファイルが見つかりません");
 
         // JPGからWebPへの変換テスト
-        let result = converter::convert_image(
-            input_jpg.to_str().unwrap(),
-            output_webp.to_str().unwrap(),
-            "webp"
-        );
+        let result = converter::convert(
+            converter::Source::File(input_jpg.to_str().unwrap()),
+            "jpg",
+            "webp",
+            &converter::ConvertOptions::default(),
+            &|_| {},
+        )
+        .and_then(|data| Ok(fs::write(&output_webp, data)?));
 
         // テスト関数内に追加
         println!("出力ファイルの存在確認: {}", output_webp.exists());
@@ -52,4 +78,776 @@ mod tests {
         // 後片付け outputファイルを削除する
         let _ = fs::remove_file(output_webp);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_svg_minify_strips_unsafe_content() {
+        let input = r##"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" onload="alert(1)">
+  <!-- comment -->
+  <script>alert(1)</script>
+  <g><path d="M10.12345.5a5 5 0 011.5.5Z"/></g>
+  <image xlink:href="http://example.com/a.png"/>
+  <use xlink:href="#a"/>
+  <a href="#x"><set attributeName="href" to="javascript:alert(1)"/><text>link</text></a>
+  <a href="#y"><animate attributeName="xlink:href" values="#y;javascript:alert(2)"/></a>
+  <rect onclick="alert(3)"><set attributeName="onclick" to="alert(4)"/><set attributeName="fill" to="red"/></rect>
+  <a href="#z"><set attributeName="href" to="#w"/></a>
+</svg>"##;
+        let output = svg::minify(input.as_bytes(), &svg::MinifyOptions::default()).unwrap();
+
+        assert!(!output.contains("script") && !output.contains("onload") && !output.contains("comment"));
+        assert!(!output.contains("example.com"));
+        assert!(output.contains(r##"xlink:href="#a""##));
+        // アニメーションによる href・イベントハンドラの書き換えも除去する
+        assert!(!output.contains("javascript") && !output.contains("alert"), "{}", output);
+        assert!(output.contains(r#"<set attributeName="fill" to="red"/>"#) && output.contains(r##"<set attributeName="href" to="#w"/>"##));
+        // 円弧のフラグが数値として結合されないこと
+        assert!(output.contains(r#"<path d="M10.12 0.5a5 5 0 0 1 1.5 0.5Z"/>"#), "{}", output);
+    }
+
+    fn jq(document: &Value, expression: &str) -> Result<Vec<Value>, query::QueryError> {
+        let result = query::run(document, expression, query::QueryLanguage::Jq, 100)?;
+        Ok(result.matches.into_iter().map(|m| m.value).collect())
+    }
+
+    #[test]
+    fn test_jq_parser_reports_syntax_errors() {
+        let document = json!({});
+        for (expression, position) in [(".a |", 5), (".a[1", 5), ("{a: .b", 7), ("foo(.)", 1), (".a ]", 4)] {
+            match jq(&document, expression) {
+                Err(query::QueryError::Syntax { position: actual, .. }) => assert_eq!(actual, position, "{}", expression),
+                other => panic!("{} は構文エラーになるべき: {:?}", expression, other),
+            }
+        }
+        let nested = format!("{}.{}", "(".repeat(100), ")".repeat(100));
+        assert!(matches!(jq(&document, &nested), Err(query::QueryError::Syntax { .. })));
+        assert_eq!(query::QueryLanguage::detect("$.a"), query::QueryLanguage::JsonPath);
+        assert_eq!(query::QueryLanguage::detect(".a"), query::QueryLanguage::Jq);
+    }
+
+    #[test]
+    fn test_jq_evaluates_filters() {
+        let document = json!({
+            "items": [{"name": "a", "n": 1}, {"name": "b", "n": 2}, {"name": "c", "n": 3}],
+            "tags": ["x", "y"],
+        });
+        assert_eq!(jq(&document, ".items | map(.n) | add").unwrap(), vec![json!(6)]);
+        assert_eq!(jq(&document, "[.items[] | select(.n > 1) | .name]").unwrap(), vec![json!(["b", "c"])]);
+        assert_eq!(jq(&document, ".items[-1].name").unwrap(), vec![json!("c")]);
+        assert_eq!(jq(&document, ".tags[1:]").unwrap(), vec![json!(["y"])]);
+        assert_eq!(jq(&document, ".missing // \"default\"").unwrap(), vec![json!("default")]);
+        assert_eq!(jq(&document, ".items | sort_by(.name) | reverse | first | .n").unwrap(), vec![json!(3)]);
+        assert_eq!(jq(&document, "{tag: .tags[], count: (.items | length)}").unwrap(), vec![
+            json!({"tag": "x", "count": 3}),
+            json!({"tag": "y", "count": 3}),
+        ]);
+        assert_eq!(jq(&document, ".tags | has(0), has(5)").unwrap(), vec![json!(true), json!(false)]);
+        assert!(matches!(jq(&document, ".tags.name"), Err(query::QueryError::Runtime(_))));
+        assert_eq!(jq(&document, ".tags.name?").unwrap(), Vec::<Value>::new());
+
+        let result = query::run(&document, ".items[]", query::QueryLanguage::Jq, 2).unwrap();
+        assert_eq!(result.matches.len(), 2);
+        assert!(result.truncated);
+    }
+
+    #[test]
+    fn test_jq_rejects_exponential_copies() {
+        // 1段ごとに文書を倍にする式は、値の数が少なくても複製する要素数の上限で止める
+        let document = json!({"data": (0..100).collect::<Vec<_>>()});
+        let expression = vec!["[.,.]"; 40].join(" | ");
+        assert!(matches!(jq(&document, &expression), Err(query::QueryError::TooExpensive)));
+
+        let expression = vec!["[.[], .[]]"; 40].join(" | ");
+        assert!(matches!(jq(&document, &format!(".data | {}", expression)), Err(query::QueryError::TooExpensive)));
+
+        let expression = format!(".data | {} | add", vec!["[., .]"; 40].join(" | "));
+        assert!(matches!(jq(&document, &expression), Err(query::QueryError::TooExpensive)));
+    }
+
+    // 画素の R・G に x・y 座標を書き込んだ画像（切り抜き位置の確認用）
+    fn coordinate_image(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_fn(width, height, |x, y| Rgba([x as u8, y as u8, 0, 255])))
+    }
+
+    fn edit(img: DynamicImage, options: editor::EditOptions) -> DynamicImage {
+        editor::apply_edits(img, &options).unwrap()
+    }
+
+    #[test]
+    fn test_editor_crop_and_aspect() {
+        // はみ出す切り抜きは画像の範囲に収める
+        let crop = "10,10,50,50".parse::<editor::CropRect>().unwrap();
+        let img = edit(coordinate_image(40, 30), editor::EditOptions { crop: Some(crop), ..Default::default() });
+        assert_eq!(img.dimensions(), (30, 20));
+        assert_eq!(img.get_pixel(0, 0), Rgba([10, 10, 0, 255]));
+        let outside = "40,0,5,5".parse::<editor::CropRect>().unwrap();
+        assert!(editor::apply_edits(coordinate_image(40, 30), &editor::EditOptions { crop: Some(outside), ..Default::default() }).is_err());
+
+        // 横長の画像を 16:9 にすると幅を削り、gravity で残す位置が決まる
+        let aspect = Some("16:9".parse::<editor::AspectRatio>().unwrap());
+        for (gravity, x) in [("west", 0), ("center", 11), ("east", 22)] {
+            let gravity = gravity.parse::<editor::Gravity>().unwrap();
+            let img = edit(coordinate_image(200, 100), editor::EditOptions { aspect, gravity, ..Default::default() });
+            assert_eq!(img.dimensions(), (178, 100));
+            assert_eq!(img.get_pixel(0, 0), Rgba([x, 0, 0, 255]), "{:?}", gravity);
+        }
+
+        // 縦長の画像を 1:1 にすると高さを削る
+        let aspect = Some("1:1".parse::<editor::AspectRatio>().unwrap());
+        for (gravity, y) in [(editor::Gravity::North, 0), (editor::Gravity::Center, 50), (editor::Gravity::SouthEast, 100)] {
+            let img = edit(coordinate_image(100, 200), editor::EditOptions { aspect, gravity, ..Default::default() });
+            assert_eq!(img.dimensions(), (100, 100));
+            assert_eq!(img.get_pixel(0, 0), Rgba([0, y, 0, 255]), "{:?}", gravity);
+        }
+    }
+
+    #[test]
+    fn test_editor_rotates_clockwise() {
+        let red = Rgba([255, 0, 0, 255]);
+        let blue = Rgba([0, 0, 255, 255]);
+        // 左が赤・右が青の横長画像
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_fn(2, 1, |x, _| if x == 0 { red } else { blue }));
+
+        // 時計回りに90度回すと左端が上に来る
+        let rotated = edit(img.clone(), editor::EditOptions { rotate: 90.0, ..Default::default() });
+        assert_eq!(rotated.dimensions(), (1, 2));
+        assert_eq!((rotated.get_pixel(0, 0), rotated.get_pixel(0, 1)), (red, blue));
+
+        // 負の角度は反時計回り
+        let rotated = edit(img.clone(), editor::EditOptions { rotate: -90.0, ..Default::default() });
+        assert_eq!((rotated.get_pixel(0, 0), rotated.get_pixel(0, 1)), (blue, red));
+
+        // 任意角度はキャンバスを広げ、四隅を背景色で塗る
+        let background = editor::parse_color("#00ff00").unwrap();
+        let square = DynamicImage::ImageRgba8(RgbaImage::from_pixel(10, 10, red));
+        let rotated = edit(square, editor::EditOptions { rotate: 45.0, background, ..Default::default() });
+        assert_eq!(rotated.dimensions(), (15, 15));
+        assert_eq!(rotated.get_pixel(0, 0), background);
+        assert_eq!(rotated.get_pixel(7, 7), red);
+
+        // 細長い画像を斜めに回すとキャンバスが上限を超えるため、確保する前に拒否する
+        let line = DynamicImage::ImageRgba8(RgbaImage::from_pixel(16_000, 1, red));
+        let error = editor::apply_edits(line, &editor::EditOptions { rotate: 45.0, ..Default::default() }).unwrap_err();
+        assert!(matches!(error.downcast_ref::<limits::LimitError>(), Some(limits::LimitError::TooManyPixels { .. })), "{}", error);
+    }
+
+    #[test]
+    fn test_smart_thumbnail_picks_detailed_region() {
+        // 左 2/3 は単色、右 1/3 だけ市松模様の画像
+        let gray = Rgba([128, 128, 128, 255]);
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_fn(300, 100, |x, y| {
+            if x < 200 {
+                gray
+            } else if (x / 10 + y / 10) % 2 == 0 {
+                Rgba([0, 0, 0, 255])
+            } else {
+                Rgba([255, 255, 255, 255])
+            }
+        }));
+        let size = "100".parse::<thumbnail::ThumbnailSize>().unwrap();
+
+        let smart = thumbnail::generate_thumbnail(&img, size, thumbnail::CropMode::Smart).to_luma8();
+        let (min, max) = smart.pixels().fold((255, 0), |(min, max), p| (p[0].min(min), p[0].max(max)));
+        assert!(min < 20 && max > 235, "市松模様の領域が選ばれていない: {}..{}", min, max);
+
+        let center = thumbnail::generate_thumbnail(&img, size, thumbnail::CropMode::Center).to_luma8();
+        assert!(center.pixels().all(|p| p[0] == 128));
+    }
+
+    #[test]
+    fn test_ico_contains_requested_sizes() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(300, 200, Rgba([0, 128, 255, 255])));
+        let sizes = [16, 32, 48, 256];
+        let ico = icons::encode_ico(&img, &sizes).unwrap();
+
+        // ICONDIR（6バイト）のあとに16バイトのエントリが続き、幅・高さの 0 は 256 を表す
+        assert_eq!(&ico[..4], &[0, 0, 1, 0]);
+        assert_eq!(u16::from_le_bytes([ico[4], ico[5]]) as usize, sizes.len());
+        for (i, size) in sizes.iter().enumerate() {
+            let entry = &ico[6 + i * 16..];
+            assert_eq!((entry[0], entry[1]), ((*size % 256) as u8, (*size % 256) as u8));
+        }
+        assert_eq!(icons::FAVICON_SIZES, [16, 32, 48]);
+        assert!(icons::encode_ico(&img, &[0]).is_err());
+        assert!(icons::encode_ico(&img, &[257]).is_err());
+    }
+
+    #[test]
+    fn test_maskable_icon_fits_safe_zone() {
+        let logo = DynamicImage::ImageRgba8(RgbaImage::from_pixel(64, 64, Rgba([255, 0, 0, 255])));
+        let options = icons::IconOptions { background_color: Rgba([0, 0, 255, 255]), ..Default::default() };
+        let package = icons::generate_icon_package(&logo, &options).unwrap();
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(package)).unwrap();
+        let mut png = Vec::new();
+        std::io::Read::read_to_end(&mut archive.by_name("maskable-icon-512x512.png").unwrap(), &mut png).unwrap();
+        let icon = image::load_from_memory(&png).unwrap().to_rgba8();
+
+        // 512 * 0.8 / √2 ≈ 290px のロゴが中央に置かれ、四隅が直径 410px の円に収まる
+        let red = (0..512).filter(|&x| icon.get_pixel(x, 256)[0] > 128).count();
+        assert!((288..=292).contains(&red), "{}", red);
+        let (first, last) = (256 - red as u32 / 2, 256 + red as u32 / 2 - 1);
+        let radius = ((first as f32 - 255.5).powi(2) * 2.0).sqrt();
+        assert!(radius <= 512.0 * 0.8 / 2.0, "{}", radius);
+        assert_eq!(icon.get_pixel(first - 2, first - 2).0, [0, 0, 255, 255]);
+        assert_eq!(icon.get_pixel(last + 2, last + 2).0, [0, 0, 255, 255]);
+    }
+
+    fn patterns(password: &str) -> Vec<strength::Pattern> {
+        strength::analyze(password, &[]).sequence.into_iter().map(|m| m.pattern).collect()
+    }
+
+    #[test]
+    fn test_password_strength_scores() {
+        for weak in ["password", "123456", "qwerty", "aaaaaaaa"] {
+            assert_eq!(strength::analyze(weak, &[]).score, 0, "{}", weak);
+        }
+        for strong in ["k7$Vq9!mZp2#Lx8wRt", "correct-Horse7-battery!-staple9-Qz"] {
+            assert_eq!(strength::analyze(strong, &[]).score, 4, "{}", strong);
+        }
+        // ユーザー名そのものは弱い
+        assert_eq!(strength::analyze("tanaka-hanako", &["tanaka-hanako".to_string()]).score, 0);
+
+        // 生成したパスワードは最高評価になり、各文字種の最低文字数を満たす
+        let generated = password::generate_password(&password::PasswordOptions { length: 24, ..Default::default() }).unwrap();
+        assert_eq!(generated.value.chars().count(), 24);
+        assert!(generated.value.chars().any(|c| password::SYMBOLS.contains(c)));
+        assert_eq!(strength::analyze(&generated.value, &[]).score, 4);
+    }
+
+    #[test]
+    fn test_password_strength_patterns() {
+        assert!(patterns("zyxwvu").contains(&strength::Pattern::Sequence { ascending: false }));
+        assert!(patterns("mmmmmmmm").contains(&strength::Pattern::Repeat { repeat_count: 8 }));
+        assert!(patterns("13.05.1998").contains(&strength::Pattern::Date { year: 1998, separator: true }));
+        assert!(patterns("19980513").contains(&strength::Pattern::Date { year: 1998, separator: false }));
+
+        let analysis = strength::analyze("Qx7zyxwvu", &[]);
+        let sequence = analysis.sequence.iter().find(|m| matches!(m.pattern, strength::Pattern::Sequence { .. })).unwrap();
+        assert_eq!((sequence.start, sequence.end), (3, 9));
+    }
+
+    #[test]
+    fn test_decode_limits() {
+        let limits = limits::DecodeLimits { max_pixels: 100, max_dimension: 20, max_memory_bytes: 400 };
+        assert!(limits.check(20, 5).is_ok());
+        assert!(matches!(limits.check(21, 1), Err(limits::LimitError::DimensionsTooLarge { max: 20, .. })));
+        assert!(matches!(limits.check(1, 21), Err(limits::LimitError::DimensionsTooLarge { .. })));
+        assert!(matches!(limits.check(11, 10), Err(limits::LimitError::TooManyPixels { max: 100, .. })));
+        let limits = limits::DecodeLimits { max_memory_bytes: 399, ..limits };
+        assert!(matches!(limits.check(20, 5), Err(limits::LimitError::TooMuchMemory { required: 400, max: 399 })));
+
+        // 69バイトのPNGに 60000x60000 と書かれた画像は展開する前に拒否する
+        let bomb: &[u8] = &[
+            0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44, 0x52,
+            0x00, 0x00, 0xea, 0x60, 0x00, 0x00, 0xea, 0x60, 0x08, 0x02, 0x00, 0x00, 0x00, 0x0f, 0xb0, 0xe2,
+            0x15, 0x00, 0x00, 0x00, 0x0c, 0x49, 0x44, 0x41, 0x54, 0x78, 0x9c, 0x63, 0x60, 0xa0, 0x3d, 0x00,
+            0x00, 0x00, 0x64, 0x00, 0x01, 0x86, 0x64, 0x3c, 0x35, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4e,
+            0x44, 0xae, 0x42, 0x60, 0x82,
+        ];
+        assert_eq!(limits::probe_dimensions(bomb), Some((60000, 60000)));
+        let error = limits::decode_memory(bomb, None).unwrap_err();
+        assert!(error.downcast_ref::<limits::LimitError>().is_some(), "{}", error);
+    }
+
+    // 幅の異なる画像を1ページずつ並べたPDF（ページは MediaBox の幅で見分ける）
+    fn pdf_with_widths(widths: &[u32]) -> lopdf::Document {
+        let images = widths
+            .iter()
+            .map(|&width| pdf::PdfImage::from_image(&DynamicImage::ImageRgb8(image::RgbImage::new(width, 10))))
+            .collect::<Vec<_>>();
+        let options = pdf::PageOptions { size: pdf::PageSize::Image, margin_mm: 0.0, ..Default::default() };
+        let data = pdf::images_to_pdf(&images, &options).unwrap();
+        pdf::load_document(&data).unwrap()
+    }
+
+    fn page_widths(data: &[u8]) -> Vec<i64> {
+        let doc = pdf::load_document(data).unwrap();
+        doc.get_pages()
+            .values()
+            .map(|&id| {
+                let media_box = doc.get_dictionary(id).unwrap().get(b"MediaBox").unwrap().as_array().unwrap();
+                media_box[2].as_float().unwrap().round() as i64
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_pdf_split_merge_and_reorder() {
+        // 画像は96dpiとして配置されるため、幅40pxのページは30pt
+        let doc = pdf_with_widths(&[40, 80, 120]);
+
+        let ranges = pdf::parse_split_ranges("1-2,3", 3).unwrap();
+        let parts = pdf::split(&doc, &ranges).unwrap();
+        assert_eq!(parts.iter().map(|p| page_widths(p)).collect::<Vec<_>>(), vec![vec![30, 60], vec![90]]);
+        assert!(pdf::parse_split_ranges("1,4", 3).is_err());
+
+        let merged = pdf::merge(&[doc.clone(), pdf_with_widths(&[160])]).unwrap();
+        assert_eq!(page_widths(&merged), vec![30, 60, 90, 120]);
+
+        assert_eq!(page_widths(&pdf::reorder(&doc, &[3, 1]).unwrap()), vec![90, 30]);
+
+        let rotated = pdf::load_document(&pdf::rotate(&doc, &[2], -90).unwrap()).unwrap();
+        let angles = rotated
+            .get_pages()
+            .values()
+            .map(|&id| rotated.get_dictionary(id).unwrap().get(b"Rotate").unwrap().as_i64().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(angles, vec![0, 270, 0]);
+        assert!(pdf::rotate(&doc, &[1], 45).is_err());
+    }
+
+    // 文書内の最初の画像ストリーム
+    fn first_image(doc: &mut lopdf::Document) -> &mut lopdf::Stream {
+        doc.objects
+            .values_mut()
+            .filter_map(|object| object.as_stream_mut().ok())
+            .find(|stream| stream.dict.get(b"Subtype").and_then(lopdf::Object::as_name).ok() == Some(b"Image"))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_pdf_compress_recompresses_images() {
+        let photo = RgbaImage::from_fn(256, 256, |x, y| {
+            let noise = ((x * 7919 + y * 104_729) % 31) as u8;
+            Rgba([x as u8 / 2 + noise, y as u8 / 2 + noise, 128, 255])
+        });
+        let data = pdf::images_to_pdf(&[pdf::PdfImage::from_image(&DynamicImage::ImageRgba8(photo))], &pdf::PageOptions::default()).unwrap();
+        let (output, stats) = pdf::compress(&data, 60).unwrap();
+        assert_eq!(stats.images_recompressed, 1);
+        assert!(output.len() < data.len());
+        let mut doc = pdf::load_document(&output).unwrap();
+        assert_eq!(first_image(&mut doc).dict.get(b"Filter").unwrap().as_name().unwrap(), b"DCTDecode");
+
+        // Width・Height より大きく展開される画像は展開を打ち切り、再圧縮しない
+        let mut doc = pdf_with_widths(&[10]);
+        let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::fast());
+        std::io::Write::write_all(&mut encoder, &vec![0u8; 16 * 1024 * 1024]).unwrap();
+        first_image(&mut doc).set_content(encoder.finish().unwrap());
+        let mut bomb = Vec::new();
+        doc.save_to(&mut bomb).unwrap();
+        let (_, stats) = pdf::compress(&bomb, 60).unwrap();
+        assert_eq!(stats.images_recompressed, 0);
+    }
+
+    #[test]
+    fn test_pdf_stops_on_cyclic_parents() {
+        // MediaBox のないページの親が自分に戻る文書でも、親をたどる処理が終わること
+        let mut doc = pdf_with_widths(&[40]);
+        let page_id = *doc.get_pages().values().next().unwrap();
+        let node_id = doc.add_object(lopdf::dictionary! { "Type" => "Pages", "Parent" => page_id });
+        let page = doc.get_dictionary_mut(page_id).unwrap();
+        page.remove(b"MediaBox");
+        page.set("Parent", node_id);
+        let mut data = Vec::new();
+        doc.save_to(&mut data).unwrap();
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let _ = pdf::render_pages(&data, &pdf::RenderOptions::default());
+            // ページを別の文書に移すときの継承される属性の収集も同様
+            let parts = pdf::split(&pdf::load_document(&data).unwrap(), &[vec![1]]).map(|parts| parts.len());
+            sender.send(parts).unwrap();
+        });
+        let parts = receiver.recv_timeout(std::time::Duration::from_secs(30)).expect("処理が終わらない");
+        assert_eq!(parts.unwrap(), 1);
+    }
+
+    #[test]
+    fn test_password_generation_options() {
+        // 各文字種の最低文字数を満たし、見間違えやすい文字を除外する
+        let options = password::PasswordOptions {
+            length: 12,
+            symbols: false,
+            exclude_ambiguous: true,
+            min_digits: 4,
+            ..Default::default()
+        };
+        for _ in 0..20 {
+            let generated = password::generate_password(&options).unwrap();
+            assert_eq!(generated.value.chars().count(), 12);
+            assert!(generated.value.chars().filter(|c| c.is_ascii_digit()).count() >= 4);
+            assert!(!generated.value.chars().any(|c| password::AMBIGUOUS.contains(c) || password::SYMBOLS.contains(c)));
+        }
+        let digits_only = password::PasswordOptions {
+            length: 10,
+            lowercase: false,
+            uppercase: false,
+            symbols: false,
+            ..Default::default()
+        };
+        assert_eq!(password::generate_password(&digits_only).unwrap().entropy_bits, 33.2);
+        assert!(password::generate_password(&password::PasswordOptions { length: 3, ..Default::default() }).is_err());
+
+        // BIP-39 の2048語から選ぶため1語あたり11ビット
+        let options = password::PassphraseOptions { words: 5, separator: "_".to_string(), ..Default::default() };
+        let generated = password::generate_passphrase(&options).unwrap();
+        assert_eq!(generated.value.split('_').count(), 5);
+        assert_eq!(generated.entropy_bits, 55.0);
+        let long_separator = password::PassphraseOptions { separator: "-".repeat(password::MAX_SEPARATOR_CHARS + 1), ..Default::default() };
+        assert!(password::generate_passphrase(&long_separator).is_err());
+        assert!(password::generate_passphrase(&password::PassphraseOptions { words: password::MAX_WORDS + 1, ..Default::default() }).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_pool_queues_jobs_instead_of_rejecting() {
+        // 同時実行1・待ち行列0のプールで、実行中の処理が終わるまで枠を埋めておく
+        let pool: &'static pool::BlockingPool = Box::leak(Box::new(pool::BlockingPool::new(1, 0)));
+        let (release, wait) = std::sync::mpsc::channel::<()>();
+        let (started, wait_started) = tokio::sync::oneshot::channel();
+        let running = tokio::spawn(pool.run(move || {
+            started.send(()).unwrap();
+            wait.recv().unwrap()
+        }));
+        wait_started.await.unwrap();
+
+        // リクエスト用の run は断り、ジョブ用の run_queued は空くのを待って実行する
+        assert!(matches!(pool.run(|| ()).await, Err(pool::PoolError::Busy(0))));
+        let queued = tokio::spawn(pool.run_queued(|| 42));
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(!queued.is_finished());
+        release.send(()).unwrap();
+        running.await.unwrap().unwrap();
+        assert_eq!(queued.await.unwrap().unwrap(), 42);
+    }
+
+    #[test]
+    fn test_responsive_variants() {
+        assert_eq!(responsive::parse_widths("640w, 320,640").unwrap(), vec![320, 640]);
+        assert!(responsive::parse_widths("0,320").is_err());
+        assert!(responsive::parse_formats("jpg,tiff").is_err());
+        // 元画像より大きい幅は元の幅に置き換える
+        assert_eq!(responsive::target_widths(800, &[320, 640, 1280, 1920]), vec![320, 640, 800]);
+
+        let photo = DynamicImage::ImageRgb8(image::RgbImage::from_fn(400, 200, |x, y| {
+            image::Rgb([(x * 7 % 256) as u8, (y * 13 % 256) as u8, ((x ^ y) % 256) as u8])
+        }));
+        let formats = vec!["jpeg".to_string(), "png".to_string()];
+        let variants = responsive::generate_variants(&photo, "photo", &[100, 200, 800], &formats, 90).unwrap();
+        let sizes = variants.iter().map(|v| (v.file_name.as_str(), v.width, v.height)).collect::<Vec<_>>();
+        assert_eq!(sizes, vec![
+            ("photo-100w.jpg", 100, 50), ("photo-100w.png", 100, 50),
+            ("photo-200w.jpg", 200, 100), ("photo-200w.png", 200, 100),
+            ("photo-400w.jpg", 400, 200), ("photo-400w.png", 400, 200),
+        ]);
+        assert_eq!(image::load_from_memory(&variants[5].data).unwrap().dimensions(), (400, 200));
+
+        // 品質は各フォーマットのエンコードに反映される
+        let low = responsive::generate_variants(&photo, "photo", &[400], &formats[..1], 20).unwrap();
+        assert!(low[0].data.len() < variants[4].data.len());
+
+        let html = responsive::picture_html(&variants, &formats, "50vw", "a \"photo\"", "/img/");
+        assert!(html.contains(r#"<img src="/img/photo-400w.png" srcset="/img/photo-100w.png 100w, /img/photo-200w.png 200w, /img/photo-400w.png 400w""#), "{}", html);
+        assert!(html.contains(r#"<source type="image/jpeg""#) && html.contains("alt=\"a &quot;photo&quot;\""));
+    }
+
+    #[test]
+    fn test_convert_honors_quality() {
+        let photo = DynamicImage::ImageRgb8(image::RgbImage::from_fn(256, 256, |x, y| {
+            image::Rgb([(x * 7 % 256) as u8, (y * 13 % 256) as u8, ((x ^ y) % 256) as u8])
+        }));
+        let png = converter::encode_image(&photo, "png").unwrap();
+        let convert = |quality: Option<u8>| {
+            let options = converter::ConvertOptions { quality, ..Default::default() };
+            converter::convert(converter::Source::Memory(&png), "png", "jpg", &options, &|_| {}).unwrap()
+        };
+        let (low, default, high) = (convert(Some(20)), convert(None), convert(Some(100)));
+        assert!(low.len() < default.len() && default.len() < high.len(), "{} {} {}", low.len(), default.len(), high.len());
+        assert_eq!(image::load_from_memory(&low).unwrap().dimensions(), (256, 256));
+    }
+
+    #[test]
+    fn test_check_conversion_requires_read_delegate() {
+        assert!(formats::check_conversion("png", "jpeg", None).is_ok());
+        assert!(formats::check_conversion("", "png", None).is_ok());
+        assert!(formats::check_conversion("xyz", "png", None).is_err());
+
+        // HEIC・AVIF・JXLの入力は読み込みデリゲートがある場合のみ受け付ける
+        for (ext, magick_format) in [("heic", "HEIC"), ("avif", "AVIF"), ("jxl", "JXL")] {
+            let result = formats::check_conversion(ext, "png", None);
+            assert_eq!(result.is_ok(), formats::magick_supports(magick_format, false), "{}", ext);
+            if let Err(e) = result {
+                assert!(e.to_string().contains("is not available on this server"), "{}", e);
+            }
+        }
+    }
+
+    #[test]
+    fn test_schema_inference_and_validation() {
+        let examples = [
+            json!({"zeta": 1, "id": "3f2b8c1e-9a4d-4c7b-8e2f-1a2b3c4d5e6f", "tags": ["a"]}),
+            json!({"zeta": 2.5, "id": "0b1c2d3e-4f50-4a6b-9c7d-8e9f0a1b2c3d", "created": "2024-01-02"}),
+        ];
+        let inferred = schema::infer(&examples);
+        assert_eq!(inferred["$schema"], json!(schema::DRAFT_2020_12));
+        let properties = inferred["properties"].as_object().unwrap();
+        // プロパティは最初に現れた順に並ぶ
+        assert_eq!(properties.keys().collect::<Vec<_>>(), ["zeta", "id", "tags", "created"]);
+        assert_eq!(properties["zeta"]["type"], json!("number"));
+        assert_eq!(properties["id"]["format"], json!("uuid"));
+        assert_eq!(properties["tags"]["items"]["type"], json!("string"));
+        assert_eq!(inferred["required"], json!(["zeta", "id"]));
+
+        for example in &examples {
+            assert!(schema::validate(&inferred, example).unwrap().valid);
+        }
+        let report = schema::validate(&inferred, &json!({"zeta": "x", "tags": [1]})).unwrap();
+        assert!(!report.valid && !report.truncated);
+        let paths = report.errors.iter().map(|e| e.instance_path.as_str()).collect::<Vec<_>>();
+        assert!(paths.contains(&"") && paths.contains(&"/zeta") && paths.contains(&"/tags/0"), "{:?}", paths);
+        assert!(schema::validate(&json!({"type": 5}), &json!(1)).is_err());
+    }
+
+    #[test]
+    fn test_json_format_and_conversion() {
+        let options = |from: &str, to: &str| json::FormatOptions {
+            from: from.parse().unwrap(),
+            to: to.parse().unwrap(),
+            ..Default::default()
+        };
+
+        // キーの順序は入力のまま保たれ、sort_keys で再帰的に並べ替える
+        let input = r#"{"b": 1, "a": {"d": [true, null], "c": "x"}}"#;
+        let pretty = json::format_document(input, &options("json", "json")).unwrap();
+        assert_eq!(pretty, "{\n  \"b\": 1,\n  \"a\": {\n    \"d\": [\n      true,\n      null\n    ],\n    \"c\": \"x\"\n  }\n}");
+        let sorted = json::FormatOptions { minify: true, sort_keys: true, ..options("json", "json") };
+        assert_eq!(json::format_document(input, &sorted).unwrap(), r#"{"a":{"c":"x","d":[true,null]},"b":1}"#);
+        let tabs = json::FormatOptions { indent: "tab".parse().unwrap(), ..options("json", "json") };
+        assert!(json::format_document("[1]", &tabs).unwrap().contains("\n\t1\n"));
+        assert!("9".parse::<json::Indent>().is_err());
+
+        // 構文エラーと重複キーは行・列付きで返す
+        match json::parse_json("{\n  \"a\": 1,\n  \"a\": 2\n}") {
+            Err(json::DocumentError::Syntax { line: Some(3), .. }) => {},
+            other => panic!("duplicate key accepted: {:?}", other),
+        }
+        match json::parse_json("{\"a\": }") {
+            Err(json::DocumentError::Syntax { line: Some(1), column: Some(column), .. }) => assert!(column >= 6),
+            other => panic!("{:?}", other),
+        }
+
+        // CSV ⇔ JSON ⇔ YAML / TOML
+        let csv = "id,name,zip\n1,Alice,0123\n2,Bob,4567\n";
+        let rows = json::format_document(csv, &json::FormatOptions { minify: true, ..options("csv", "json") }).unwrap();
+        assert_eq!(rows, r#"[{"id":1,"name":"Alice","zip":"0123"},{"id":2,"name":"Bob","zip":4567}]"#);
+        assert_eq!(json::format_document(&rows, &options("json", "csv")).unwrap(), csv);
+        let yaml = json::format_document(input, &options("json", "yaml")).unwrap();
+        assert_eq!(json::parse(&yaml, json::DataFormat::Yaml).unwrap(), json::parse_json(input).unwrap());
+        assert!(matches!(json::format_document(input, &options("json", "toml")), Err(json::DocumentError::Conversion(_))));
+        let toml = json::format_document(r#"{"a": {"c": "x"}, "b": 1}"#, &options("json", "toml")).unwrap();
+        assert_eq!(json::parse(&toml, json::DataFormat::Toml).unwrap(), json!({"a": {"c": "x"}, "b": 1}));
+    }
+
+    // ジョブが終わるまで待って最終状態を返す
+    async fn wait_for_job(job: &jobs::Job) -> jobs::JobSnapshot {
+        let mut events = job.subscribe();
+        let finished = |job: &jobs::Job| job.replay().iter().any(|event| matches!(event, jobs::JobEvent::Finished(_)));
+        while !finished(job) {
+            let _ = tokio::time::timeout(std::time::Duration::from_secs(1), events.recv()).await;
+        }
+        job.snapshot()
+    }
+
+    #[tokio::test]
+    async fn test_job_reports_limit_error_codes() {
+        let processor: jobs::Processor = std::sync::Arc::new(|name: &str, data: &[u8], _: &dyn Fn(converter::Stage)| match name {
+            "huge.png" => Err(limits::LimitError::TooManyPixels { width: 10_000, height: 10_000, max: 1_000 }.into()),
+            "broken.png" => Err(anyhow::anyhow!("Failed to decode")),
+            _ => Ok(jobs::FileOutput { name: name.to_string(), mime_type: "image/png", data: data.to_vec() }),
+        });
+        let files = ["huge.png", "broken.png", "ok.png"].iter().map(|name| (name.to_string(), vec![1, 2, 3])).collect();
+        let job = jobs::store().submit("convert", files, processor).unwrap();
+        let snapshot = wait_for_job(&job).await;
+
+        assert_eq!(snapshot.status, jobs::JobStatus::Completed);
+        let codes = snapshot.files.iter().map(|f| f.error.as_ref().map(|e| e.code)).collect::<Vec<_>>();
+        assert_eq!(codes, [Some("too_many_pixels"), Some("processing_failed"), None]);
+        assert_eq!(snapshot.files[2].result.as_ref().unwrap().url, "data:image/png;base64,AQID");
+        assert_eq!((snapshot.progress.done, snapshot.progress.failed, snapshot.progress.percent), (1, 2, 100));
+    }
+
+    #[test]
+    fn test_json_diff_and_patch() {
+        let left = json!({"name": "a", "tags": ["x", "y"], "a/b": 1, "meta": {"keep": true, "drop": 1}});
+        let right = json!({"name": "b", "tags": ["x"], "a/b": 1, "meta": {"keep": true, "note": null}, "new": 2});
+        let report = diff::diff(&left, &right);
+
+        assert!(!report.identical);
+        assert_eq!((report.summary.added, report.summary.removed, report.summary.changed), (2, 2, 1));
+        let changes = report.changes.iter().map(|c| (c.kind, c.path.as_str())).collect::<Vec<_>>();
+        assert!(changes.contains(&(diff::ChangeKind::Changed, "/name")));
+        assert!(changes.contains(&(diff::ChangeKind::Removed, "/tags/1")));
+        assert!(changes.contains(&(diff::ChangeKind::Added, "/meta/note")));
+        assert!(diff::diff(&left, &left).identical);
+        assert_eq!(diff::diff(&json!({"a~/": 1}), &json!({})).changes[0].path, "/a~0~1");
+
+        // JSON Patch は right を再現し、null の値を含む merge patch は再現できないことを示す
+        let mut patched = left.clone();
+        diff::apply(&mut patched, &serde_json::to_value(&report.patch).unwrap(), diff::PatchKind::JsonPatch).unwrap();
+        assert_eq!(patched, right);
+        assert!(!report.merge_patch_exact);
+        assert_eq!(report.merge_patch["meta"], json!({"drop": null, "note": null}));
+        let exact = diff::diff(&json!({"a": 1, "b": {"c": 2}}), &json!({"b": {"c": 3}}));
+        assert!(exact.merge_patch_exact);
+        let mut merged = json!({"a": 1, "b": {"c": 2}});
+        diff::apply(&mut merged, &exact.merge_patch, diff::PatchKind::MergePatch).unwrap();
+        assert_eq!(merged, json!({"b": {"c": 3}}));
+
+        // 途中の操作が失敗した場合は文書を変更せず、失敗した操作の位置を返す
+        let patch = json!([{"op": "replace", "path": "/name", "value": "c"}, {"op": "remove", "path": "/missing"}]);
+        let mut document = left.clone();
+        match diff::apply(&mut document, &patch, diff::PatchKind::JsonPatch) {
+            Err(diff::PatchError::Failed { operation: 1, path, .. }) => assert_eq!(path, "/missing"),
+            other => panic!("{:?}", other),
+        }
+        assert_eq!(document, left);
+        assert!(matches!(diff::apply(&mut document, &json!([{"op": "jump"}]), diff::PatchKind::JsonPatch), Err(diff::PatchError::Invalid(_))));
+        assert_eq!(diff::PatchKind::detect(&patch), diff::PatchKind::JsonPatch);
+        assert_eq!("merge".parse::<diff::PatchKind>().unwrap(), diff::PatchKind::MergePatch);
+    }
+
+    fn digest_hex(digests: &hash::Digests, algorithm: hash::Algorithm) -> String {
+        hex::encode(&digests.digests.iter().find(|d| d.algorithm == algorithm).unwrap().value)
+    }
+
+    #[test]
+    fn test_hash_digests_and_hmac() {
+        let mut hasher = hash::Hasher::new(None).unwrap();
+        hasher.update(b"a");
+        hasher.update(b"bc");
+        let digests = hasher.finalize();
+        assert_eq!(digests.size, 3);
+        assert_eq!(digest_hex(&digests, hash::Algorithm::Md5), "900150983cd24fb0d6963f7d28e17f72");
+        assert_eq!(digest_hex(&digests, hash::Algorithm::Sha1), "a9993e364706816aba3e25717850c26c9cd0d89d");
+        assert_eq!(digest_hex(&digests, hash::Algorithm::Sha256), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        assert_eq!(digest_hex(&digests, hash::Algorithm::Crc32), "352441c2");
+        assert_eq!(serde_json::to_value(&digests).unwrap()["digests"]["md5"], json!("900150983cd24fb0d6963f7d28e17f72"));
+
+        // RFC 4231 / RFC 2202 のテストケース（鍵 "Jefe"）
+        let key = hash::KeyEncoding::Hex.decode("4a656665").unwrap();
+        assert_eq!(key, hash::KeyEncoding::Base64.decode("SmVmZQ==").unwrap());
+        let hmac = |algorithm| {
+            let mut hasher = hash::Hasher::new(Some((algorithm, &key))).unwrap();
+            hasher.update(b"what do ya want for nothing?");
+            hasher.finalize()
+        };
+        let sha256 = hmac(hash::Algorithm::Sha256);
+        assert_eq!(hex::encode(&sha256.hmac.as_ref().unwrap().value), "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843");
+        assert_eq!(hex::encode(&hmac(hash::Algorithm::Md5).hmac.unwrap().value), "750c783e6ab0b503eaa86e310a5db738");
+        assert!(hash::Hasher::new(Some((hash::Algorithm::Blake3, &key))).is_err());
+        assert!(hash::KeyEncoding::Hex.decode("xyz").is_err());
+
+        // 照合は HMAC を優先し、アルゴリズムの指定・大文字の16進数・base64 を受け付ける
+        let verify = |expected: &str| sha256.verify(&expected.parse().unwrap());
+        let matched = verify("5BDCC146BF60754E6A042426089575C75A003F089D2739839DEC58B964EC3843");
+        assert!(matched.matched && matched.hmac && matched.algorithm == Some(hash::Algorithm::Sha256));
+        let plain = hex::encode(&sha256.digests[0].value);
+        let matched = verify(&format!("md5={}", plain));
+        assert!(matched.matched && !matched.hmac);
+        assert!(!verify(&format!("sha1={}", plain)).matched);
+        let base64 = base64::engine::general_purpose::STANDARD.encode(&sha256.digests[0].value);
+        assert!(verify(&base64).matched);
+        assert!("".parse::<hash::Expected>().is_err() && "not base64!".parse::<hash::Expected>().is_err());
+    }
+
+    #[test]
+    fn test_encoding_round_trips() {
+        use encoding::{Charset, Codec};
+
+        let data = b"\xFB\xFF hello =\xE3\x81\x82/~";
+        for codec in [Codec::Base64, Codec::Base64Url, Codec::Percent, Codec::Hex, Codec::QuotedPrintable] {
+            for padding in [true, false] {
+                let encoded = codec.encode(data, padding);
+                assert_eq!(codec.decode(&encoded).unwrap(), data, "{} {}", codec.name(), encoded);
+            }
+        }
+        assert_eq!(Codec::Base64.encode(b"\xFB\xFF", true), "+/8=");
+        assert_eq!(Codec::Base64Url.encode(b"\xFB\xFF", false), "-_8");
+        assert_eq!(Codec::Base64.decode("+/8\n").unwrap(), b"\xFB\xFF");
+        assert_eq!(Codec::Percent.encode("a b/é~".as_bytes(), true), "a%20b%2F%C3%A9~");
+        assert!(matches!(Codec::Hex.decode("abc"), Err(encoding::EncodingError::Invalid(_))));
+        assert!("rot13".parse::<Codec>().is_err());
+
+        // 日本語の文字コードは往復でき、内容から推定できる
+        let text = "日本語のテキスト、カタカナ\n2行目";
+        for charset in [Charset::Utf8, Charset::ShiftJis, Charset::EucJp, Charset::Utf16Le, Charset::Utf16Be] {
+            let bytes = charset.encode(text, true).unwrap();
+            assert_eq!(charset.decode(&bytes).unwrap(), text, "{}", charset.name());
+            assert_eq!(Charset::detect(&bytes), Some(charset), "{}", charset.name());
+        }
+        assert_eq!(Charset::ShiftJis.encode("あ", true).unwrap(), b"\x82\xA0");
+        assert_eq!(Charset::EucJp.encode("あ", false).unwrap(), b"\xA4\xA2");
+        assert_eq!(Charset::Utf16Le.encode("A", true).unwrap(), b"\xFF\xFEA\x00");
+
+        // 表せない文字は行・列、読めないバイト列は位置を返す
+        match Charset::ShiftJis.encode("abc\nxy😀", false) {
+            Err(encoding::EncodingError::Unmappable { character: '😀', line: 2, column: 3, .. }) => {},
+            other => panic!("{:?}", other),
+        }
+        assert!(matches!(Charset::Utf8.decode(b"ab\xFFc"), Err(encoding::EncodingError::Malformed { offset: 2, .. })));
+        assert_eq!("windows_31j".parse::<Charset>().unwrap(), Charset::ShiftJis);
+    }
+
+    #[tokio::test]
+    async fn test_job_cancel_skips_waiting_files() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::Arc;
+
+        // 処理枠がすべて埋まるよう、同時実行数より2件多いファイルを投入する
+        let concurrency = crate::config::get().job_concurrency.max(1);
+        let released = Arc::new(AtomicBool::new(false));
+        let gate = released.clone();
+        let processor: jobs::Processor = Arc::new(move |name: &str, data: &[u8], progress: &dyn Fn(converter::Stage)| {
+            progress(converter::Stage::Decoding);
+            while !gate.load(Ordering::SeqCst) {
+                std::thread::sleep(std::time::Duration::from_millis(5));
+            }
+            Ok(jobs::FileOutput { name: name.to_string(), mime_type: "text/plain", data: data.to_vec() })
+        });
+        let files = (0..concurrency + 2).map(|i| (format!("{}.txt", i), vec![b'x'])).collect();
+        let job = jobs::store().submit("test", files, processor).unwrap();
+        let mut events = job.subscribe();
+        assert_eq!(job.snapshot().progress.total, concurrency + 2);
+
+        // 1件目の処理が始まったら取り消し、処理中のファイルは最後まで処理させる
+        loop {
+            let started = job.snapshot().files.iter().any(|f| f.status == jobs::FileStatus::Decoding);
+            if started {
+                break;
+            }
+            let _ = tokio::time::timeout(std::time::Duration::from_secs(1), events.recv()).await;
+        }
+        assert_eq!(job.snapshot().status, jobs::JobStatus::Running);
+        job.cancel();
+        released.store(true, Ordering::SeqCst);
+        let snapshot = wait_for_job(&job).await;
+
+        assert_eq!(snapshot.status, jobs::JobStatus::Cancelled);
+        assert!(snapshot.progress.done >= 1 && snapshot.progress.failed == 0, "{:?}", snapshot.progress);
+        assert!(snapshot.files[concurrency..].iter().all(|f| f.status == jobs::FileStatus::Cancelled));
+        assert_eq!(snapshot.progress.done + snapshot.progress.cancelled, concurrency + 2);
+        assert_eq!(snapshot.progress.percent, 100);
+        let last = job.replay().pop().unwrap();
+        assert_eq!(last.name(), "end");
+        assert!(matches!(last, jobs::JobEvent::Finished(jobs::FinishedEvent { status: jobs::JobStatus::Cancelled, .. })));
+    }
+
+    #[test]
+    fn test_inline_image_snippets() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(3, 2, Rgba([255, 0, 0, 255])));
+        let png = converter::encode_image(&img, "png").unwrap();
+        let inline = inline::build(&png, "image/png", "a <b> & \"c\"");
+
+        assert!(inline.data_uri.starts_with("data:image/png;base64,iVBORw0KGgo"));
+        let encoded = inline.data_uri.trim_start_matches("data:image/png;base64,");
+        assert_eq!(base64::engine::general_purpose::STANDARD.decode(encoded).unwrap(), png);
+        assert_eq!(inline.css, format!("background-image: url(\"{}\");", inline.data_uri));
+        assert_eq!(inline.img, format!("<img src=\"{}\" alt=\"a &lt;b&gt; &amp; &quot;c&quot;\" width=\"3\" height=\"2\">", inline.data_uri));
+        assert_eq!((inline.width, inline.height), (Some(3), Some(2)));
+
+        // サイズを読めない形式は width・height を付けない
+        let svg = inline::build(b"<svg xmlns='http://www.w3.org/2000/svg'/>", "image/svg+xml", "");
+        assert!(svg.img.ends_with("alt=\"\">") && svg.width.is_none());
+
+        assert!(inline::budget_warning(100, 100).is_none());
+        assert!(inline::budget_warning(101, 100).unwrap().contains("101 bytes"));
+    }
+}
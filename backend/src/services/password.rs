@@ -0,0 +1,180 @@
+use anyhow::{Result, anyhow};
+use rand::rngs::OsRng;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::Serialize;
+use std::sync::OnceLock;
+
+// 文字の種類（フロントエンドの PasswordGenerator.tsx と同じ文字集合）
+pub const LOWERCASE: &str = "abcdefghijklmnopqrstuvwxyz";
+pub const UPPERCASE: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+pub const DIGITS: &str = "0123456789";
+pub const SYMBOLS: &str = "!@#$%^&*()_+[]{}|;:,.<>?";
+// 見間違えやすい文字
+pub const AMBIGUOUS: &str = "0OoIl1|";
+
+pub const MAX_LENGTH: usize = 1024;
+pub const MAX_WORDS: usize = 64;
+// パスフレーズの区切り文字の最大文字数
+pub const MAX_SEPARATOR_CHARS: usize = 8;
+pub const MAX_COUNT: usize = 100;
+
+// パスフレーズ用の単語リスト（BIP-39 英語版、2048語）
+static WORDLIST: &str = include_str!("wordlist.txt");
+static WORDS: OnceLock<Vec<&'static str>> = OnceLock::new();
+
+fn words() -> &'static [&'static str] {
+    WORDS.get_or_init(|| WORDLIST.lines().map(str::trim).filter(|w| !w.is_empty()).collect())
+}
+
+// パスワード生成の設定（min_* は各文字種の最低文字数）
+#[derive(Debug, Clone)]
+pub struct PasswordOptions {
+    pub length: usize,
+    pub lowercase: bool,
+    pub uppercase: bool,
+    pub digits: bool,
+    pub symbols: bool,
+    pub exclude_ambiguous: bool,
+    pub min_lowercase: usize,
+    pub min_uppercase: usize,
+    pub min_digits: usize,
+    pub min_symbols: usize,
+}
+
+impl Default for PasswordOptions {
+    fn default() -> Self {
+        PasswordOptions {
+            length: 16,
+            lowercase: true,
+            uppercase: true,
+            digits: true,
+            symbols: true,
+            exclude_ambiguous: false,
+            min_lowercase: 1,
+            min_uppercase: 1,
+            min_digits: 1,
+            min_symbols: 1,
+        }
+    }
+}
+
+// パスフレーズ生成の設定
+#[derive(Debug, Clone)]
+pub struct PassphraseOptions {
+    pub words: usize,
+    pub separator: String,
+    pub capitalize: bool,
+    // ランダムな単語の後ろに数字を1つ付ける
+    pub include_number: bool,
+}
+
+impl Default for PassphraseOptions {
+    fn default() -> Self {
+        PassphraseOptions { words: 6, separator: "-".to_string(), capitalize: false, include_number: false }
+    }
+}
+
+// 生成結果（エントロピーは生成に使った乱数から求めた下限）
+#[derive(Debug, Serialize)]
+pub struct Generated {
+    pub value: String,
+    pub entropy_bits: f64,
+}
+
+// 有効な文字種ごとの文字と最低文字数
+fn character_classes(options: &PasswordOptions) -> Vec<(Vec<char>, usize)> {
+    [
+        (options.lowercase, LOWERCASE, options.min_lowercase),
+        (options.uppercase, UPPERCASE, options.min_uppercase),
+        (options.digits, DIGITS, options.min_digits),
+        (options.symbols, SYMBOLS, options.min_symbols),
+    ]
+    .into_iter()
+    .filter(|(enabled, _, _)| *enabled)
+    .map(|(_, chars, minimum)| {
+        let chars = chars.chars().filter(|c| !(options.exclude_ambiguous && AMBIGUOUS.contains(*c))).collect();
+        (chars, minimum)
+    })
+    .collect()
+}
+
+fn validate(options: &PasswordOptions, classes: &[(Vec<char>, usize)]) -> Result<()> {
+    if classes.is_empty() {
+        return Err(anyhow!("At least one character class must be enabled"));
+    }
+    if options.length == 0 || options.length > MAX_LENGTH {
+        return Err(anyhow!("Length must be between 1 and {}", MAX_LENGTH));
+    }
+    let required = classes.iter().map(|(_, minimum)| minimum).sum::<usize>();
+    if required > options.length {
+        return Err(anyhow!("Minimum character counts ({}) exceed the length ({})", required, options.length));
+    }
+    Ok(())
+}
+
+// 暗号論的に安全な乱数（OsRng）でパスワードを生成
+pub fn generate_password(options: &PasswordOptions) -> Result<Generated> {
+    let classes = character_classes(options);
+    validate(options, &classes)?;
+    let pool = classes.iter().flat_map(|(chars, _)| chars.iter().copied()).collect::<Vec<_>>();
+
+    // 各文字種の最低文字数を先に選び、残りを全体から選んでから並べ替える
+    let mut rng = OsRng;
+    let mut password = Vec::with_capacity(options.length);
+    let mut entropy_bits = 0.0;
+    for (chars, minimum) in &classes {
+        for _ in 0..*minimum {
+            password.push(chars[rng.gen_range(0..chars.len())]);
+        }
+        entropy_bits += *minimum as f64 * (chars.len() as f64).log2();
+    }
+    let remaining = options.length - password.len();
+    for _ in 0..remaining {
+        password.push(pool[rng.gen_range(0..pool.len())]);
+    }
+    entropy_bits += remaining as f64 * (pool.len() as f64).log2();
+    password.shuffle(&mut rng);
+
+    Ok(Generated { value: password.into_iter().collect(), entropy_bits: round_bits(entropy_bits) })
+}
+
+// 単語リストから diceware 方式でパスフレーズを生成
+pub fn generate_passphrase(options: &PassphraseOptions) -> Result<Generated> {
+    if options.words == 0 || options.words > MAX_WORDS {
+        return Err(anyhow!("Word count must be between 1 and {}", MAX_WORDS));
+    }
+    if options.separator.chars().count() > MAX_SEPARATOR_CHARS {
+        return Err(anyhow!("Separator must be at most {} characters", MAX_SEPARATOR_CHARS));
+    }
+    let words = words();
+    let mut rng = OsRng;
+
+    let mut chosen = (0..options.words)
+        .map(|_| {
+            let word = words[rng.gen_range(0..words.len())];
+            if options.capitalize { capitalize(word) } else { word.to_string() }
+        })
+        .collect::<Vec<_>>();
+    let mut entropy_bits = options.words as f64 * (words.len() as f64).log2();
+
+    if options.include_number {
+        let index = rng.gen_range(0..chosen.len());
+        chosen[index].push(char::from(b'0' + rng.gen_range(0..10u8)));
+        entropy_bits += 10f64.log2();
+    }
+
+    Ok(Generated { value: chosen.join(&options.separator), entropy_bits: round_bits(entropy_bits) })
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn round_bits(bits: f64) -> f64 {
+    (bits * 10.0).round() / 10.0
+}
@@ -0,0 +1,900 @@
+use anyhow::{Result, anyhow};
+use flate2::read::ZlibDecoder;
+use std::collections::HashSet;
+use std::io::Read;
+use std::str::FromStr;
+use image::{DynamicImage, GenericImageView, GrayImage, RgbImage};
+use lopdf::content::{Content, Operation};
+use lopdf::{dictionary, Dictionary, Document, Object, ObjectId, Stream};
+use magick_rust::{AlphaChannelOption, MagickWand, PixelWand};
+
+use crate::config;
+use crate::services::{compressor, converter, limits};
+use crate::services::limits::LimitError;
+
+// 1mmあたりのポイント数
+const POINTS_PER_MM: f32 = 72.0 / 25.4;
+// 画像のピクセルをポイントに換算する解像度
+const IMAGE_DPI: f32 = 96.0;
+// PDFの基準解像度（1pt = 1/72インチ）
+const PDF_DPI: f32 = 72.0;
+// レンダリング解像度の上限
+pub const MAX_RENDER_DPI: u32 = 600;
+
+// 用紙サイズ
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum PageSize {
+    A3,
+    #[default]
+    A4,
+    A5,
+    Letter,
+    Legal,
+    // 画像と同じ大きさ（96dpi換算）
+    Image,
+}
+
+impl PageSize {
+    // 縦向きの幅と高さ（ポイント）
+    fn dimensions(self) -> Option<(f32, f32)> {
+        match self {
+            PageSize::A3 => Some((297.0 * POINTS_PER_MM, 420.0 * POINTS_PER_MM)),
+            PageSize::A4 => Some((210.0 * POINTS_PER_MM, 297.0 * POINTS_PER_MM)),
+            PageSize::A5 => Some((148.0 * POINTS_PER_MM, 210.0 * POINTS_PER_MM)),
+            PageSize::Letter => Some((612.0, 792.0)),
+            PageSize::Legal => Some((612.0, 1008.0)),
+            PageSize::Image => None,
+        }
+    }
+}
+
+impl FromStr for PageSize {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "a3" => Ok(PageSize::A3),
+            "a4" => Ok(PageSize::A4),
+            "a5" => Ok(PageSize::A5),
+            "letter" => Ok(PageSize::Letter),
+            "legal" => Ok(PageSize::Legal),
+            "image" | "fit" => Ok(PageSize::Image),
+            _ => Err(anyhow!("Invalid page size: {}", s)),
+        }
+    }
+}
+
+// 用紙の向き
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Orientation {
+    Portrait,
+    Landscape,
+    // 画像の縦横比に合わせる
+    #[default]
+    Auto,
+}
+
+impl FromStr for Orientation {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "portrait" => Ok(Orientation::Portrait),
+            "landscape" => Ok(Orientation::Landscape),
+            "auto" => Ok(Orientation::Auto),
+            _ => Err(anyhow!("Invalid orientation: {}", s)),
+        }
+    }
+}
+
+// 余白内への画像の配置方法
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum FitMode {
+    // 縦横比を保って全体を収める
+    #[default]
+    Contain,
+    // 縦横比を保って余白内を埋める（はみ出した部分は切り取る）
+    Cover,
+    // 縦横比を無視して余白内に合わせる
+    Fill,
+    // 96dpi換算の原寸で中央に配置（はみ出した部分は切り取る）
+    Original,
+}
+
+impl FromStr for FitMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "contain" => Ok(FitMode::Contain),
+            "cover" => Ok(FitMode::Cover),
+            "fill" | "stretch" => Ok(FitMode::Fill),
+            "original" => Ok(FitMode::Original),
+            _ => Err(anyhow!("Invalid fit mode: {}", s)),
+        }
+    }
+}
+
+// 画像からPDFを作成する際のページ設定
+#[derive(Debug, Clone)]
+pub struct PageOptions {
+    pub size: PageSize,
+    pub orientation: Orientation,
+    pub margin_mm: f32,
+    pub fit: FitMode,
+}
+
+impl Default for PageOptions {
+    fn default() -> Self {
+        PageOptions {
+            size: PageSize::default(),
+            orientation: Orientation::default(),
+            margin_mm: 10.0,
+            fit: FitMode::default(),
+        }
+    }
+}
+
+// PDFに埋め込む画像
+pub struct PdfImage {
+    width: u32,
+    height: u32,
+    color_space: &'static str,
+    // DCTDecode（JPEGそのまま）または FlateDecode
+    filter: &'static str,
+    data: Vec<u8>,
+    // 透過情報（グレースケール）
+    alpha: Option<Vec<u8>>,
+}
+
+impl PdfImage {
+    // アップロードされた画像を埋め込み用に変換（JPEGは再圧縮せずにそのまま埋め込む）
+    pub fn from_upload(data: &[u8], input_ext: &str) -> Result<Self> {
+        let img = converter::decode_image(data, input_ext)?;
+        let (width, height) = img.dimensions();
+
+        if let Some(components) = jpeg_components(data) {
+            if components == 1 || components == 3 {
+                tracing::debug!("JPEGをそのまま埋め込み: {}x{} ({}チャンネル)", width, height, components);
+                return Ok(PdfImage {
+                    width,
+                    height,
+                    color_space: if components == 1 { "DeviceGray" } else { "DeviceRGB" },
+                    filter: "DCTDecode",
+                    data: data.to_vec(),
+                    alpha: None,
+                });
+            }
+        }
+        Ok(Self::from_image(&img))
+    }
+
+    pub fn from_image(img: &DynamicImage) -> Self {
+        let (width, height) = img.dimensions();
+        let alpha = img.color().has_alpha().then(|| {
+            img.to_rgba8().pixels().map(|p| p[3]).collect::<Vec<u8>>()
+        });
+        // 完全に不透明な場合はSMaskを付けない
+        let alpha = alpha.filter(|a| a.iter().any(|&v| v != 255));
+
+        PdfImage {
+            width,
+            height,
+            color_space: "DeviceRGB",
+            filter: "FlateDecode",
+            data: img.to_rgb8().into_raw(),
+            alpha,
+        }
+    }
+}
+
+// 複数の画像を順番に1ページずつ配置したPDFを作成
+pub fn images_to_pdf(images: &[PdfImage], options: &PageOptions) -> Result<Vec<u8>> {
+    if images.is_empty() {
+        return Err(anyhow!("No images to convert"));
+    }
+    tracing::debug!("PDF作成開始: {}ページ, {:?}", images.len(), options);
+
+    let mut doc = Document::with_version("1.7");
+    let pages_id = doc.new_object_id();
+    let mut kids = Vec::new();
+
+    for (index, image) in images.iter().enumerate() {
+        let image_width = image.width as f32 * PDF_DPI / IMAGE_DPI;
+        let image_height = image.height as f32 * PDF_DPI / IMAGE_DPI;
+        let margin = options.margin_mm.max(0.0) * POINTS_PER_MM;
+
+        let (page_width, page_height) = page_dimensions(options, image_width, image_height, margin);
+        let area_width = page_width - margin * 2.0;
+        let area_height = page_height - margin * 2.0;
+        if area_width <= 0.0 || area_height <= 0.0 {
+            return Err(anyhow!("Margin is too large for the page size"));
+        }
+
+        let (draw_width, draw_height) = match options.fit {
+            FitMode::Contain | FitMode::Cover => {
+                let scale_x = area_width / image_width;
+                let scale_y = area_height / image_height;
+                let scale = if options.fit == FitMode::Contain { scale_x.min(scale_y) } else { scale_x.max(scale_y) };
+                (image_width * scale, image_height * scale)
+            },
+            FitMode::Fill => (area_width, area_height),
+            FitMode::Original => (image_width, image_height),
+        };
+        let x = margin + (area_width - draw_width) / 2.0;
+        let y = margin + (area_height - draw_height) / 2.0;
+        tracing::debug!(
+            "ページ{}: {:.1}x{:.1}pt, 画像 {:.1}x{:.1}pt ({:.1}, {:.1})",
+            index + 1, page_width, page_height, draw_width, draw_height, x, y
+        );
+
+        let image_id = add_image(&mut doc, image)?;
+
+        // はみ出す可能性のある配置方法では余白内で切り抜く
+        let mut operations = vec![Operation::new("q", vec![])];
+        if matches!(options.fit, FitMode::Cover | FitMode::Original) {
+            operations.push(Operation::new("re", vec![margin.into(), margin.into(), area_width.into(), area_height.into()]));
+            operations.push(Operation::new("W", vec![]));
+            operations.push(Operation::new("n", vec![]));
+        }
+        operations.push(Operation::new(
+            "cm",
+            vec![draw_width.into(), 0.into(), 0.into(), draw_height.into(), x.into(), y.into()],
+        ));
+        operations.push(Operation::new("Do", vec!["Im0".into()]));
+        operations.push(Operation::new("Q", vec![]));
+
+        let content = Content { operations }.encode()?;
+        let content_id = doc.add_object(Stream::new(dictionary! {}, content).with_compression(true));
+
+        let page_id = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "MediaBox" => vec![0.into(), 0.into(), page_width.into(), page_height.into()],
+            "Contents" => content_id,
+            "Resources" => dictionary! {
+                "XObject" => dictionary! { "Im0" => image_id },
+            },
+        });
+        kids.push(page_id.into());
+    }
+
+    let page_count = kids.len() as i64;
+    doc.objects.insert(pages_id, Object::Dictionary(dictionary! {
+        "Type" => "Pages",
+        "Kids" => kids,
+        "Count" => page_count,
+    }));
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    doc.trailer.set("Root", catalog_id);
+    doc.compress();
+
+    let buffer = save(&mut doc)?;
+    tracing::debug!("PDF作成完了: {} バイト", buffer.len());
+    Ok(buffer)
+}
+
+// 用紙サイズと向きからページの幅と高さを決める
+fn page_dimensions(options: &PageOptions, image_width: f32, image_height: f32, margin: f32) -> (f32, f32) {
+    let Some((width, height)) = options.size.dimensions() else {
+        return (image_width + margin * 2.0, image_height + margin * 2.0);
+    };
+    let landscape = match options.orientation {
+        Orientation::Portrait => false,
+        Orientation::Landscape => true,
+        Orientation::Auto => image_width > image_height,
+    };
+    if landscape { (height, width) } else { (width, height) }
+}
+
+// 画像XObjectを追加（透過がある場合はSMaskを付ける）
+fn add_image(doc: &mut Document, image: &PdfImage) -> Result<ObjectId> {
+    let mut dict = dictionary! {
+        "Type" => "XObject",
+        "Subtype" => "Image",
+        "Width" => image.width as i64,
+        "Height" => image.height as i64,
+        "ColorSpace" => image.color_space,
+        "BitsPerComponent" => 8,
+    };
+
+    if let Some(alpha) = &image.alpha {
+        let mask = flate_stream(dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Image",
+            "Width" => image.width as i64,
+            "Height" => image.height as i64,
+            "ColorSpace" => "DeviceGray",
+            "BitsPerComponent" => 8,
+        }, alpha.clone())?;
+        let mask_id = doc.add_object(mask);
+        dict.set("SMask", mask_id);
+    }
+
+    let stream = if image.filter == "DCTDecode" {
+        dict.set("Filter", "DCTDecode");
+        Stream::new(dict, image.data.clone()).with_compression(false)
+    } else {
+        flate_stream(dict, image.data.clone())?
+    };
+    Ok(doc.add_object(stream))
+}
+
+fn flate_stream(dict: Dictionary, data: Vec<u8>) -> Result<Stream> {
+    let mut stream = Stream::new(dict, data);
+    stream.compress()?;
+    Ok(stream)
+}
+
+// JPEGのSOFマーカーからチャンネル数を取得（JPEGでない場合はNone）
+fn jpeg_components(data: &[u8]) -> Option<u8> {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+    let mut i = 2;
+    while i + 4 <= data.len() {
+        if data[i] != 0xFF {
+            return None;
+        }
+        let marker = data[i + 1];
+        let length = u16::from_be_bytes([data[i + 2], data[i + 3]]) as usize;
+        // SOF0〜SOF15（DHT・JPG・DACを除く）
+        if (0xC0..=0xCF).contains(&marker) && !matches!(marker, 0xC4 | 0xC8 | 0xCC) {
+            return data.get(i + 9).copied();
+        }
+        i += 2 + length;
+    }
+    None
+}
+
+// PDFのページをレンダリングする際の設定
+#[derive(Debug, Clone)]
+pub struct RenderOptions {
+    pub dpi: u32,
+    // 出力形式（png / jpeg）
+    pub format: String,
+    // 1始まりのページ番号（未指定の場合は全ページ）
+    pub pages: Option<Vec<u32>>,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            dpi: 150,
+            format: "png".to_string(),
+            pages: None,
+        }
+    }
+}
+
+// レンダリングされたページ
+pub struct RenderedPage {
+    pub page: u32,
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+// ページ範囲（"1-3,5"）を1始まりのページ番号のリストに変換
+pub fn parse_page_range(s: &str, page_count: u32) -> Result<Vec<u32>> {
+    let mut pages = Vec::new();
+    for part in s.split(',').map(|p| p.trim()).filter(|p| !p.is_empty()) {
+        let (start, end) = match part.split_once('-') {
+            Some((start, end)) => {
+                let start = if start.trim().is_empty() { 1 } else { parse_page_number(start)? };
+                let end = if end.trim().is_empty() { page_count } else { parse_page_number(end)? };
+                (start, end)
+            },
+            None => {
+                let page = parse_page_number(part)?;
+                (page, page)
+            },
+        };
+        if start > end || end > page_count {
+            return Err(anyhow!("Invalid page range '{}' for {} pages", part, page_count));
+        }
+        pages.extend(start..=end);
+    }
+    if pages.is_empty() {
+        return Err(anyhow!("Page range is empty"));
+    }
+    Ok(pages)
+}
+
+fn parse_page_number(s: &str) -> Result<u32> {
+    s.trim()
+        .parse::<u32>()
+        .ok()
+        .filter(|&p| p > 0)
+        .ok_or_else(|| anyhow!("Invalid page number: {}", s))
+}
+
+// PDFを読み込む（暗号化されたPDFは扱わない）
+pub fn load_document(data: &[u8]) -> Result<Document> {
+    check_inflated_sizes(data)?;
+    let doc = Document::load_mem(data).map_err(|e| anyhow!("Failed to parse PDF: {}", e))?;
+    if doc.is_encrypted() {
+        return Err(anyhow!("Encrypted PDF is not supported"));
+    }
+    Ok(doc)
+}
+
+// 読み込み前に確認する、全ストリームを展開した合計サイズの上限（デコードのメモリ上限に対する倍数）
+const MAX_INFLATED_TOTAL_FACTOR: u64 = 4;
+
+// lopdf は読み込み時に /ObjStm・/XRef ストリームを上限なしで展開するため、
+// 読み込む前にすべての Flate ストリームを展開してサイズだけを数える（展開したデータは保持しない）
+fn check_inflated_sizes(data: &[u8]) -> Result<(), LimitError> {
+    let max = limits::DecodeLimits::from_config().max_memory_bytes;
+    let max_total = max.saturating_mul(MAX_INFLATED_TOTAL_FACTOR);
+    let mut buffer = vec![0u8; 64 * 1024];
+    let mut total = 0u64;
+    let mut position = 0;
+    while let Some(offset) = data[position..].windows(6).position(|w| w == b"stream") {
+        let start = position + offset;
+        position = start + b"stream".len();
+        if data[..start].ends_with(b"end") {
+            continue;
+        }
+        let content = &data[position..];
+        let content = content.strip_prefix(b"\r\n").or_else(|| content.strip_prefix(b"\n")).unwrap_or(content);
+
+        // 展開に失敗した時点までのデータも lopdf は保持するため、読めた分だけ数える
+        let mut decoder = ZlibDecoder::new(content);
+        let mut size = 0u64;
+        while let Ok(read @ 1..) = decoder.read(&mut buffer) {
+            size += read as u64;
+            total += read as u64;
+            if size > max {
+                tracing::warn!("PDFのストリーム（オフセット{}）の展開後のサイズが上限を超えました", start);
+                return Err(LimitError::InflatedTooLarge { max });
+            }
+            if total > max_total {
+                tracing::warn!("PDFのストリームの展開後の合計サイズが上限を超えました");
+                return Err(LimitError::InflatedTooLarge { max: max_total });
+            }
+        }
+    }
+    Ok(())
+}
+
+// Flate ストリームを最大 limit バイトまで展開する（超える場合は展開を打ち切ってエラー）
+fn inflate_limited(data: &[u8], limit: u64) -> Result<Vec<u8>> {
+    let mut output = Vec::new();
+    ZlibDecoder::new(data)
+        .take(limit + 1)
+        .read_to_end(&mut output)
+        .map_err(|e| anyhow!("Failed to inflate stream: {}", e))?;
+    if output.len() as u64 > limit {
+        return Err(LimitError::InflatedTooLarge { max: limit }.into());
+    }
+    Ok(output)
+}
+
+// ページのMediaBox（親のPagesから継承される場合を含む、親の参照が循環している場合は None）
+fn media_box(doc: &Document, page_id: ObjectId) -> Option<(f32, f32)> {
+    let mut current = doc.get_dictionary(page_id).ok()?;
+    let mut visited = HashSet::from([page_id]);
+    loop {
+        if let Ok(Object::Array(values)) = current.get(b"MediaBox").and_then(|o| doc.dereference(o).map(|(_, o)| o)) {
+            let numbers = values.iter().filter_map(|v| v.as_float().ok()).collect::<Vec<f32>>();
+            if numbers.len() == 4 {
+                return Some(((numbers[2] - numbers[0]).abs(), (numbers[3] - numbers[1]).abs()));
+            }
+        }
+        let parent = current.get(b"Parent").and_then(Object::as_reference).ok()?;
+        if !visited.insert(parent) {
+            tracing::warn!("ページ{:?}の親の参照が循環しています", page_id);
+            return None;
+        }
+        current = doc.get_dictionary(parent).ok()?;
+    }
+}
+
+// PDFの各ページを画像にレンダリング（ImageMagick + Ghostscript）
+pub fn render_pages(data: &[u8], options: &RenderOptions) -> Result<Vec<RenderedPage>> {
+    let dpi = options.dpi.clamp(1, MAX_RENDER_DPI);
+    let format = match converter::extension_for(&options.format) {
+        "png" => "PNG",
+        "jpg" => "JPEG",
+        _ => return Err(anyhow!("Unsupported output format: {}", options.format)),
+    };
+
+    let mut doc = load_document(data)?;
+    let all_pages = doc.get_pages();
+    let page_count = all_pages.len() as u32;
+    let mut pages = options.pages.clone().unwrap_or_else(|| (1..=page_count).collect());
+    pages.sort_unstable();
+    pages.dedup();
+    if pages.iter().any(|&p| p == 0 || p > page_count) {
+        return Err(anyhow!("Page number out of range (1-{})", page_count));
+    }
+
+    let max_pages = config::get().max_pdf_pages;
+    if pages.len() > max_pages {
+        return Err(anyhow!("Too many pages to render: {} (max {})", pages.len(), max_pages));
+    }
+
+    // レンダリング前に、MediaBox と解像度から求めたページの画素数を画像のデコード上限で確認
+    // MediaBox を読めないページはここでは確認せず、ImageMagick のリソース上限に任せる
+    let decode_limits = limits::DecodeLimits::from_config();
+    let scale = dpi as f32 / PDF_DPI;
+    for &page in &pages {
+        if let Some((width, height)) = media_box(&doc, all_pages[&page]) {
+            let (width, height) = ((width * scale).ceil() as u32, (height * scale).ceil() as u32);
+            decode_limits.check(width, height).inspect_err(|e| {
+                tracing::warn!("ページ{}は{}dpiでレンダリングすると大きすぎます: {}", page, dpi, e);
+            })?;
+        }
+    }
+    tracing::debug!("PDFレンダリング開始: {}ページ中{}ページ, {}dpi, {}", page_count, pages.len(), dpi, format);
+
+    // 対象外のページを削除してからレンダリングする
+    let source = if pages.len() as u32 == page_count {
+        data.to_vec()
+    } else {
+        let removed = (1..=page_count).filter(|p| !pages.contains(p)).collect::<Vec<u32>>();
+        doc.delete_pages(&removed);
+        doc.prune_objects();
+        save(&mut doc)?
+    };
+
+    let wand = MagickWand::new();
+    // 解像度は読み込み前に設定する必要がある
+    wand.set_resolution(dpi as f64, dpi as f64)
+        .map_err(|e| anyhow!("Failed to set resolution: {}", e))?;
+    wand.read_image_blob(&source)
+        .map_err(|e| anyhow!("Failed to render PDF with ImageMagick: {}", e))?;
+
+    // ページ数が異なる場合は画像とページ番号の対応が分からないためエラーにする
+    let rendered_count = wand.get_number_images();
+    if rendered_count != pages.len() {
+        tracing::error!("レンダリングされたページ数が一致しません: {} / {}", rendered_count, pages.len());
+        return Err(anyhow!("Rendered {} of {} pages", rendered_count, pages.len()));
+    }
+
+    let mut background = PixelWand::new();
+    background.set_color("white").map_err(|e| anyhow!("Failed to set background: {}", e))?;
+
+    let mut result = Vec::new();
+    for (index, &page) in pages.iter().enumerate() {
+        wand.set_iterator_index(index as isize)
+            .map_err(|e| anyhow!("Failed to select page {}: {}", page, e))?;
+        // 透明な背景は白で塗りつぶす
+        wand.set_image_background_color(&background)
+            .and_then(|_| wand.set_image_alpha_channel(AlphaChannelOption::Remove))
+            .map_err(|e| anyhow!("Failed to flatten page {}: {}", page, e))?;
+        if format == "JPEG" {
+            if let Err(e) = wand.set_image_compression_quality(90) {
+                tracing::warn!("圧縮品質設定エラー: {:?}", e);
+            }
+        }
+
+        let data = wand.write_image_blob(format)
+            .map_err(|e| anyhow!("Failed to encode page {}: {}", page, e))?;
+        let width = wand.get_image_width() as u32;
+        let height = wand.get_image_height() as u32;
+        tracing::debug!("ページ{}: {}x{} ({} バイト)", page, width, height, data.len());
+        result.push(RenderedPage { page, width, height, data });
+    }
+
+    Ok(result)
+}
+
+// ページから継承される属性（ページを別の文書に移す際はページ自身に持たせる）
+const INHERITABLE_KEYS: [&[u8]; 4] = [b"MediaBox", b"CropBox", b"Resources", b"Rotate"];
+// 再圧縮の対象とする画像の最小サイズ（これより小さい画像はそのまま）
+const MIN_RECOMPRESS_BYTES: usize = 4 * 1024;
+
+// 組み立て後の文書に含めるページ
+#[derive(Debug, Clone, Copy)]
+pub struct PageRef {
+    // 元の文書の番号
+    pub document: usize,
+    // 1始まりのページ番号
+    pub page: u32,
+    // 追加で回転する角度（90の倍数）
+    pub rotate: i64,
+}
+
+// 圧縮結果の統計
+#[derive(Debug, Clone, Default)]
+pub struct CompressStats {
+    pub images_recompressed: usize,
+    pub objects_removed: usize,
+}
+
+// 複数の文書から選択したページを順番に並べた新しい文書を作成
+// 結合・分割・回転・並べ替えはすべてこの処理を使う
+pub fn assemble(documents: &[Document], selection: &[PageRef]) -> Result<Vec<u8>> {
+    if selection.is_empty() {
+        return Err(anyhow!("No pages selected"));
+    }
+
+    let mut output = Document::with_version("1.7");
+    let mut pages_by_document = Vec::with_capacity(documents.len());
+    let mut max_id = 1;
+
+    for source in documents {
+        // オブジェクト番号が重ならないように振り直す
+        let mut source = source.clone();
+        source.renumber_objects_with(max_id);
+        max_id = source.max_id + 1;
+
+        let pages = source.get_pages();
+        for &page_id in pages.values() {
+            let attributes = inherited_attributes(&source, page_id);
+            if let Ok(page) = source.get_dictionary_mut(page_id) {
+                for (key, value) in attributes {
+                    page.set(key, value);
+                }
+            }
+        }
+
+        // カタログ・ページツリーは新しく作るため引き継がない
+        for (id, object) in source.objects {
+            let type_name = object.as_dict().and_then(|d| d.get(b"Type")).and_then(Object::as_name).ok();
+            if !matches!(type_name, Some(b"Catalog") | Some(b"Pages")) {
+                output.objects.insert(id, object);
+            }
+        }
+        pages_by_document.push(pages);
+    }
+    output.max_id = max_id;
+
+    let pages_id = output.new_object_id();
+    let mut used = HashSet::new();
+    let mut kids = Vec::with_capacity(selection.len());
+
+    for page_ref in selection {
+        let page_id = pages_by_document
+            .get(page_ref.document)
+            .and_then(|pages| pages.get(&page_ref.page))
+            .copied()
+            .ok_or_else(|| anyhow!("Page {} does not exist", page_ref.page))?;
+
+        let mut page = output.get_dictionary(page_id)?.clone();
+        page.set("Parent", pages_id);
+        let rotate = page.get(b"Rotate").and_then(Object::as_i64).unwrap_or(0) + page_ref.rotate;
+        page.set("Rotate", rotate.rem_euclid(360));
+
+        // 同じページを複数回使う場合は別のオブジェクトにする
+        let id = if used.insert(page_id) { page_id } else { output.new_object_id() };
+        output.objects.insert(id, Object::Dictionary(page));
+        kids.push(Object::Reference(id));
+    }
+
+    let count = kids.len() as i64;
+    output.objects.insert(pages_id, Object::Dictionary(dictionary! {
+        "Type" => "Pages",
+        "Kids" => kids,
+        "Count" => count,
+    }));
+    let catalog_id = output.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    output.trailer.set("Root", catalog_id);
+
+    // 選択されなかったページとそこからのみ参照されるオブジェクトを削除
+    output.prune_objects();
+    output.renumber_objects();
+    output.compress();
+    save(&mut output)
+}
+
+// ページの親から継承される属性を集める（ページ自身に設定されているものは除く）
+fn inherited_attributes(doc: &Document, page_id: ObjectId) -> Vec<(&'static [u8], Object)> {
+    let mut attributes = Vec::new();
+    let Ok(page) = doc.get_dictionary(page_id) else {
+        return attributes;
+    };
+    for key in INHERITABLE_KEYS {
+        if page.has(key) {
+            continue;
+        }
+        let mut parent = page.get(b"Parent").and_then(Object::as_reference).ok();
+        let mut visited = HashSet::from([page_id]);
+        while let Some(parent_id) = parent {
+            // 親の参照が循環している場合は打ち切る
+            if !visited.insert(parent_id) {
+                break;
+            }
+            let Ok(node) = doc.get_dictionary(parent_id) else { break };
+            if let Ok(value) = node.get(key) {
+                attributes.push((key, value.clone()));
+                break;
+            }
+            parent = node.get(b"Parent").and_then(Object::as_reference).ok();
+        }
+    }
+    attributes
+}
+
+fn save(doc: &mut Document) -> Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+    doc.save_to(&mut buffer)?;
+    Ok(buffer)
+}
+
+// アップロード順に文書を結合
+pub fn merge(documents: &[Document]) -> Result<Vec<u8>> {
+    let selection = documents
+        .iter()
+        .enumerate()
+        .flat_map(|(index, doc)| {
+            (1..=doc.get_pages().len() as u32).map(move |page| PageRef { document: index, page, rotate: 0 })
+        })
+        .collect::<Vec<_>>();
+    tracing::debug!("PDF結合: {}文書, {}ページ", documents.len(), selection.len());
+    assemble(documents, &selection)
+}
+
+// ページ範囲ごとに別の文書に分割
+pub fn split(doc: &Document, ranges: &[Vec<u32>]) -> Result<Vec<Vec<u8>>> {
+    tracing::debug!("PDF分割: {}ファイル", ranges.len());
+    let documents = std::slice::from_ref(doc);
+    ranges
+        .iter()
+        .map(|pages| {
+            let selection = pages.iter().map(|&page| PageRef { document: 0, page, rotate: 0 }).collect::<Vec<_>>();
+            assemble(documents, &selection)
+        })
+        .collect()
+}
+
+// 指定したページを回転（90の倍数のみ）
+pub fn rotate(doc: &Document, pages: &[u32], angle: i64) -> Result<Vec<u8>> {
+    if angle % 90 != 0 {
+        return Err(anyhow!("Rotation angle must be a multiple of 90: {}", angle));
+    }
+    let page_count = doc.get_pages().len() as u32;
+    let selection = (1..=page_count)
+        .map(|page| PageRef { document: 0, page, rotate: if pages.contains(&page) { angle } else { 0 } })
+        .collect::<Vec<_>>();
+    tracing::debug!("PDF回転: {}ページを{}度", pages.len(), angle);
+    assemble(std::slice::from_ref(doc), &selection)
+}
+
+// 指定した順にページを並べ替え（指定されなかったページは削除される）
+pub fn reorder(doc: &Document, order: &[u32]) -> Result<Vec<u8>> {
+    let selection = order.iter().map(|&page| PageRef { document: 0, page, rotate: 0 }).collect::<Vec<_>>();
+    tracing::debug!("PDF並べ替え: {:?}", order);
+    assemble(std::slice::from_ref(doc), &selection)
+}
+
+// 文書ごとに分割するページ範囲（"1-3,4,5-" は3つのファイルになる）
+pub fn parse_split_ranges(s: &str, page_count: u32) -> Result<Vec<Vec<u32>>> {
+    let ranges = s
+        .split(',')
+        .map(|part| part.trim())
+        .filter(|part| !part.is_empty())
+        .map(|part| parse_page_range(part, page_count))
+        .collect::<Result<Vec<_>>>()?;
+    if ranges.is_empty() {
+        return Err(anyhow!("Page range is empty"));
+    }
+    Ok(ranges)
+}
+
+// 埋め込み画像を mozjpeg で再圧縮し、未使用のオブジェクトを削除
+pub fn compress(data: &[u8], quality: i32) -> Result<(Vec<u8>, CompressStats)> {
+    let mut doc = load_document(data)?;
+    let mut stats = CompressStats::default();
+    tracing::debug!("PDF圧縮開始: {} バイト, {}オブジェクト (品質: {}%)", data.len(), doc.objects.len(), quality);
+
+    // 透過マスクとして使われている画像は非可逆圧縮しない
+    let masks = doc
+        .objects
+        .values()
+        .filter_map(|object| object.as_stream().ok())
+        .flat_map(|stream| [stream.dict.get(b"SMask"), stream.dict.get(b"Mask")])
+        .filter_map(|value| value.and_then(Object::as_reference).ok())
+        .collect::<HashSet<ObjectId>>();
+
+    for (id, object) in doc.objects.iter_mut() {
+        let Object::Stream(stream) = object else { continue };
+        if masks.contains(id) || stream.dict.get(b"Subtype").and_then(Object::as_name).ok() != Some(b"Image") {
+            continue;
+        }
+        match recompress_image(stream, quality) {
+            Ok(true) => stats.images_recompressed += 1,
+            Ok(false) => {},
+            Err(e) => tracing::debug!("画像{:?}は再圧縮しません: {}", id, e),
+        }
+    }
+
+    stats.objects_removed = doc.prune_objects().len() + doc.delete_zero_length_streams().len();
+    doc.renumber_objects();
+    doc.compress();
+    let output = save(&mut doc)?;
+    tracing::debug!(
+        "PDF圧縮完了: {} → {} バイト (再圧縮: {}画像, 削除: {}オブジェクト)",
+        data.len(), output.len(), stats.images_recompressed, stats.objects_removed
+    );
+
+    // 小さくならなかった場合は元のデータを返す
+    if output.len() >= data.len() {
+        return Ok((data.to_vec(), CompressStats::default()));
+    }
+    Ok((output, stats))
+}
+
+// 画像XObjectをJPEGに再圧縮（小さくなった場合のみ置き換えてtrueを返す）
+fn recompress_image(stream: &mut Stream, quality: i32) -> Result<bool> {
+    if stream.content.len() < MIN_RECOMPRESS_BYTES {
+        return Ok(false);
+    }
+    let dict = &stream.dict;
+    let predictor = dict
+        .get(b"DecodeParms")
+        .and_then(Object::as_dict)
+        .and_then(|params| params.get(b"Predictor"))
+        .and_then(Object::as_i64)
+        .unwrap_or(1);
+    if dict.get(b"BitsPerComponent").and_then(Object::as_i64).ok() != Some(8)
+        || dict.has(b"Decode")
+        || dict.get(b"ImageMask").and_then(Object::as_bool).unwrap_or(false)
+        || !(predictor == 1 || (10..=15).contains(&predictor))
+    {
+        return Err(anyhow!("unsupported image parameters"));
+    }
+    let width = dict.get(b"Width").and_then(Object::as_i64)? as u32;
+    let height = dict.get(b"Height").and_then(Object::as_i64)? as u32;
+    let components = match dict.get(b"ColorSpace").and_then(Object::as_name).ok() {
+        Some(b"DeviceRGB") => 3,
+        Some(b"DeviceGray") => 1,
+        _ => return Err(anyhow!("unsupported color space")),
+    };
+    // 展開する前にサイズを確認する
+    limits::DecodeLimits::from_config().check(width, height)?;
+
+    let filters = stream.filters().unwrap_or_default();
+    let img = match filters.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        ["DCTDecode"] => {
+            if jpeg_components(&stream.content) != Some(components) {
+                return Err(anyhow!("unexpected JPEG components"));
+            }
+            limits::decode_memory(&stream.content, Some(image::ImageFormat::Jpeg))?
+        },
+        [] | ["FlateDecode"] => {
+            // 展開後のサイズは Width・Height から決まるため、それを超えて展開しない
+            // PNG予測子を使う場合は各行の先頭にフィルタの種類の1バイトが付く
+            let row_bytes = width as usize * components as usize;
+            let raw = if filters.is_empty() {
+                stream.content.clone()
+            } else if predictor == 1 {
+                inflate_limited(&stream.content, (row_bytes * height as usize) as u64)?
+            } else {
+                let inflated = inflate_limited(&stream.content, ((row_bytes + 1) * height as usize) as u64)?;
+                lopdf::filters::png::decode_frame(&inflated, components as usize, width as usize)?
+            };
+            let raw = raw.get(..row_bytes * height as usize)
+                .ok_or_else(|| anyhow!("image data is too short"))?
+                .to_vec();
+            if components == 3 {
+                DynamicImage::ImageRgb8(RgbImage::from_raw(width, height, raw).ok_or_else(|| anyhow!("invalid image size"))?)
+            } else {
+                DynamicImage::ImageLuma8(GrayImage::from_raw(width, height, raw).ok_or_else(|| anyhow!("invalid image size"))?)
+            }
+        },
+        _ => return Err(anyhow!("unsupported filter {:?}", filters)),
+    };
+    let img = if components == 1 { DynamicImage::ImageLuma8(img.to_luma8()) } else { img };
+
+    let jpeg = compressor::encode_jpeg(&img, quality)?;
+    if jpeg.len() >= stream.content.len() {
+        return Ok(false);
+    }
+    tracing::debug!("画像を再圧縮: {}x{}, {} → {} バイト", width, height, stream.content.len(), jpeg.len());
+    stream.dict.set("Filter", "DCTDecode");
+    stream.dict.remove(b"DecodeParms");
+    stream.set_content(jpeg);
+    // 再度Flate圧縮されないようにする
+    stream.allows_compression = false;
+    Ok(true)
+}
+
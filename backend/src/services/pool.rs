@@ -0,0 +1,109 @@
+// 画像のデコード・エンコードなど CPU を使う処理をブロッキングスレッドで実行する
+// 同時に実行する数を制限し、空きを待つ処理の数が上限を超えたら受け付けない
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::config;
+
+pub struct BlockingPool {
+    permits: Arc<Semaphore>,
+    waiting: AtomicUsize,
+    max_waiting: usize,
+}
+
+static POOL: OnceLock<BlockingPool> = OnceLock::new();
+
+pub fn pool() -> &'static BlockingPool {
+    POOL.get_or_init(|| {
+        let config = config::get();
+        BlockingPool::new(config.blocking_concurrency, config.blocking_queue_size)
+    })
+}
+
+// 待ち行列から抜けるときに数を戻す（待っている間にリクエストが切断された場合も含む）
+struct WaitingGuard<'a>(&'a AtomicUsize);
+
+impl Drop for WaitingGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl BlockingPool {
+    pub fn new(concurrency: usize, max_waiting: usize) -> Self {
+        BlockingPool {
+            permits: Arc::new(Semaphore::new(concurrency.max(1))),
+            waiting: AtomicUsize::new(0),
+            max_waiting,
+        }
+    }
+
+    // 空きがあればすぐに、なければ待ってから task をブロッキングスレッドで実行する
+    pub async fn run<T, F>(&self, task: F) -> Result<T, PoolError>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let permit = match self.permits.clone().try_acquire_owned() {
+            Ok(permit) => permit,
+            Err(_) => {
+                if self.waiting.fetch_add(1, Ordering::SeqCst) >= self.max_waiting {
+                    self.waiting.fetch_sub(1, Ordering::SeqCst);
+                    tracing::warn!("ブロッキング処理の待ち行列が上限（{}件）に達したため受け付けません", self.max_waiting);
+                    return Err(PoolError::Busy(self.max_waiting));
+                }
+                let _guard = WaitingGuard(&self.waiting);
+                tracing::debug!("ブロッキング処理の空き待ち（待機中: {}件）", self.waiting.load(Ordering::SeqCst));
+                self.permits.clone().acquire_owned().await.map_err(|_| PoolError::Closed)?
+            },
+        };
+        execute(permit, task).await
+    }
+
+    // バックグラウンドジョブ用：待ち行列の上限で断らず、空きが出るまで待ってから実行する
+    // （ジョブの同時実行数はジョブのセマフォで制限している）
+    pub async fn run_queued<T, F>(&self, task: F) -> Result<T, PoolError>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let permit = self.permits.clone().acquire_owned().await.map_err(|_| PoolError::Closed)?;
+        execute(permit, task).await
+    }
+}
+
+async fn execute<T, F>(permit: OwnedSemaphorePermit, task: F) -> Result<T, PoolError>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    tokio::task::spawn_blocking(move || {
+        let _permit = permit;
+        task()
+    })
+    .await
+    .map_err(|e| PoolError::Panicked(e.to_string()))
+}
+
+#[derive(Debug)]
+pub enum PoolError {
+    // 待ち行列が上限に達した
+    Busy(usize),
+    Closed,
+    // 処理中にパニックした
+    Panicked(String),
+}
+
+impl fmt::Display for PoolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PoolError::Busy(max) => write!(f, "Server is busy ({} requests already waiting), try again later", max),
+            PoolError::Closed => write!(f, "Processing pool is shut down"),
+            PoolError::Panicked(message) => write!(f, "Processing failed unexpectedly: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for PoolError {}
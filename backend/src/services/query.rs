@@ -0,0 +1,1098 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+use serde_json_path::JsonPath;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+// 式の最大長（文字数）と括弧の最大の深さ
+const MAX_EXPRESSION_CHARS: usize = 1024;
+const MAX_NESTING: usize = 64;
+// jq 形式の評価で生成できる値の総数（式による計算量の爆発を防ぐ）
+const MAX_EVALUATED_VALUES: usize = 1_000_000;
+// jq 形式の評価で複製できる要素（配列・オブジェクトの中身を含む値）の総数
+// 値の数だけでは [.,.]|[.,.]|… のように1段ごとに文書を倍にする式でメモリを使い切ってしまう
+const MAX_COPIED_NODES: usize = 2_000_000;
+
+// 問い合わせ言語
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QueryLanguage {
+    // RFC 9535 JSONPath
+    JsonPath,
+    // jq の主要な構文のサブセット
+    Jq,
+}
+
+impl QueryLanguage {
+    // 指定がなければ $ で始まる式を JSONPath、それ以外を jq とみなす
+    pub fn detect(expression: &str) -> Self {
+        if expression.trim_start().starts_with('$') { QueryLanguage::JsonPath } else { QueryLanguage::Jq }
+    }
+}
+
+impl FromStr for QueryLanguage {
+    type Err = QueryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "jsonpath" | "json-path" => Ok(QueryLanguage::JsonPath),
+            "jq" => Ok(QueryLanguage::Jq),
+            other => Err(QueryError::Unsupported(format!("Unsupported query language: {}", other))),
+        }
+    }
+}
+
+// 一致した値（JSONPath の場合は位置を JSON Pointer で返す）
+#[derive(Debug, Serialize)]
+pub struct Match {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    pub value: Value,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QueryResult {
+    pub matches: Vec<Match>,
+    // 上限を超えたため省略された結果があるか
+    pub truncated: bool,
+}
+
+#[derive(Debug)]
+pub enum QueryError {
+    // 式の構文エラー（位置は1始まりの文字数）
+    Syntax { message: String, position: usize },
+    // 評価中のエラー（型の合わない添字など）
+    Runtime(String),
+    // 生成する値が多すぎる
+    TooExpensive,
+    Unsupported(String),
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueryError::Syntax { message, position } => write!(f, "Invalid expression at position {}: {}", position, message),
+            QueryError::Runtime(message) | QueryError::Unsupported(message) => write!(f, "{}", message),
+            QueryError::TooExpensive => write!(
+                f,
+                "Query produces too many intermediate values (max {} values, {} copied elements)",
+                MAX_EVALUATED_VALUES, MAX_COPIED_NODES
+            ),
+        }
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+// 文書に式を適用し、最大 limit 件の結果を返す
+pub fn run(document: &Value, expression: &str, language: QueryLanguage, limit: usize) -> Result<QueryResult, QueryError> {
+    if expression.chars().count() > MAX_EXPRESSION_CHARS {
+        return Err(QueryError::Unsupported(format!("Expression is too long (max {} characters)", MAX_EXPRESSION_CHARS)));
+    }
+    tracing::debug!("問い合わせ開始: {:?} '{}'", language, expression);
+
+    let mut matches = Vec::new();
+    let mut truncated = false;
+    match language {
+        QueryLanguage::JsonPath => {
+            let path = JsonPath::parse(expression).map_err(|e| QueryError::Syntax {
+                message: e.message().to_string(),
+                position: e.position(),
+            })?;
+            for node in path.query_located(document).iter() {
+                if matches.len() >= limit {
+                    truncated = true;
+                    break;
+                }
+                matches.push(Match { path: Some(node.location().to_json_pointer()), value: node.node().clone() });
+            }
+        },
+        QueryLanguage::Jq => {
+            let filter = Parser::new(expression)?.parse()?;
+            let mut budget = Budget::new();
+            let mut outputs = Vec::new();
+            eval(&filter, Cow::Borrowed(document), &mut outputs, &mut budget)?;
+            truncated = outputs.len() > limit;
+            for value in outputs.into_iter().take(limit) {
+                matches.push(Match { path: None, value: into_owned(value, &mut budget)? });
+            }
+        },
+    }
+
+    tracing::debug!("問い合わせ完了: {}件{}", matches.len(), if truncated { "（省略あり）" } else { "" });
+    Ok(QueryResult { matches, truncated })
+}
+
+// ==== jq 形式の式 ====
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Function {
+    Length,
+    Keys,
+    KeysUnsorted,
+    Type,
+    Not,
+    First,
+    Last,
+    Reverse,
+    Sort,
+    Unique,
+    Min,
+    Max,
+    Add,
+    Empty,
+    ToString,
+    ToNumber,
+    ToEntries,
+    // 以下は引数を1つ取る
+    Select,
+    Map,
+    Has,
+    SortBy,
+}
+
+impl Function {
+    fn lookup(name: &str, with_argument: bool) -> Option<Self> {
+        let function = match (name, with_argument) {
+            ("length", false) => Function::Length,
+            ("keys", false) => Function::Keys,
+            ("keys_unsorted", false) => Function::KeysUnsorted,
+            ("type", false) => Function::Type,
+            ("not", false) => Function::Not,
+            ("first", false) => Function::First,
+            ("last", false) => Function::Last,
+            ("reverse", false) => Function::Reverse,
+            ("sort", false) => Function::Sort,
+            ("unique", false) => Function::Unique,
+            ("min", false) => Function::Min,
+            ("max", false) => Function::Max,
+            ("add", false) => Function::Add,
+            ("empty", false) => Function::Empty,
+            ("tostring", false) => Function::ToString,
+            ("tonumber", false) => Function::ToNumber,
+            ("to_entries", false) => Function::ToEntries,
+            ("select", true) => Function::Select,
+            ("map", true) => Function::Map,
+            ("has", true) => Function::Has,
+            ("sort_by", true) => Function::SortBy,
+            _ => return None,
+        };
+        Some(function)
+    }
+}
+
+#[derive(Debug)]
+enum Filter {
+    Identity,
+    // ..（自身とすべての子孫）
+    Recurse,
+    Field(String),
+    Index(i64),
+    Slice(Option<i64>, Option<i64>),
+    Iterate,
+    Literal(Value),
+    // 後置の ? （エラーを無視する）
+    Try(Box<Filter>),
+    Pipe(Box<Filter>, Box<Filter>),
+    Comma(Box<Filter>, Box<Filter>),
+    Alternative(Box<Filter>, Box<Filter>),
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+    Compare(Box<Filter>, CompareOp, Box<Filter>),
+    Array(Option<Box<Filter>>),
+    Object(Vec<(String, Filter)>),
+    Call(Function, Option<Box<Filter>>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Dot,
+    DotDot,
+    Field(String),
+    Ident(String),
+    Literal(Value),
+    Symbol(&'static str),
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    depth: usize,
+    // 式の末尾の位置（エラー表示用）
+    end: usize,
+}
+
+impl Parser {
+    fn new(expression: &str) -> Result<Self, QueryError> {
+        let chars = expression.chars().collect::<Vec<_>>();
+        Ok(Parser { tokens: tokenize(&chars)?, pos: 0, depth: 0, end: chars.len() + 1 })
+    }
+
+    fn parse(mut self) -> Result<Filter, QueryError> {
+        if self.tokens.is_empty() {
+            return Ok(Filter::Identity);
+        }
+        let filter = self.parse_pipe()?;
+        match self.tokens.get(self.pos) {
+            Some((token, position)) => Err(syntax(format!("Unexpected {}", describe(token)), *position)),
+            None => Ok(filter),
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens.get(self.pos).map(|(_, position)| *position).unwrap_or(self.end)
+    }
+
+    fn eat(&mut self, symbol: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Symbol(s)) if *s == symbol) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, symbol: &str) -> Result<(), QueryError> {
+        if self.eat(symbol) {
+            Ok(())
+        } else {
+            Err(syntax(format!("Expected '{}'", symbol), self.position()))
+        }
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Ident(name)) if name == keyword) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    // 優先順位: | < , < // < or < and < 比較 < 後置
+    fn parse_pipe(&mut self) -> Result<Filter, QueryError> {
+        let mut filter = self.parse_comma()?;
+        while self.eat("|") {
+            filter = Filter::Pipe(Box::new(filter), Box::new(self.parse_comma()?));
+        }
+        Ok(filter)
+    }
+
+    fn parse_comma(&mut self) -> Result<Filter, QueryError> {
+        let mut filter = self.parse_alternative()?;
+        while self.eat(",") {
+            filter = Filter::Comma(Box::new(filter), Box::new(self.parse_alternative()?));
+        }
+        Ok(filter)
+    }
+
+    fn parse_alternative(&mut self) -> Result<Filter, QueryError> {
+        let filter = self.parse_or()?;
+        if self.eat("//") {
+            // 右結合
+            return Ok(Filter::Alternative(Box::new(filter), Box::new(self.parse_alternative()?)));
+        }
+        Ok(filter)
+    }
+
+    fn parse_or(&mut self) -> Result<Filter, QueryError> {
+        let mut filter = self.parse_and()?;
+        while self.eat_keyword("or") {
+            filter = Filter::Or(Box::new(filter), Box::new(self.parse_and()?));
+        }
+        Ok(filter)
+    }
+
+    fn parse_and(&mut self) -> Result<Filter, QueryError> {
+        let mut filter = self.parse_compare()?;
+        while self.eat_keyword("and") {
+            filter = Filter::And(Box::new(filter), Box::new(self.parse_compare()?));
+        }
+        Ok(filter)
+    }
+
+    fn parse_compare(&mut self) -> Result<Filter, QueryError> {
+        let filter = self.parse_postfix()?;
+        let op = match self.peek() {
+            Some(Token::Symbol("==")) => CompareOp::Eq,
+            Some(Token::Symbol("!=")) => CompareOp::Ne,
+            Some(Token::Symbol("<")) => CompareOp::Lt,
+            Some(Token::Symbol("<=")) => CompareOp::Le,
+            Some(Token::Symbol(">")) => CompareOp::Gt,
+            Some(Token::Symbol(">=")) => CompareOp::Ge,
+            _ => return Ok(filter),
+        };
+        self.pos += 1;
+        Ok(Filter::Compare(Box::new(filter), op, Box::new(self.parse_postfix()?)))
+    }
+
+    fn parse_postfix(&mut self) -> Result<Filter, QueryError> {
+        let mut filter = self.parse_term()?;
+        loop {
+            let suffix = match self.peek() {
+                Some(Token::Field(name)) => {
+                    let name = name.clone();
+                    self.pos += 1;
+                    Filter::Field(name)
+                },
+                // .a.[0] のような書き方も受け付ける
+                Some(Token::Dot) if matches!(self.tokens.get(self.pos + 1), Some((Token::Symbol("["), _))) => {
+                    self.pos += 1;
+                    continue;
+                },
+                Some(Token::Symbol("[")) => {
+                    self.pos += 1;
+                    self.parse_brackets()?
+                },
+                Some(Token::Symbol("?")) => {
+                    self.pos += 1;
+                    filter = Filter::Try(Box::new(filter));
+                    continue;
+                },
+                _ => return Ok(filter),
+            };
+            filter = Filter::Pipe(Box::new(filter), Box::new(suffix));
+        }
+    }
+
+    // [ の後: []、[n]、["key"]、[n:m]
+    fn parse_brackets(&mut self) -> Result<Filter, QueryError> {
+        if self.eat("]") {
+            return Ok(Filter::Iterate);
+        }
+        let position = self.position();
+        let start = self.parse_index_literal()?;
+        if self.eat(":") {
+            let end = match self.peek() {
+                Some(Token::Symbol("]")) => None,
+                _ => Some(self.parse_integer()?),
+            };
+            self.expect("]")?;
+            let start = match start {
+                None => None,
+                Some(Value::Number(n)) => Some(n.as_i64().ok_or_else(|| syntax("Slice bounds must be integers".to_string(), position))?),
+                Some(_) => return Err(syntax("Slice bounds must be integers".to_string(), position)),
+            };
+            return Ok(Filter::Slice(start, end));
+        }
+        self.expect("]")?;
+        match start {
+            Some(Value::String(name)) => Ok(Filter::Field(name)),
+            Some(Value::Number(n)) => n
+                .as_i64()
+                .map(Filter::Index)
+                .ok_or_else(|| syntax("Array index must be an integer".to_string(), position)),
+            _ => Err(syntax("Expected an index, a key or a slice".to_string(), position)),
+        }
+    }
+
+    fn parse_index_literal(&mut self) -> Result<Option<Value>, QueryError> {
+        match self.peek() {
+            Some(Token::Symbol(":")) => Ok(None),
+            Some(Token::Literal(value @ (Value::Number(_) | Value::String(_)))) => {
+                let value = value.clone();
+                self.pos += 1;
+                Ok(Some(value))
+            },
+            _ => Err(syntax("Expected an index, a key or a slice".to_string(), self.position())),
+        }
+    }
+
+    fn parse_integer(&mut self) -> Result<i64, QueryError> {
+        match self.peek() {
+            Some(Token::Literal(Value::Number(n))) if n.is_i64() => {
+                let n = n.as_i64().unwrap_or_default();
+                self.pos += 1;
+                Ok(n)
+            },
+            _ => Err(syntax("Expected an integer".to_string(), self.position())),
+        }
+    }
+
+    // 括弧などの入れ子は parse_term を経由するので、ここで深さを制限する
+    fn parse_term(&mut self) -> Result<Filter, QueryError> {
+        self.depth += 1;
+        if self.depth > MAX_NESTING {
+            return Err(syntax("Expression is nested too deeply".to_string(), self.position()));
+        }
+        let filter = self.parse_primary();
+        self.depth -= 1;
+        filter
+    }
+
+    fn parse_primary(&mut self) -> Result<Filter, QueryError> {
+        let position = self.position();
+        let Some(token) = self.peek().cloned() else {
+            return Err(syntax("Unexpected end of expression".to_string(), position));
+        };
+        self.pos += 1;
+        match token {
+            Token::Dot => Ok(Filter::Identity),
+            Token::DotDot => Ok(Filter::Recurse),
+            Token::Field(name) => Ok(Filter::Field(name)),
+            Token::Literal(value) => Ok(Filter::Literal(value)),
+            Token::Symbol("(") => {
+                let filter = self.parse_pipe()?;
+                self.expect(")")?;
+                Ok(filter)
+            },
+            Token::Symbol("[") => {
+                if self.eat("]") {
+                    return Ok(Filter::Array(None));
+                }
+                let filter = self.parse_pipe()?;
+                self.expect("]")?;
+                Ok(Filter::Array(Some(Box::new(filter))))
+            },
+            Token::Symbol("{") => self.parse_object(),
+            Token::Ident(name) => match name.as_str() {
+                "true" => Ok(Filter::Literal(Value::Bool(true))),
+                "false" => Ok(Filter::Literal(Value::Bool(false))),
+                "null" => Ok(Filter::Literal(Value::Null)),
+                _ => {
+                    let with_argument = self.eat("(");
+                    let function = Function::lookup(&name, with_argument)
+                        .ok_or_else(|| syntax(format!("Unknown function: {}/{}", name, usize::from(with_argument)), position))?;
+                    if !with_argument {
+                        return Ok(Filter::Call(function, None));
+                    }
+                    let argument = self.parse_pipe()?;
+                    self.expect(")")?;
+                    Ok(Filter::Call(function, Some(Box::new(argument))))
+                },
+            },
+            token => Err(syntax(format!("Unexpected {}", describe(&token)), position)),
+        }
+    }
+
+    // {a, "b": .x, c: .y | .z}
+    fn parse_object(&mut self) -> Result<Filter, QueryError> {
+        let mut entries = Vec::new();
+        if self.eat("}") {
+            return Ok(Filter::Object(entries));
+        }
+        loop {
+            let position = self.position();
+            let key = match self.peek().cloned() {
+                Some(Token::Ident(name)) => name,
+                Some(Token::Literal(Value::String(name))) => name,
+                _ => return Err(syntax("Expected an object key".to_string(), position)),
+            };
+            self.pos += 1;
+            let value = if self.eat(":") {
+                let mut value = self.parse_alternative()?;
+                while self.eat("|") {
+                    value = Filter::Pipe(Box::new(value), Box::new(self.parse_alternative()?));
+                }
+                value
+            } else {
+                Filter::Field(key.clone())
+            };
+            entries.push((key, value));
+            if self.eat("}") {
+                return Ok(Filter::Object(entries));
+            }
+            self.expect(",")?;
+        }
+    }
+}
+
+fn syntax(message: String, position: usize) -> QueryError {
+    QueryError::Syntax { message, position }
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Dot => "'.'".to_string(),
+        Token::DotDot => "'..'".to_string(),
+        Token::Field(name) => format!("'.{}'", name),
+        Token::Ident(name) => format!("'{}'", name),
+        Token::Literal(value) => format!("'{}'", value),
+        Token::Symbol(symbol) => format!("'{}'", symbol),
+    }
+}
+
+const SYMBOLS: [&str; 17] = ["//", "==", "!=", "<=", ">=", "<", ">", "|", ",", ":", "?", "(", ")", "[", "]", "{", "}"];
+
+fn tokenize(chars: &[char]) -> Result<Vec<(Token, usize)>, QueryError> {
+    let is_ident_start = |c: char| c.is_ascii_alphabetic() || c == '_';
+    let is_ident = |c: char| c.is_ascii_alphanumeric() || c == '_';
+
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let position = i + 1;
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '.' {
+            if chars.get(i + 1) == Some(&'.') {
+                tokens.push((Token::DotDot, position));
+                i += 2;
+            } else if chars.get(i + 1).is_some_and(|c| is_ident_start(*c)) {
+                let end = (i + 1..chars.len()).find(|&j| !is_ident(chars[j])).unwrap_or(chars.len());
+                tokens.push((Token::Field(chars[i + 1..end].iter().collect()), position));
+                i = end;
+            } else if chars.get(i + 1) == Some(&'"') {
+                let (name, end) = read_string(chars, i + 1)?;
+                tokens.push((Token::Field(name), position));
+                i = end;
+            } else {
+                tokens.push((Token::Dot, position));
+                i += 1;
+            }
+        } else if c == '"' {
+            let (value, end) = read_string(chars, i)?;
+            tokens.push((Token::Literal(Value::String(value)), position));
+            i = end;
+        } else if c.is_ascii_digit() || (c == '-' && chars.get(i + 1).is_some_and(char::is_ascii_digit)) {
+            let end = (i + 1..chars.len())
+                .find(|&j| !(chars[j].is_ascii_digit() || matches!(chars[j], '.' | 'e' | 'E' | '+' | '-')))
+                .unwrap_or(chars.len());
+            let text = chars[i..end].iter().collect::<String>();
+            let value = serde_json::from_str::<Value>(&text)
+                .ok()
+                .filter(Value::is_number)
+                .ok_or_else(|| syntax(format!("Invalid number: {}", text), position))?;
+            tokens.push((Token::Literal(value), position));
+            i = end;
+        } else if is_ident_start(c) {
+            let end = (i..chars.len()).find(|&j| !is_ident(chars[j])).unwrap_or(chars.len());
+            tokens.push((Token::Ident(chars[i..end].iter().collect()), position));
+            i = end;
+        } else {
+            let symbol = SYMBOLS
+                .iter()
+                .find(|symbol| symbol.chars().enumerate().all(|(k, s)| chars.get(i + k) == Some(&s)))
+                .ok_or_else(|| syntax(format!("Unexpected character '{}'", c), position))?;
+            tokens.push((Token::Symbol(symbol), position));
+            i += symbol.chars().count();
+        }
+    }
+    Ok(tokens)
+}
+
+// "..." を読み取り、内容と閉じ引用符の次の位置を返す（エスケープは JSON と同じ）
+fn read_string(chars: &[char], start: usize) -> Result<(String, usize), QueryError> {
+    let mut i = start + 1;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 2,
+            '"' => {
+                let text = chars[start..=i].iter().collect::<String>();
+                let value = serde_json::from_str::<String>(&text)
+                    .map_err(|e| syntax(format!("Invalid string: {}", e), start + 1))?;
+                return Ok((value, i + 1));
+            },
+            _ => i += 1,
+        }
+    }
+    Err(syntax("Unterminated string".to_string(), start + 1))
+}
+
+// ==== 評価 ====
+
+type Values<'a> = Vec<Cow<'a, Value>>;
+
+// 評価で生成する値の数と、複製する要素の数の残り
+struct Budget {
+    values: usize,
+    nodes: usize,
+}
+
+impl Budget {
+    fn new() -> Self {
+        Budget { values: MAX_EVALUATED_VALUES, nodes: MAX_COPIED_NODES }
+    }
+
+    fn spend_value(&mut self) -> Result<(), QueryError> {
+        self.values = self.values.checked_sub(1).ok_or(QueryError::TooExpensive)?;
+        Ok(())
+    }
+
+    // 値の要素数を数えて差し引く（上限に達した時点で数えるのをやめる）
+    fn spend_copy(&mut self, value: &Value) -> Result<(), QueryError> {
+        let mut pending = vec![value];
+        while let Some(value) = pending.pop() {
+            self.nodes = self.nodes.checked_sub(1).ok_or(QueryError::TooExpensive)?;
+            match value {
+                Value::Array(items) => pending.extend(items),
+                Value::Object(map) => pending.extend(map.values()),
+                _ => {},
+            }
+        }
+        Ok(())
+    }
+}
+
+// 値を複製する（要素数を予算から差し引く）
+fn copy(value: &Value, budget: &mut Budget) -> Result<Value, QueryError> {
+    budget.spend_copy(value)?;
+    Ok(value.clone())
+}
+
+// 入力を別のフィルタにも渡すための複製（借用はそのまま、所有している値は複製する）
+fn duplicate<'a>(value: &Cow<'a, Value>, budget: &mut Budget) -> Result<Cow<'a, Value>, QueryError> {
+    match value {
+        Cow::Borrowed(value) => Ok(Cow::Borrowed(value)),
+        Cow::Owned(value) => copy(value, budget).map(Cow::Owned),
+    }
+}
+
+// 所有した値にする（借用している値は複製する）
+fn into_owned(value: Cow<'_, Value>, budget: &mut Budget) -> Result<Value, QueryError> {
+    match value {
+        Cow::Borrowed(value) => copy(value, budget),
+        Cow::Owned(value) => Ok(value),
+    }
+}
+
+fn emit<'a>(outputs: &mut Values<'a>, value: Cow<'a, Value>, budget: &mut Budget) -> Result<(), QueryError> {
+    budget.spend_value()?;
+    outputs.push(value);
+    Ok(())
+}
+
+// 入力1つに対するフィルタの出力をすべて outputs に追加する
+fn eval<'a>(filter: &Filter, input: Cow<'a, Value>, outputs: &mut Values<'a>, budget: &mut Budget) -> Result<(), QueryError> {
+    match filter {
+        Filter::Identity => emit(outputs, input, budget),
+        Filter::Recurse => recurse(input, outputs, budget),
+        Filter::Field(name) => {
+            let value = child(&input, budget, |value| match value {
+                Value::Object(map) => Ok(map.get(name)),
+                Value::Null => Ok(None),
+                other => Err(QueryError::Runtime(format!("Cannot index {} with \"{}\"", type_name(other), name))),
+            })?;
+            emit(outputs, value, budget)
+        },
+        Filter::Index(index) => {
+            let value = child(&input, budget, |value| match value {
+                Value::Array(items) => Ok(resolve_index(*index, items.len()).and_then(|i| items.get(i))),
+                Value::Null => Ok(None),
+                other => Err(QueryError::Runtime(format!("Cannot index {} with number", type_name(other)))),
+            })?;
+            emit(outputs, value, budget)
+        },
+        Filter::Slice(start, end) => {
+            let value = match input.as_ref() {
+                Value::Null => Value::Null,
+                Value::Array(items) => {
+                    let (from, to) = slice_bounds(*start, *end, items.len());
+                    Value::Array(items[from..to].iter().map(|item| copy(item, budget)).collect::<Result<_, _>>()?)
+                },
+                Value::String(s) => {
+                    let chars = s.chars().collect::<Vec<_>>();
+                    let (from, to) = slice_bounds(*start, *end, chars.len());
+                    Value::String(chars[from..to].iter().collect())
+                },
+                other => return Err(QueryError::Runtime(format!("Cannot slice {}", type_name(other)))),
+            };
+            emit(outputs, Cow::Owned(value), budget)
+        },
+        Filter::Iterate => {
+            let children = match input {
+                Cow::Borrowed(Value::Array(items)) => items.iter().map(Cow::Borrowed).collect::<Values>(),
+                Cow::Borrowed(Value::Object(map)) => map.values().map(Cow::Borrowed).collect(),
+                Cow::Owned(Value::Array(items)) => items.into_iter().map(Cow::Owned).collect(),
+                Cow::Owned(Value::Object(map)) => map.into_iter().map(|(_, value)| Cow::Owned(value)).collect(),
+                other => return Err(QueryError::Runtime(format!("Cannot iterate over {}", type_name(&other)))),
+            };
+            for value in children {
+                emit(outputs, value, budget)?;
+            }
+            Ok(())
+        },
+        Filter::Literal(value) => emit(outputs, Cow::Owned(copy(value, budget)?), budget),
+        Filter::Try(inner) => {
+            // エラーまでに出力された値は残す
+            let mut partial = Vec::new();
+            match eval(inner, input, &mut partial, budget) {
+                Err(QueryError::Runtime(_)) | Ok(()) => {
+                    outputs.append(&mut partial);
+                    Ok(())
+                },
+                Err(e) => Err(e),
+            }
+        },
+        Filter::Pipe(left, right) => {
+            let mut intermediate = Vec::new();
+            eval(left, input, &mut intermediate, budget)?;
+            for value in intermediate {
+                eval(right, value, outputs, budget)?;
+            }
+            Ok(())
+        },
+        Filter::Comma(left, right) => {
+            eval(left, duplicate(&input, budget)?, outputs, budget)?;
+            eval(right, input, outputs, budget)
+        },
+        Filter::Alternative(left, right) => {
+            let mut candidates = Vec::new();
+            match eval(left, duplicate(&input, budget)?, &mut candidates, budget) {
+                Ok(()) | Err(QueryError::Runtime(_)) => {},
+                Err(e) => return Err(e),
+            }
+            let truthy = candidates.into_iter().filter(|value| is_truthy(value)).collect::<Values>();
+            if truthy.is_empty() {
+                eval(right, input, outputs, budget)
+            } else {
+                outputs.extend(truthy);
+                Ok(())
+            }
+        },
+        Filter::And(left, right) | Filter::Or(left, right) => {
+            let is_and = matches!(filter, Filter::And(..));
+            for value in collect(left, duplicate(&input, budget)?, budget)? {
+                // and は左が偽、or は左が真なら右を評価しない
+                if is_truthy(&value) != is_and {
+                    emit(outputs, Cow::Owned(Value::Bool(!is_and)), budget)?;
+                    continue;
+                }
+                for value in collect(right, duplicate(&input, budget)?, budget)? {
+                    emit(outputs, Cow::Owned(Value::Bool(is_truthy(&value))), budget)?;
+                }
+            }
+            Ok(())
+        },
+        Filter::Compare(left, op, right) => {
+            let rights = collect(right, duplicate(&input, budget)?, budget)?;
+            for left in collect(left, input, budget)? {
+                for right in &rights {
+                    let ordering = compare(&left, right);
+                    let result = match op {
+                        CompareOp::Eq => ordering == Ordering::Equal,
+                        CompareOp::Ne => ordering != Ordering::Equal,
+                        CompareOp::Lt => ordering == Ordering::Less,
+                        CompareOp::Le => ordering != Ordering::Greater,
+                        CompareOp::Gt => ordering == Ordering::Greater,
+                        CompareOp::Ge => ordering != Ordering::Less,
+                    };
+                    emit(outputs, Cow::Owned(Value::Bool(result)), budget)?;
+                }
+            }
+            Ok(())
+        },
+        Filter::Array(inner) => {
+            let items = match inner {
+                Some(inner) => collect(inner, input, budget)?
+                    .into_iter()
+                    .map(|value| into_owned(value, budget))
+                    .collect::<Result<_, _>>()?,
+                None => Vec::new(),
+            };
+            emit(outputs, Cow::Owned(Value::Array(items)), budget)
+        },
+        Filter::Object(entries) => {
+            // 値が複数出力される場合はすべての組み合わせを作る
+            let mut objects = vec![Map::new()];
+            for (key, filter) in entries {
+                let values = collect(filter, duplicate(&input, budget)?, budget)?;
+                let mut next = Vec::new();
+                for object in &objects {
+                    for value in &values {
+                        budget.spend_value()?;
+                        let mut object = object
+                            .iter()
+                            .map(|(key, value)| Ok((key.clone(), copy(value, budget)?)))
+                            .collect::<Result<Map<_, _>, QueryError>>()?;
+                        object.insert(key.clone(), copy(value, budget)?);
+                        next.push(object);
+                    }
+                }
+                objects = next;
+            }
+            for object in objects {
+                emit(outputs, Cow::Owned(Value::Object(object)), budget)?;
+            }
+            Ok(())
+        },
+        Filter::Call(function, argument) => call(*function, argument.as_deref(), input, outputs, budget),
+    }
+}
+
+fn collect<'a>(filter: &Filter, input: Cow<'a, Value>, budget: &mut Budget) -> Result<Values<'a>, QueryError> {
+    let mut outputs = Vec::new();
+    eval(filter, input, &mut outputs, budget)?;
+    Ok(outputs)
+}
+
+// 入力が借用ならそのまま借用、所有なら複製した子要素を返す
+fn child<'a>(
+    input: &Cow<'a, Value>,
+    budget: &mut Budget,
+    pick: impl for<'b> Fn(&'b Value) -> Result<Option<&'b Value>, QueryError>,
+) -> Result<Cow<'a, Value>, QueryError> {
+    let value = match input {
+        Cow::Borrowed(value) => pick(value)?.map(Cow::Borrowed),
+        Cow::Owned(value) => pick(value)?.map(|value| copy(value, budget)).transpose()?.map(Cow::Owned),
+    };
+    Ok(value.unwrap_or(Cow::Owned(Value::Null)))
+}
+
+fn recurse<'a>(input: Cow<'a, Value>, outputs: &mut Values<'a>, budget: &mut Budget) -> Result<(), QueryError> {
+    let children = match &input {
+        Cow::Borrowed(value) => match *value {
+            Value::Array(items) => items.iter().map(Cow::Borrowed).collect::<Values>(),
+            Value::Object(map) => map.values().map(Cow::Borrowed).collect(),
+            _ => Vec::new(),
+        },
+        Cow::Owned(Value::Array(items)) => items.iter().map(|item| copy(item, budget).map(Cow::Owned)).collect::<Result<_, _>>()?,
+        Cow::Owned(Value::Object(map)) => map.values().map(|value| copy(value, budget).map(Cow::Owned)).collect::<Result<_, _>>()?,
+        Cow::Owned(_) => Vec::new(),
+    };
+    emit(outputs, input, budget)?;
+    for value in children {
+        recurse(value, outputs, budget)?;
+    }
+    Ok(())
+}
+
+fn call<'a>(
+    function: Function,
+    argument: Option<&Filter>,
+    input: Cow<'a, Value>,
+    outputs: &mut Values<'a>,
+    budget: &mut Budget,
+) -> Result<(), QueryError> {
+    let argument = argument.unwrap_or(&Filter::Identity);
+    let value = match function {
+        Function::Empty => return Ok(()),
+        Function::Select => {
+            for condition in collect(argument, duplicate(&input, budget)?, budget)? {
+                if is_truthy(&condition) {
+                    emit(outputs, duplicate(&input, budget)?, budget)?;
+                }
+            }
+            return Ok(());
+        },
+        Function::Map => {
+            let mut items = Vec::new();
+            eval(&Filter::Iterate, input, &mut items, budget)?;
+            let mut mapped = Vec::new();
+            for item in items {
+                for value in collect(argument, item, budget)? {
+                    mapped.push(into_owned(value, budget)?);
+                }
+            }
+            Value::Array(mapped)
+        },
+        Function::Has => {
+            for key in collect(argument, duplicate(&input, budget)?, budget)? {
+                let has = match (input.as_ref(), key.as_ref()) {
+                    (Value::Object(map), Value::String(key)) => map.contains_key(key),
+                    (Value::Array(items), Value::Number(n)) => n.as_f64().is_some_and(|n| n >= 0.0 && (n as usize) < items.len()),
+                    (value, key) => {
+                        return Err(QueryError::Runtime(format!("Cannot check whether {} has a {} key", type_name(value), type_name(key))));
+                    },
+                };
+                emit(outputs, Cow::Owned(Value::Bool(has)), budget)?;
+            }
+            return Ok(());
+        },
+        Function::SortBy => {
+            let Value::Array(items) = input.as_ref() else {
+                return Err(QueryError::Runtime(format!("Cannot sort {}", type_name(&input))));
+            };
+            let mut keyed = Vec::with_capacity(items.len());
+            for item in items {
+                let key = collect(argument, Cow::Borrowed(item), budget)?
+                    .into_iter()
+                    .map(|value| into_owned(value, budget))
+                    .collect::<Result<Vec<_>, _>>()?;
+                keyed.push((Value::Array(key), item));
+            }
+            keyed.sort_by(|a, b| compare(&a.0, &b.0));
+            Value::Array(keyed.into_iter().map(|(_, item)| copy(item, budget)).collect::<Result<_, _>>()?)
+        },
+        Function::Length => match input.as_ref() {
+            Value::Null => Value::from(0),
+            Value::Bool(_) => return Err(QueryError::Runtime("boolean has no length".to_string())),
+            Value::Number(n) => match (n.as_i64(), n.as_f64()) {
+                (Some(i), _) => Value::from(i.unsigned_abs()),
+                (None, Some(f)) => Value::from(f.abs()),
+                _ => Value::Number(n.clone()),
+            },
+            Value::String(s) => Value::from(s.chars().count()),
+            Value::Array(items) => Value::from(items.len()),
+            Value::Object(map) => Value::from(map.len()),
+        },
+        Function::Keys | Function::KeysUnsorted => match input.as_ref() {
+            Value::Object(map) => {
+                let mut keys = map.keys().cloned().collect::<Vec<_>>();
+                if function == Function::Keys {
+                    keys.sort();
+                }
+                Value::from(keys)
+            },
+            Value::Array(items) => Value::from((0..items.len()).collect::<Vec<_>>()),
+            other => return Err(QueryError::Runtime(format!("{} has no keys", type_name(other)))),
+        },
+        Function::Type => Value::from(type_name(&input)),
+        Function::Not => Value::Bool(!is_truthy(&input)),
+        Function::First | Function::Last => {
+            let index = if function == Function::First { 0 } else { -1 };
+            return eval(&Filter::Index(index), input, outputs, budget);
+        },
+        Function::Reverse => match input.as_ref() {
+            Value::Null => Value::Array(Vec::new()),
+            Value::Array(items) => Value::Array(items.iter().rev().map(|item| copy(item, budget)).collect::<Result<_, _>>()?),
+            Value::String(s) => Value::String(s.chars().rev().collect()),
+            other => return Err(QueryError::Runtime(format!("Cannot reverse {}", type_name(other)))),
+        },
+        Function::Sort | Function::Unique | Function::Min | Function::Max => {
+            let Value::Array(items) = input.as_ref() else {
+                return Err(QueryError::Runtime(format!("Cannot sort {}", type_name(&input))));
+            };
+            let mut items = items.iter().collect::<Vec<_>>();
+            items.sort_by(|a, b| compare(a, b));
+            match function {
+                Function::Min => items.first().map(|v| copy(v, budget)).transpose()?.unwrap_or(Value::Null),
+                Function::Max => items.last().map(|v| copy(v, budget)).transpose()?.unwrap_or(Value::Null),
+                Function::Unique => {
+                    items.dedup_by(|a, b| compare(a, b) == Ordering::Equal);
+                    Value::Array(items.into_iter().map(|item| copy(item, budget)).collect::<Result<_, _>>()?)
+                },
+                _ => Value::Array(items.into_iter().map(|item| copy(item, budget)).collect::<Result<_, _>>()?),
+            }
+        },
+        Function::Add => {
+            let mut items = Vec::new();
+            eval(&Filter::Iterate, input, &mut items, budget)?;
+            let mut sum = Value::Null;
+            for item in items {
+                sum = add(sum, into_owned(item, budget)?)?;
+            }
+            sum
+        },
+        Function::ToString => match input.as_ref() {
+            Value::String(s) => Value::String(s.clone()),
+            other => {
+                budget.spend_copy(other)?;
+                Value::String(other.to_string())
+            },
+        },
+        Function::ToNumber => match input.as_ref() {
+            Value::Number(n) => Value::Number(n.clone()),
+            Value::String(s) => serde_json::from_str::<Value>(s.trim())
+                .ok()
+                .filter(Value::is_number)
+                .ok_or_else(|| QueryError::Runtime(format!("Cannot parse '{}' as a number", s)))?,
+            other => return Err(QueryError::Runtime(format!("{} cannot be parsed as a number", type_name(other)))),
+        },
+        Function::ToEntries => match input.as_ref() {
+            Value::Object(map) => Value::Array(
+                map.iter()
+                    .map(|(key, value)| Ok(serde_json::json!({ "key": key, "value": copy(value, budget)? })))
+                    .collect::<Result<_, QueryError>>()?,
+            ),
+            other => return Err(QueryError::Runtime(format!("{} has no keys", type_name(other)))),
+        },
+    };
+    emit(outputs, Cow::Owned(value), budget)
+}
+
+fn add(left: Value, right: Value) -> Result<Value, QueryError> {
+    match (left, right) {
+        (Value::Null, value) | (value, Value::Null) => Ok(value),
+        (Value::Number(a), Value::Number(b)) => match (a.as_i64(), b.as_i64()) {
+            (Some(a), Some(b)) if a.checked_add(b).is_some() => Ok(Value::from(a + b)),
+            _ => Ok(serde_json::Number::from_f64(a.as_f64().unwrap_or_default() + b.as_f64().unwrap_or_default())
+                .map(Value::Number)
+                .unwrap_or(Value::Null)),
+        },
+        (Value::String(a), Value::String(b)) => Ok(Value::String(a + &b)),
+        (Value::Array(mut a), Value::Array(b)) => {
+            a.extend(b);
+            Ok(Value::Array(a))
+        },
+        (Value::Object(mut a), Value::Object(b)) => {
+            a.extend(b);
+            Ok(Value::Object(a))
+        },
+        (a, b) => Err(QueryError::Runtime(format!("{} and {} cannot be added", type_name(&a), type_name(&b)))),
+    }
+}
+
+fn resolve_index(index: i64, len: usize) -> Option<usize> {
+    if index >= 0 {
+        Some(index as usize)
+    } else {
+        len.checked_sub(index.unsigned_abs() as usize)
+    }
+}
+
+fn slice_bounds(start: Option<i64>, end: Option<i64>, len: usize) -> (usize, usize) {
+    let clamp = |index: i64| -> usize {
+        if index < 0 { len.saturating_sub(index.unsigned_abs() as usize) } else { (index as usize).min(len) }
+    };
+    let from = start.map(clamp).unwrap_or(0);
+    let to = end.map(clamp).unwrap_or(len);
+    (from, to.max(from))
+}
+
+fn is_truthy(value: &Value) -> bool {
+    !matches!(value, Value::Null | Value::Bool(false))
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+// jq と同じ順序: null < false < true < 数値 < 文字列 < 配列 < オブジェクト
+fn compare(a: &Value, b: &Value) -> Ordering {
+    fn rank(value: &Value) -> u8 {
+        match value {
+            Value::Null => 0,
+            Value::Bool(false) => 1,
+            Value::Bool(true) => 2,
+            Value::Number(_) => 3,
+            Value::String(_) => 4,
+            Value::Array(_) => 5,
+            Value::Object(_) => 6,
+        }
+    }
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => {
+            x.as_f64().unwrap_or_default().partial_cmp(&y.as_f64().unwrap_or_default()).unwrap_or(Ordering::Equal)
+        },
+        (Value::String(x), Value::String(y)) => x.cmp(y),
+        (Value::Array(x), Value::Array(y)) => {
+            x.iter().zip(y).map(|(x, y)| compare(x, y)).find(|o| *o != Ordering::Equal).unwrap_or(x.len().cmp(&y.len()))
+        },
+        (Value::Object(x), Value::Object(y)) => {
+            // キーの集合を比べ、同じならキー順に値を比べる
+            let mut x_keys = x.keys().collect::<Vec<_>>();
+            let mut y_keys = y.keys().collect::<Vec<_>>();
+            x_keys.sort();
+            y_keys.sort();
+            x_keys.cmp(&y_keys).then_with(|| {
+                x_keys.iter().map(|key| compare(&x[*key], &y[*key])).find(|o| *o != Ordering::Equal).unwrap_or(Ordering::Equal)
+            })
+        },
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
@@ -0,0 +1,185 @@
+use anyhow::{Result, anyhow};
+use image::{DynamicImage, GenericImageView};
+use image::imageops::FilterType;
+
+use crate::services::{compressor, converter, formats};
+
+// レスポンシブ画像として出力できるフォーマット
+pub const SUPPORTED_FORMATS: [&str; 4] = ["avif", "webp", "jpeg", "png"];
+
+// 一度に指定できる幅の数
+const MAX_WIDTHS: usize = 12;
+
+// 生成した画像1件分
+#[derive(Debug)]
+pub struct Variant {
+    pub file_name: String,
+    pub format: String,
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+// カンマ区切りの幅一覧を解析（昇順・重複なし）
+pub fn parse_widths(s: &str) -> Result<Vec<u32>> {
+    let mut widths = s
+        .split(',')
+        .map(str::trim)
+        .filter(|w| !w.is_empty())
+        .map(|w| w.trim_end_matches('w').parse::<u32>().map_err(|_| anyhow!("Invalid width: {}", w)))
+        .collect::<Result<Vec<_>>>()?;
+    widths.sort_unstable();
+    widths.dedup();
+
+    if widths.is_empty() || widths[0] == 0 {
+        return Err(anyhow!("Widths must be positive integers"));
+    }
+    if widths.len() > MAX_WIDTHS {
+        return Err(anyhow!("Too many widths (max {})", MAX_WIDTHS));
+    }
+    Ok(widths)
+}
+
+// カンマ区切りのフォーマット一覧を解析
+pub fn parse_formats(s: &str) -> Result<Vec<String>> {
+    let mut formats = Vec::new();
+    for format in s.split(',').map(|f| f.trim().to_lowercase()).filter(|f| !f.is_empty()) {
+        let format = if format == "jpg" { "jpeg".to_string() } else { format };
+        if !SUPPORTED_FORMATS.contains(&format.as_str()) {
+            return Err(anyhow!("Unsupported output format: {}", format));
+        }
+        // サーバーのImageMagickで書き出せない形式は受け付けない
+        formats::check_conversion("", &format, None)?;
+        if !formats.contains(&format) {
+            formats.push(format);
+        }
+    }
+    if formats.is_empty() {
+        return Err(anyhow!("No output formats specified"));
+    }
+    Ok(formats)
+}
+
+// 元画像より大きい幅は拡大になるため、元の幅に置き換える
+pub fn target_widths(original_width: u32, requested: &[u32]) -> Vec<u32> {
+    let mut widths: Vec<u32> = requested.iter().copied().filter(|&w| w <= original_width).collect();
+    if widths.len() < requested.len() && !widths.contains(&original_width) {
+        widths.push(original_width);
+    }
+    widths
+}
+
+// 各幅・各フォーマットの画像を生成
+// 縮小した画像から各フォーマットへ一度だけエンコードする（JPEGは mozjpeg、WebPは ImageMagick の非可逆、それ以外は converter のエンコーダー）
+// PNGは可逆のまま出力し、減色はしない
+pub fn generate_variants(
+    source: &DynamicImage,
+    base_name: &str,
+    widths: &[u32],
+    formats: &[String],
+    quality: i32,
+) -> Result<Vec<Variant>> {
+    let (original_width, original_height) = source.dimensions();
+    let mut variants = Vec::new();
+
+    for width in target_widths(original_width, widths) {
+        let height = ((original_height as f64 * width as f64 / original_width as f64).round() as u32).max(1);
+        tracing::debug!("リサイズ: {}x{} → {}x{}", original_width, original_height, width, height);
+
+        let resized = if width == original_width {
+            source.clone()
+        } else {
+            source.resize_exact(width, height, FilterType::Lanczos3)
+        };
+        for format in formats {
+            let extension = converter::extension_for(format);
+            let file_name = format!("{}-{}w.{}", base_name, width, extension);
+
+            let data = match format.as_str() {
+                "jpeg" => compressor::encode_jpeg(&resized, quality)?,
+                "webp" => compressor::encode_webp(&resized, quality)?,
+                _ => {
+                    let options = converter::ConvertOptions { quality: Some(quality as u8), ..Default::default() };
+                    converter::encode_image_with_options(&resized, format, &options)?
+                },
+            };
+            tracing::debug!("生成完了: {} ({} バイト)", file_name, data.len());
+            variants.push(Variant {
+                file_name,
+                format: format.clone(),
+                width,
+                height,
+                data,
+            });
+        }
+    }
+
+    Ok(variants)
+}
+
+// <picture> 要素のHTMLスニペットを生成
+// JPEG/PNGをフォールバックの <img> とし、それ以外を <source> として出力
+pub fn picture_html(variants: &[Variant], formats: &[String], sizes: &str, alt: &str, url_prefix: &str) -> String {
+    let fallback = formats
+        .iter()
+        .rev()
+        .find(|f| *f == "jpeg" || *f == "png")
+        .or_else(|| formats.last())
+        .cloned()
+        .unwrap_or_default();
+
+    let srcset = |format: &str| -> String {
+        variants
+            .iter()
+            .filter(|v| v.format == format)
+            .map(|v| format!("{}{} {}w", url_prefix, v.file_name, v.width))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    let mut html = String::from("<picture>\n");
+    for format in formats.iter().filter(|f| **f != fallback) {
+        html.push_str(&format!(
+            "  <source type=\"{}\" srcset=\"{}\" sizes=\"{}\">\n",
+            converter::mime_type_for(format),
+            escape_html(&srcset(format)),
+            escape_html(sizes),
+        ));
+    }
+
+    // 最大幅の画像を src とし、width/height でレイアウトシフトを防ぐ
+    if let Some(largest) = variants.iter().filter(|v| v.format == fallback).max_by_key(|v| v.width) {
+        html.push_str(&format!(
+            "  <img src=\"{}\" srcset=\"{}\" sizes=\"{}\" width=\"{}\" height=\"{}\" alt=\"{}\" loading=\"lazy\" decoding=\"async\">\n",
+            escape_html(&format!("{}{}", url_prefix, largest.file_name)),
+            escape_html(&srcset(&fallback)),
+            escape_html(sizes),
+            largest.width,
+            largest.height,
+            escape_html(alt),
+        ));
+    }
+    html.push_str("</picture>");
+    html
+}
+
+// ファイル名に使える文字だけを残したベース名
+pub fn sanitize_base_name(file_name: &str) -> String {
+    let stem = std::path::Path::new(file_name)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("");
+    let name: String = stem
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '-' })
+        .collect();
+    let name = name.trim_matches('-').to_lowercase();
+    if name.is_empty() { "image".to_string() } else { name }
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
@@ -0,0 +1,242 @@
+use anyhow::{Result, anyhow};
+use indexmap::IndexMap;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+// 推定したスキーマに付ける $schema
+pub const DRAFT_2020_12: &str = "https://json-schema.org/draft/2020-12/schema";
+// 返す違反の最大件数
+const MAX_VIOLATIONS: usize = 1000;
+
+// スキーマ違反1件分（パスはJSON Pointer）
+#[derive(Debug, Serialize)]
+pub struct Violation {
+    pub instance_path: String,
+    pub schema_path: String,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ValidationReport {
+    pub valid: bool,
+    pub errors: Vec<Violation>,
+    // 上限を超えたため省略された違反があるか
+    pub truncated: bool,
+}
+
+// JSON Schema（draft 2020-12）で検証し、すべての違反を返す
+pub fn validate(schema: &Value, instance: &Value) -> Result<ValidationReport> {
+    let validator = jsonschema::draft202012::options()
+        .should_validate_formats(true)
+        .build(schema)
+        .map_err(|e| anyhow!("Invalid schema at '{}': {}", e.schema_path.as_str(), e))?;
+
+    let mut errors = Vec::new();
+    let mut truncated = false;
+    for error in validator.iter_errors(instance) {
+        if errors.len() >= MAX_VIOLATIONS {
+            truncated = true;
+            break;
+        }
+        errors.push(Violation {
+            instance_path: error.instance_path.as_str().to_string(),
+            schema_path: error.schema_path.as_str().to_string(),
+            message: error.to_string(),
+        });
+    }
+    tracing::debug!("スキーマ検証完了: {}件の違反", errors.len());
+
+    Ok(ValidationReport { valid: errors.is_empty(), errors, truncated })
+}
+
+// 1つ以上の例からスキーマを推定
+pub fn infer(examples: &[Value]) -> Value {
+    let mut node = SchemaNode::default();
+    for example in examples {
+        node.observe(example);
+    }
+    let mut schema = Map::new();
+    schema.insert("$schema".to_string(), json!(DRAFT_2020_12));
+    if let Value::Object(inferred) = node.to_schema() {
+        schema.extend(inferred);
+    }
+    Value::Object(schema)
+}
+
+// 推定中のスキーマ（観測した値の型・プロパティ・要素をまとめる）
+#[derive(Debug, Default)]
+struct SchemaNode {
+    types: Vec<&'static str>,
+    // プロパティ名 → スキーマ・出現回数（出現順）
+    properties: IndexMap<String, (SchemaNode, usize)>,
+    // 観測したオブジェクトの数（required の判定に使う）
+    objects: usize,
+    items: Option<Box<SchemaNode>>,
+    // 文字列の形式（None: 未観測, Some(None): 形式が一致しない）
+    format: Option<Option<&'static str>>,
+}
+
+impl SchemaNode {
+    fn observe(&mut self, value: &Value) {
+        let type_name = match value {
+            Value::Null => "null",
+            Value::Bool(_) => "boolean",
+            Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+            Value::Number(_) => "number",
+            Value::String(_) => "string",
+            Value::Array(_) => "array",
+            Value::Object(_) => "object",
+        };
+        if !self.types.contains(&type_name) {
+            self.types.push(type_name);
+        }
+
+        match value {
+            Value::String(s) => {
+                let format = string_format(s);
+                self.format = match self.format {
+                    None => Some(format),
+                    Some(previous) if previous == format => Some(format),
+                    Some(_) => Some(None),
+                };
+            },
+            Value::Array(items) => {
+                let node = self.items.get_or_insert_with(Default::default);
+                for item in items {
+                    node.observe(item);
+                }
+            },
+            Value::Object(map) => {
+                self.objects += 1;
+                for (key, child) in map {
+                    let (node, count) = self.properties.entry(key.clone()).or_default();
+                    node.observe(child);
+                    *count += 1;
+                }
+            },
+            _ => {},
+        }
+    }
+
+    fn to_schema(&self) -> Value {
+        let mut schema = Map::new();
+
+        // integer と number が混在する場合は number にまとめる
+        let mut types = self.types.clone();
+        if types.contains(&"number") {
+            types.retain(|t| *t != "integer");
+        }
+        match types.as_slice() {
+            [] => {},
+            [single] => {
+                schema.insert("type".to_string(), json!(single));
+            },
+            multiple => {
+                schema.insert("type".to_string(), json!(multiple));
+            },
+        }
+
+        if let Some(Some(format)) = self.format {
+            schema.insert("format".to_string(), json!(format));
+        }
+
+        if self.objects > 0 {
+            let properties = self
+                .properties
+                .iter()
+                .map(|(name, (node, _))| (name.clone(), node.to_schema()))
+                .collect::<Map<String, Value>>();
+            // すべての例に現れたプロパティを必須とする
+            let required = self
+                .properties
+                .iter()
+                .filter(|(_, (_, count))| *count == self.objects)
+                .map(|(name, _)| json!(name))
+                .collect::<Vec<Value>>();
+            schema.insert("properties".to_string(), Value::Object(properties));
+            if !required.is_empty() {
+                schema.insert("required".to_string(), Value::Array(required));
+            }
+        }
+
+        if let Some(items) = &self.items {
+            if !items.types.is_empty() {
+                schema.insert("items".to_string(), items.to_schema());
+            }
+        }
+
+        Value::Object(schema)
+    }
+}
+
+// よく使われる文字列の形式を判定
+fn string_format(s: &str) -> Option<&'static str> {
+    if is_date_time(s) {
+        Some("date-time")
+    } else if is_date(s) {
+        Some("date")
+    } else if is_uuid(s) {
+        Some("uuid")
+    } else if is_email(s) {
+        Some("email")
+    } else if (s.starts_with("http://") || s.starts_with("https://")) && !s.contains(char::is_whitespace) {
+        Some("uri")
+    } else {
+        None
+    }
+}
+
+// YYYY-MM-DD
+fn is_date(s: &str) -> bool {
+    let b = s.as_bytes();
+    b.len() == 10
+        && b.iter().enumerate().all(|(i, c)| if i == 4 || i == 7 { *c == b'-' } else { c.is_ascii_digit() })
+}
+
+// YYYY-MM-DDThh:mm:ss[.fff](Z|±hh:mm)
+fn is_date_time(s: &str) -> bool {
+    if !s.is_ascii() || s.len() < 20 || !is_date(&s[..10]) || !matches!(s.as_bytes()[10], b'T' | b't') {
+        return false;
+    }
+    let time = &s[11..];
+    let b = time.as_bytes();
+    if b.len() < 9 || b[2] != b':' || b[5] != b':' || ![0, 1, 3, 4, 6, 7].iter().all(|&i| b[i].is_ascii_digit()) {
+        return false;
+    }
+    let mut rest = &time[8..];
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let digits = fraction.chars().take_while(|c| c.is_ascii_digit()).count();
+        if digits == 0 {
+            return false;
+        }
+        rest = &fraction[digits..];
+    }
+    match rest.as_bytes() {
+        [b'Z' | b'z'] => true,
+        [b'+' | b'-', h1, h2, b':', m1, m2] => [h1, h2, m1, m2].iter().all(|c| c.is_ascii_digit()),
+        _ => false,
+    }
+}
+
+// 8-4-4-4-12 の16進数
+fn is_uuid(s: &str) -> bool {
+    let b = s.as_bytes();
+    b.len() == 36
+        && b.iter().enumerate().all(|(i, c)| {
+            if matches!(i, 8 | 13 | 18 | 23) { *c == b'-' } else { c.is_ascii_hexdigit() }
+        })
+}
+
+fn is_email(s: &str) -> bool {
+    match s.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && !domain.contains('@')
+                && domain.contains('.')
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+                && !s.contains(char::is_whitespace)
+        },
+        None => false,
+    }
+}
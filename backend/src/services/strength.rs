@@ -0,0 +1,713 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::services::keyboard::{self, KeyboardGraph};
+
+// 分析するパスワードの最大長（文字数）
+pub const MAX_PASSWORD_CHARS: usize = 256;
+// 辞書と照合する部分文字列の最大長
+const MAX_WORD_CHARS: usize = 32;
+// 1つのパターンの最小推測回数（1文字・2文字以上）
+const MIN_GUESSES_SINGLE_CHAR: f64 = 10.0;
+const MIN_GUESSES_MULTI_CHAR: f64 = 50.0;
+// パターンが1つ増えるごとに加える推測回数（log10）
+const MIN_GUESSES_PER_PATTERN_LOG10: f64 = 4.0;
+// 最近の年からの最小の差（年単位の推測範囲）
+const MIN_YEAR_SPACE: f64 = 20.0;
+
+static COMMON_PASSWORDS: &str = include_str!("common_passwords.txt");
+static ENGLISH_WORDS: &str = include_str!("wordlist.txt");
+
+// 辞書（単語 → 頻度順位）
+struct Dictionaries {
+    passwords: HashMap<&'static str, usize>,
+    english: HashMap<&'static str, usize>,
+}
+
+static DICTIONARIES: OnceLock<Dictionaries> = OnceLock::new();
+
+fn dictionaries() -> &'static Dictionaries {
+    DICTIONARIES.get_or_init(|| {
+        let ranked = |list: &'static str| {
+            list.lines().map(str::trim).filter(|w| !w.is_empty()).enumerate().map(|(i, w)| (w, i + 1)).collect()
+        };
+        // 英単語リストは頻度順ではないので、すべて中央の順位とみなす
+        let english = ENGLISH_WORDS.lines().map(str::trim).filter(|w| !w.is_empty()).collect::<Vec<_>>();
+        let middle = english.len() / 2;
+        Dictionaries {
+            passwords: ranked(COMMON_PASSWORDS),
+            english: english.into_iter().map(|w| (w, middle)).collect(),
+        }
+    })
+}
+
+// よくある文字の置き換え（l33t）
+const LEET_TABLE: &[(char, &[char])] = &[
+    ('4', &['a']),
+    ('@', &['a']),
+    ('8', &['b']),
+    ('(', &['c']),
+    ('3', &['e']),
+    ('6', &['g']),
+    ('9', &['g']),
+    ('1', &['i', 'l']),
+    ('!', &['i']),
+    ('|', &['i', 'l']),
+    ('0', &['o']),
+    ('$', &['s']),
+    ('5', &['s']),
+    ('7', &['t']),
+    ('+', &['t']),
+    ('2', &['z']),
+];
+// 1つの部分文字列で試す置き換えの組み合わせの上限
+const MAX_LEET_VARIANTS: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DictionaryKind {
+    CommonPassword,
+    EnglishWord,
+    UserInput,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "pattern", rename_all = "snake_case")]
+pub enum Pattern {
+    Dictionary {
+        dictionary: DictionaryKind,
+        rank: usize,
+        reversed: bool,
+        l33t: bool,
+    },
+    Spatial {
+        keyboard: &'static str,
+        turns: usize,
+        shifted: usize,
+    },
+    Repeat {
+        repeat_count: usize,
+    },
+    Sequence {
+        ascending: bool,
+    },
+    Date {
+        year: i32,
+        separator: bool,
+    },
+    Year,
+    Bruteforce,
+}
+
+// パスワードの一部に一致したパターン（位置は文字単位、end は含まない）
+#[derive(Debug, Clone, Serialize)]
+pub struct PatternMatch {
+    #[serde(flatten)]
+    pub pattern: Pattern,
+    pub start: usize,
+    pub end: usize,
+    #[serde(skip)]
+    pub guesses_log10: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CrackTimes {
+    // オンライン攻撃（1時間に100回に制限）
+    pub online_throttling: f64,
+    // オンライン攻撃（1秒に10回）
+    pub online_no_throttling: f64,
+    // オフライン攻撃（bcrypt などの遅いハッシュ、1秒に1万回）
+    pub offline_slow_hashing: f64,
+    // オフライン攻撃（速いハッシュ、1秒に100億回）
+    pub offline_fast_hashing: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CrackTimesDisplay {
+    pub online_throttling: String,
+    pub online_no_throttling: String,
+    pub offline_slow_hashing: String,
+    pub offline_fast_hashing: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Feedback {
+    pub warning: Option<String>,
+    pub suggestions: Vec<String>,
+}
+
+// 強度の分析結果（score は 0〜4）
+#[derive(Debug, Serialize)]
+pub struct Analysis {
+    pub score: u8,
+    pub guesses_log10: f64,
+    pub crack_times_seconds: CrackTimes,
+    pub crack_times_display: CrackTimesDisplay,
+    pub feedback: Feedback,
+    pub sequence: Vec<PatternMatch>,
+}
+
+// パスワードの強度を推定する（user_inputs はユーザー名など推測に使われやすい語）
+pub fn analyze(password: &str, user_inputs: &[String]) -> Analysis {
+    let chars = password.chars().take(MAX_PASSWORD_CHARS).collect::<Vec<_>>();
+    let user_inputs = user_inputs
+        .iter()
+        .map(|w| w.trim().to_lowercase())
+        .filter(|w| !w.is_empty())
+        .enumerate()
+        .map(|(i, w)| (w, i + 1))
+        .collect::<HashMap<_, _>>();
+
+    let matches = find_matches(&chars, &user_inputs);
+    let (guesses_log10, sequence) = most_guessable(&chars, matches);
+
+    let score = match guesses_log10 {
+        g if g < 3.0 => 0,
+        g if g < 6.0 => 1,
+        g if g < 8.0 => 2,
+        g if g < 10.0 => 3,
+        _ => 4,
+    };
+    let seconds = |per_second: f64| 10f64.powf(guesses_log10) / per_second;
+    let crack_times_seconds = CrackTimes {
+        online_throttling: seconds(100.0 / 3600.0),
+        online_no_throttling: seconds(10.0),
+        offline_slow_hashing: seconds(1e4),
+        offline_fast_hashing: seconds(1e10),
+    };
+    let crack_times_display = CrackTimesDisplay {
+        online_throttling: display_time(crack_times_seconds.online_throttling),
+        online_no_throttling: display_time(crack_times_seconds.online_no_throttling),
+        offline_slow_hashing: display_time(crack_times_seconds.offline_slow_hashing),
+        offline_fast_hashing: display_time(crack_times_seconds.offline_fast_hashing),
+    };
+
+    Analysis {
+        score,
+        guesses_log10: (guesses_log10 * 100.0).round() / 100.0,
+        crack_times_seconds,
+        crack_times_display,
+        feedback: feedback(score, &sequence, &chars),
+        sequence,
+    }
+}
+
+// ==== パターンの検出 ====
+
+fn find_matches(chars: &[char], user_inputs: &HashMap<String, usize>) -> Vec<PatternMatch> {
+    let mut matches = Vec::new();
+    dictionary_matches(chars, user_inputs, &mut matches);
+    spatial_matches(chars, &mut matches);
+    repeat_matches(chars, user_inputs, &mut matches);
+    sequence_matches(chars, &mut matches);
+    date_matches(chars, &mut matches);
+    matches
+}
+
+fn dictionary_matches(chars: &[char], user_inputs: &HashMap<String, usize>, matches: &mut Vec<PatternMatch>) {
+    let dictionaries = dictionaries();
+    let lower = chars.iter().flat_map(|c| c.to_lowercase()).collect::<Vec<_>>();
+    // 小文字にすると文字数が変わる場合は位置がずれるので元の文字を使う
+    let lower = if lower.len() == chars.len() { lower } else { chars.to_vec() };
+
+    for start in 0..chars.len() {
+        for end in start + 1..=chars.len().min(start + MAX_WORD_CHARS) {
+            let original = &chars[start..end];
+            let word = &lower[start..end];
+            let forward = word.iter().collect::<String>();
+            let reversed = word.iter().rev().collect::<String>();
+
+            let mut candidates = vec![(forward.clone(), false, false)];
+            if reversed != forward {
+                candidates.push((reversed, true, false));
+            }
+            for variant in leet_variants(word) {
+                candidates.push((variant, false, true));
+            }
+
+            for (candidate, reversed, l33t) in candidates {
+                let found = [
+                    (DictionaryKind::CommonPassword, dictionaries.passwords.get(candidate.as_str()).copied()),
+                    (DictionaryKind::EnglishWord, dictionaries.english.get(candidate.as_str()).copied()),
+                    (DictionaryKind::UserInput, user_inputs.get(&candidate).copied()),
+                ];
+                for (dictionary, rank) in found {
+                    let Some(rank) = rank else { continue };
+                    let mut guesses = rank as f64 * uppercase_variations(original);
+                    if reversed {
+                        guesses *= 2.0;
+                    }
+                    if l33t {
+                        guesses *= leet_variations(original, &candidate);
+                    }
+                    matches.push(PatternMatch {
+                        pattern: Pattern::Dictionary { dictionary, rank, reversed, l33t },
+                        start,
+                        end,
+                        guesses_log10: guesses.log10(),
+                    });
+                }
+            }
+        }
+    }
+}
+
+// l33t の置き換えを戻した候補（置き換えを含まない場合は空）
+fn leet_variants(word: &[char]) -> Vec<String> {
+    let mut variants = vec![String::new()];
+    let mut substituted = false;
+    for c in word {
+        match LEET_TABLE.iter().find(|(leet, _)| leet == c) {
+            Some((_, letters)) => {
+                substituted = true;
+                variants = variants
+                    .iter()
+                    .flat_map(|v| letters.iter().map(move |l| format!("{}{}", v, l)))
+                    .take(MAX_LEET_VARIANTS)
+                    .collect();
+            },
+            None => variants.iter_mut().for_each(|v| v.push(*c)),
+        }
+    }
+    if substituted { variants } else { Vec::new() }
+}
+
+// 大文字の位置の組み合わせ数（すべて小文字・先頭だけ・末尾だけ・すべて大文字は少なく見積もる）
+fn uppercase_variations(word: &[char]) -> f64 {
+    let upper = word.iter().filter(|c| c.is_uppercase()).count();
+    let lower = word.iter().filter(|c| c.is_lowercase()).count();
+    if upper == 0 {
+        return 1.0;
+    }
+    let first_only = word.first().is_some_and(|c| c.is_uppercase()) && upper == 1;
+    let last_only = word.last().is_some_and(|c| c.is_uppercase()) && upper == 1;
+    if first_only || last_only || lower == 0 {
+        return 2.0;
+    }
+    (1..=upper.min(lower)).map(|i| binomial(upper + lower, i)).sum::<f64>().max(1.0)
+}
+
+// 置き換えた文字の組み合わせ数
+fn leet_variations(original: &[char], plain: &str) -> f64 {
+    let mut variations = 1.0;
+    let plain = plain.chars().collect::<Vec<_>>();
+    for (leet, letters) in LEET_TABLE {
+        for letter in letters.iter() {
+            let pairs = original.iter().zip(&plain).filter(|(_, p)| *p == letter).collect::<Vec<_>>();
+            let subbed = pairs.iter().filter(|(o, _)| *o == leet).count();
+            if subbed == 0 {
+                continue;
+            }
+            let unsubbed = pairs.iter().filter(|(o, p)| o.to_lowercase().eq(p.to_lowercase())).count();
+            variations *= if unsubbed == 0 {
+                2.0
+            } else {
+                (1..=subbed.min(unsubbed)).map(|i| binomial(subbed + unsubbed, i)).sum::<f64>()
+            };
+        }
+    }
+    variations
+}
+
+fn spatial_matches(chars: &[char], matches: &mut Vec<PatternMatch>) {
+    for graph in keyboard::graphs() {
+        let mut start = 0;
+        while start + 2 < chars.len() {
+            let mut end = start + 1;
+            let mut turns = 0;
+            let mut last_direction = None;
+            while end < chars.len() {
+                let Some(direction) = graph.direction(chars[end - 1], chars[end]) else { break };
+                if last_direction != Some(direction) {
+                    turns += 1;
+                    last_direction = Some(direction);
+                }
+                end += 1;
+            }
+            if end - start >= 3 {
+                let shifted = chars[start..end].iter().filter(|c| graph.normalize(**c).1).count();
+                matches.push(PatternMatch {
+                    pattern: Pattern::Spatial { keyboard: graph.name, turns, shifted },
+                    start,
+                    end,
+                    guesses_log10: spatial_guesses(graph, end - start, turns, shifted).log10(),
+                });
+                start = end - 1;
+            } else {
+                start += 1;
+            }
+        }
+    }
+}
+
+// 長さ・曲がった回数・Shift の数から推測回数を求める
+fn spatial_guesses(graph: &KeyboardGraph, length: usize, turns: usize, shifted: usize) -> f64 {
+    let keys = graph.key_count() as f64;
+    let degree = graph.average_degree();
+    let mut guesses = 0.0;
+    for i in 2..=length {
+        for j in 1..=turns.min(i - 1) {
+            guesses += binomial(i - 1, j - 1) * keys * degree.powi(j as i32);
+        }
+    }
+    if shifted > 0 {
+        let unshifted = length - shifted;
+        guesses *= if unshifted == 0 {
+            2.0
+        } else {
+            (1..=shifted.min(unshifted)).map(|i| binomial(length, i)).sum::<f64>()
+        };
+    }
+    guesses
+}
+
+fn repeat_matches(chars: &[char], user_inputs: &HashMap<String, usize>, matches: &mut Vec<PatternMatch>) {
+    let mut start = 0;
+    while start < chars.len() {
+        // 最も長く続く繰り返しを探す
+        let mut best: Option<(usize, usize)> = None;
+        for base in 1..=(chars.len() - start) / 2 {
+            let unit = &chars[start..start + base];
+            let count = chars[start..].chunks(base).take_while(|chunk| *chunk == unit).count();
+            let length = count * base;
+            let enough = if base == 1 { count >= 3 } else { count >= 2 };
+            if enough && best.is_none_or(|(b, c)| length > b * c) {
+                best = Some((base, count));
+            }
+        }
+        match best {
+            Some((base, count)) => {
+                let unit = &chars[start..start + base];
+                let base_guesses = most_guessable(unit, find_matches(unit, user_inputs)).0;
+                matches.push(PatternMatch {
+                    pattern: Pattern::Repeat { repeat_count: count },
+                    start,
+                    end: start + base * count,
+                    guesses_log10: base_guesses + (count as f64).log10(),
+                });
+                start += base * count;
+            },
+            None => start += 1,
+        }
+    }
+}
+
+fn sequence_matches(chars: &[char], matches: &mut Vec<PatternMatch>) {
+    let mut start = 0;
+    while start + 2 < chars.len() {
+        let delta = chars[start + 1] as i64 - chars[start] as i64;
+        if delta == 0 || delta.abs() > 5 {
+            start += 1;
+            continue;
+        }
+        let mut end = start + 2;
+        while end < chars.len() && chars[end] as i64 - chars[end - 1] as i64 == delta {
+            end += 1;
+        }
+        if end - start >= 3 {
+            let first = chars[start];
+            let base = if matches!(first, 'a' | 'A' | 'z' | 'Z' | '0' | '1' | '9') {
+                4.0
+            } else if first.is_ascii_digit() {
+                10.0
+            } else {
+                26.0
+            };
+            let guesses = base * (end - start) as f64 * if delta > 0 { 1.0 } else { 2.0 };
+            matches.push(PatternMatch {
+                pattern: Pattern::Sequence { ascending: delta > 0 },
+                start,
+                end,
+                guesses_log10: guesses.log10(),
+            });
+            start = end - 1;
+        } else {
+            start += 1;
+        }
+    }
+}
+
+fn current_year() -> i32 {
+    let seconds = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+    1970 + (seconds / 31_556_952) as i32
+}
+
+fn year_guesses(year: i32) -> f64 {
+    ((year - current_year()).abs() as f64).max(MIN_YEAR_SPACE)
+}
+
+fn date_matches(chars: &[char], matches: &mut Vec<PatternMatch>) {
+    let reference = current_year();
+    for start in 0..chars.len() {
+        for end in start + 4..=chars.len().min(start + 10) {
+            let token = &chars[start..end];
+            let digits = token.iter().filter(|c| c.is_ascii_digit()).count();
+
+            // 区切りなし（1990, 19901231, 311290 など）
+            if digits == token.len() {
+                let number = token.iter().collect::<String>();
+                if token.len() == 4 {
+                    if let Ok(year) = number.parse::<i32>() {
+                        if (1900..=2099).contains(&year) {
+                            matches.push(PatternMatch {
+                                pattern: Pattern::Year,
+                                start,
+                                end,
+                                guesses_log10: year_guesses(year).log10(),
+                            });
+                        }
+                    }
+                }
+                if token.len() <= 8 {
+                    if let Some(year) = split_date(&number, reference) {
+                        matches.push(date_match(year, false, start, end));
+                    }
+                }
+                continue;
+            }
+
+            // 区切りあり（1990-12-31, 31/12/90 など）
+            let separator = token.iter().find(|c| !c.is_ascii_digit());
+            let Some(&separator) = separator.filter(|c| " /\\_.-".contains(**c)) else { continue };
+            let parts = token.iter().collect::<String>();
+            let parts = parts.split(separator).collect::<Vec<_>>();
+            let valid = parts.len() == 3 && parts.iter().all(|p| !p.is_empty() && p.chars().all(|c| c.is_ascii_digit()));
+            if !valid {
+                continue;
+            }
+            if let Some(year) = date_from_parts(parts[0], parts[1], parts[2], reference) {
+                matches.push(date_match(year, true, start, end));
+            }
+        }
+    }
+}
+
+fn date_match(year: i32, separator: bool, start: usize, end: usize) -> PatternMatch {
+    let mut guesses = year_guesses(year) * 365.0;
+    if separator {
+        guesses *= 4.0;
+    }
+    PatternMatch { pattern: Pattern::Date { year, separator }, start, end, guesses_log10: guesses.log10() }
+}
+
+// 区切りのない数字を年・月・日に分けられるか試す
+fn split_date(number: &str, reference: i32) -> Option<i32> {
+    let len = number.len();
+    let splits: &[(usize, usize)] = match len {
+        4 => &[(1, 2), (2, 3)],
+        5 => &[(1, 3), (2, 3)],
+        6 => &[(1, 2), (2, 4), (4, 5)],
+        7 => &[(1, 3), (2, 3), (4, 5), (4, 6)],
+        8 => &[(2, 4), (4, 6)],
+        _ => return None,
+    };
+    splits
+        .iter()
+        .find_map(|&(a, b)| date_from_parts(&number[..a], &number[a..b], &number[b..], reference))
+}
+
+// 3つの数字のどれかが年として解釈でき、残りが月・日になる場合に年を返す
+fn date_from_parts(a: &str, b: &str, c: &str, reference: i32) -> Option<i32> {
+    let parse = |s: &str| s.parse::<i32>().ok();
+    let (a_value, b_value, c_value) = (parse(a)?, parse(b)?, parse(c)?);
+    let candidates = [(a, a_value, b_value, c_value), (c, c_value, a_value, b_value)];
+    for (text, year, first, second) in candidates {
+        let year = match text.len() {
+            4 if (1000..=2099).contains(&year) => year,
+            2 if year > reference % 100 + 10 => 1900 + year,
+            2 => 2000 + year,
+            _ => continue,
+        };
+        let valid = |month: i32, day: i32| (1..=12).contains(&month) && (1..=31).contains(&day);
+        if valid(first, second) || valid(second, first) {
+            return Some(year);
+        }
+    }
+    None
+}
+
+// ==== 最も推測しやすい分解 ====
+
+// 推測回数の積が最小になるようにパターンを並べる（見つからない部分は総当たり）
+// 戻り値は推測回数の log10 と使ったパターン
+fn most_guessable(chars: &[char], matches: Vec<PatternMatch>) -> (f64, Vec<PatternMatch>) {
+    let n = chars.len();
+    if n == 0 {
+        return (0.0, Vec::new());
+    }
+
+    let mut by_end: Vec<Vec<PatternMatch>> = vec![Vec::new(); n + 1];
+    for mut m in matches {
+        let minimum = if m.end - m.start == 1 { MIN_GUESSES_SINGLE_CHAR } else { MIN_GUESSES_MULTI_CHAR };
+        if m.end - m.start < n {
+            m.guesses_log10 = m.guesses_log10.max(minimum.log10());
+        }
+        by_end[m.end].push(m);
+    }
+    for (end, candidates) in by_end.iter_mut().enumerate().skip(1) {
+        candidates.extend((0..end).map(|start| bruteforce(chars, start, end)));
+    }
+
+    // best[end][count]: 先頭から end までを count 個のパターンで覆うときの (推測回数の積の log10, 最後のパターン)
+    let mut best = vec![vec![(f64::INFINITY, 0usize); n + 1]; n + 1];
+    best[0][0].0 = 0.0;
+    for end in 1..=n {
+        for (index, m) in by_end[end].iter().enumerate() {
+            for count in 0..=m.start {
+                let product = best[m.start][count].0 + m.guesses_log10;
+                if product < best[end][count + 1].0 {
+                    best[end][count + 1] = (product, index);
+                }
+            }
+        }
+    }
+
+    // パターン数の階乗と、パターン数に応じた最小値を加える
+    let (count, guesses_log10) = best[n]
+        .iter()
+        .enumerate()
+        .filter(|(_, (product, _))| product.is_finite())
+        .map(|(count, (product, _))| {
+            let ordered = log10_factorial(count) + product;
+            let minimum = MIN_GUESSES_PER_PATTERN_LOG10 * (count as f64 - 1.0);
+            (count, log10_add(ordered, minimum))
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .unwrap_or((1, 0.0));
+
+    // 後ろからたどってパターンを取り出す
+    let mut sequence = Vec::with_capacity(count);
+    let (mut end, mut remaining) = (n, count);
+    while end > 0 && remaining > 0 {
+        let index = best[end][remaining].1;
+        let m = by_end[end][index].clone();
+        end = m.start;
+        remaining -= 1;
+        sequence.push(m);
+    }
+    sequence.reverse();
+    (guesses_log10, sequence)
+}
+
+// 総当たり（1文字あたり10通りとして数える）
+fn bruteforce(chars: &[char], start: usize, end: usize) -> PatternMatch {
+    let length = end - start;
+    let mut guesses_log10 = length as f64;
+    if length < chars.len() {
+        let minimum = if length == 1 { MIN_GUESSES_SINGLE_CHAR + 1.0 } else { MIN_GUESSES_MULTI_CHAR + 1.0 };
+        guesses_log10 = guesses_log10.max(minimum.log10());
+    }
+    PatternMatch { pattern: Pattern::Bruteforce, start, end, guesses_log10 }
+}
+
+fn binomial(n: usize, k: usize) -> f64 {
+    if k > n {
+        return 0.0;
+    }
+    (0..k.min(n - k)).fold(1.0, |acc, i| acc * (n - i) as f64 / (i + 1) as f64)
+}
+
+fn log10_factorial(n: usize) -> f64 {
+    (2..=n).map(|i| (i as f64).log10()).sum()
+}
+
+// log10(10^a + 10^b)
+fn log10_add(a: f64, b: f64) -> f64 {
+    let (high, low) = if a > b { (a, b) } else { (b, a) };
+    high + (1.0 + 10f64.powf(low - high)).log10()
+}
+
+fn display_time(seconds: f64) -> String {
+    const MINUTE: f64 = 60.0;
+    const HOUR: f64 = MINUTE * 60.0;
+    const DAY: f64 = HOUR * 24.0;
+    const MONTH: f64 = DAY * 31.0;
+    const YEAR: f64 = MONTH * 12.0;
+    const CENTURY: f64 = YEAR * 100.0;
+
+    let (value, unit) = match seconds {
+        s if s < 1.0 => return "less than a second".to_string(),
+        s if s < MINUTE => (s, "second"),
+        s if s < HOUR => (s / MINUTE, "minute"),
+        s if s < DAY => (s / HOUR, "hour"),
+        s if s < MONTH => (s / DAY, "day"),
+        s if s < YEAR => (s / MONTH, "month"),
+        s if s < CENTURY => (s / YEAR, "year"),
+        _ => return "centuries".to_string(),
+    };
+    let value = value.round() as u64;
+    format!("{} {}{}", value, unit, if value == 1 { "" } else { "s" })
+}
+
+// ==== フィードバック ====
+
+fn feedback(score: u8, sequence: &[PatternMatch], chars: &[char]) -> Feedback {
+    if chars.is_empty() {
+        return Feedback {
+            warning: None,
+            suggestions: vec![
+                "Use a few words, avoid common phrases".to_string(),
+                "No need for symbols, digits, or uppercase letters".to_string(),
+            ],
+        };
+    }
+    if score >= 3 {
+        return Feedback::default();
+    }
+
+    let mut feedback = Feedback {
+        warning: None,
+        suggestions: vec!["Add another word or two. Uncommon words are better.".to_string()],
+    };
+    // 最も長いパターンについて助言する
+    let Some(longest) = sequence.iter().max_by_key(|m| m.end - m.start) else { return feedback };
+    let sole = sequence.len() == 1;
+    let warning = match &longest.pattern {
+        Pattern::Dictionary { dictionary, rank, reversed, l33t } => {
+            if *reversed {
+                feedback.suggestions.push("Reversed words aren't much harder to guess".to_string());
+            }
+            if *l33t {
+                feedback.suggestions.push("Predictable substitutions like '@' instead of 'a' don't help very much".to_string());
+            }
+            match dictionary {
+                DictionaryKind::CommonPassword if sole && !l33t && !reversed && *rank <= 10 => "This is a top-10 common password",
+                DictionaryKind::CommonPassword if sole && !l33t && !reversed => "This is a very common password",
+                DictionaryKind::CommonPassword => "This is similar to a commonly used password",
+                DictionaryKind::EnglishWord if sole => "A word by itself is easy to guess",
+                DictionaryKind::EnglishWord => "Common words are easy to guess",
+                DictionaryKind::UserInput => "This contains information that is easy to associate with you",
+            }
+        },
+        Pattern::Spatial { turns, .. } => {
+            feedback.suggestions.push("Use a longer keyboard pattern with more turns".to_string());
+            if *turns == 1 { "Straight rows of keys are easy to guess" } else { "Short keyboard patterns are easy to guess" }
+        },
+        Pattern::Repeat { .. } => {
+            feedback.suggestions.push("Avoid repeated words and characters".to_string());
+            "Repeated characters or words are easy to guess"
+        },
+        Pattern::Sequence { .. } => {
+            feedback.suggestions.push("Avoid sequences".to_string());
+            "Sequences like abc or 6543 are easy to guess"
+        },
+        Pattern::Date { .. } | Pattern::Year => {
+            feedback.suggestions.push("Avoid dates and years that are associated with you".to_string());
+            if matches!(longest.pattern, Pattern::Year) { "Recent years are easy to guess" } else { "Dates are often easy to guess" }
+        },
+        Pattern::Bruteforce => return feedback,
+    };
+    feedback.warning = Some(warning.to_string());
+
+    if let Pattern::Dictionary { .. } = longest.pattern {
+        let token = &chars[longest.start..longest.end];
+        if token.iter().any(|c| c.is_lowercase()) && token.first().is_some_and(|c| c.is_uppercase()) {
+            feedback.suggestions.push("Capitalization doesn't help very much".to_string());
+        } else if token.iter().any(|c| c.is_uppercase()) && !token.iter().any(|c| c.is_lowercase()) {
+            feedback.suggestions.push("All-uppercase is almost as easy to guess as all-lowercase".to_string());
+        }
+    }
+    feedback
+}
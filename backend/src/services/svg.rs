@@ -0,0 +1,448 @@
+use anyhow::{Result, anyhow};
+use std::sync::{Arc, OnceLock};
+use image::DynamicImage;
+use resvg::{tiny_skia, usvg};
+
+use crate::services::limits::DecodeLimits;
+
+// SVGのユーザー単位の解像度
+const SVG_DPI: f32 = 96.0;
+
+const SVG_NS: &str = "http://www.w3.org/2000/svg";
+const XLINK_NS: &str = "http://www.w3.org/1999/xlink";
+const XML_NS: &str = "http://www.w3.org/XML/1998/namespace";
+
+// ラスタライズの出力サイズ指定（width が優先）
+#[derive(Debug, Clone, Default)]
+pub struct RasterizeOptions {
+    pub width: Option<u32>,
+    pub dpi: Option<f32>,
+}
+
+// 最適化の設定（script・外部参照の除去は常に行う）
+#[derive(Debug, Clone)]
+pub struct MinifyOptions {
+    // 座標を丸める小数点以下の桁数
+    pub precision: usize,
+    // metadata・desc・エディタ固有の要素と属性を削除
+    pub remove_metadata: bool,
+    // 属性のない <g> を展開し、空のグループを削除
+    pub collapse_groups: bool,
+}
+
+impl Default for MinifyOptions {
+    fn default() -> Self {
+        MinifyOptions {
+            precision: 2,
+            remove_metadata: true,
+            collapse_groups: true,
+        }
+    }
+}
+
+static FONT_DB: OnceLock<Arc<usvg::fontdb::Database>> = OnceLock::new();
+
+// システムフォントの読み込みは重いため一度だけ行う
+fn font_db() -> Arc<usvg::fontdb::Database> {
+    FONT_DB
+        .get_or_init(|| {
+            let mut db = usvg::fontdb::Database::new();
+            db.load_system_fonts();
+            tracing::debug!("システムフォント読み込み完了: {}件", db.len());
+            Arc::new(db)
+        })
+        .clone()
+}
+
+// SVGをラスタライズ
+pub fn rasterize(data: &[u8], options: &RasterizeOptions) -> Result<DynamicImage> {
+    tracing::debug!("SVGラスタライズ開始: {} バイト, {:?}", data.len(), options);
+
+    let mut usvg_options = usvg::Options {
+        fontdb: font_db(),
+        ..usvg::Options::default()
+    };
+    // ファイルパスなどの外部参照は読み込まない（data URLのみ許可）
+    usvg_options.image_href_resolver.resolve_string = Box::new(|href, _| {
+        tracing::warn!("SVGの外部参照を無視しました: {}", href);
+        None
+    });
+
+    let tree = usvg::Tree::from_data(data, &usvg_options)
+        .map_err(|e| anyhow!("Failed to parse SVG: {}", e))?;
+    let size = tree.size();
+
+    let scale = match (options.width, options.dpi) {
+        (Some(width), _) => width as f32 / size.width(),
+        (None, Some(dpi)) => dpi / SVG_DPI,
+        (None, None) => 1.0,
+    };
+    if !scale.is_finite() || scale <= 0.0 {
+        return Err(anyhow!("Invalid output size"));
+    }
+
+    let width = (size.width() * scale).ceil() as u32;
+    let height = (size.height() * scale).ceil() as u32;
+    if width == 0 || height == 0 {
+        return Err(anyhow!("Invalid output size {}x{}", width, height));
+    }
+    // キャンバスを確保する前に、他の形式のデコードと同じ上限で確認する
+    DecodeLimits::from_config().check(width, height)?;
+    tracing::debug!("ラスタライズサイズ: {}x{} (倍率: {})", width, height, scale);
+
+    let mut pixmap = tiny_skia::Pixmap::new(width, height)
+        .ok_or_else(|| anyhow!("Failed to allocate {}x{} canvas", width, height))?;
+    resvg::render(&tree, tiny_skia::Transform::from_scale(scale, scale), &mut pixmap.as_mut());
+
+    // tiny-skia は乗算済みアルファのため、PNG経由でストレートアルファに戻す
+    let png = pixmap.encode_png().map_err(|e| anyhow!("Failed to encode PNG: {}", e))?;
+    image::load_from_memory_with_format(&png, image::ImageFormat::Png)
+        .map_err(|e| anyhow!("Failed to open rasterized image: {}", e))
+}
+
+// SVGを最適化（minify）し、script と外部参照を除去する
+pub fn minify(data: &[u8], options: &MinifyOptions) -> Result<String> {
+    tracing::debug!("SVG最適化開始: {} バイト, {:?}", data.len(), options);
+
+    // svgz（gzip圧縮）にも対応
+    let decompressed;
+    let data = if data.starts_with(&[0x1f, 0x8b]) {
+        decompressed = usvg::decompress_svgz(data).map_err(|e| anyhow!("Failed to decompress SVGZ: {}", e))?;
+        &decompressed[..]
+    } else {
+        data
+    };
+    let text = std::str::from_utf8(data).map_err(|_| anyhow!("SVG must be UTF-8 encoded"))?;
+
+    // DTDは許可しない（エンティティ展開による攻撃を防ぐ）
+    let document = roxmltree::Document::parse(text).map_err(|e| anyhow!("Failed to parse SVG: {}", e))?;
+    let root = document.root_element();
+    if root.tag_name().name() != "svg" || root.tag_name().namespace() != Some(SVG_NS) {
+        return Err(anyhow!("Root element is not <svg>"));
+    }
+
+    let mut writer = SvgWriter { options, output: String::with_capacity(text.len()), uses_xlink: false };
+    writer.write_element(root, true);
+
+    // xlink属性を使っている場合のみ名前空間宣言を追加
+    let mut output = writer.output;
+    if writer.uses_xlink {
+        output = output.replacen("<svg", &format!("<svg xmlns:xlink=\"{}\"", XLINK_NS), 1);
+    }
+    tracing::debug!("SVG最適化完了: {} → {} バイト", data.len(), output.len());
+    Ok(output)
+}
+
+struct SvgWriter<'a> {
+    options: &'a MinifyOptions,
+    output: String,
+    uses_xlink: bool,
+}
+
+impl SvgWriter<'_> {
+    fn write_element(&mut self, node: roxmltree::Node, is_root: bool) {
+        let name = node.tag_name().name();
+
+        // SVG以外の名前空間の要素（エディタのメタデータなど）は出力しない
+        if node.tag_name().namespace() != Some(SVG_NS) {
+            return;
+        }
+        // script・foreignObject は常に削除
+        if matches!(name, "script" | "foreignObject") {
+            tracing::debug!("SVG要素を削除: <{}>", name);
+            return;
+        }
+        if self.options.remove_metadata && matches!(name, "metadata" | "desc") {
+            return;
+        }
+        // アニメーションで href やイベントハンドラを書き換える要素は、静的な属性の検査をすり抜けるため削除
+        if is_unsafe_animation(node) {
+            tracing::debug!("SVG要素を削除: <{}> (attributeName={:?})", name, node.attribute("attributeName"));
+            return;
+        }
+
+        let attributes = self.attributes(node);
+
+        // 属性のない <g> は子要素に展開
+        if self.options.collapse_groups && name == "g" && attributes.is_empty() {
+            for child in node.children() {
+                self.write_child(child, name);
+            }
+            return;
+        }
+
+        let mut children = String::new();
+        std::mem::swap(&mut self.output, &mut children);
+        for child in node.children() {
+            self.write_child(child, name);
+        }
+        std::mem::swap(&mut self.output, &mut children);
+
+        // 空のグループ・defs は削除
+        if self.options.collapse_groups && matches!(name, "g" | "defs") && children.is_empty() && !is_root {
+            return;
+        }
+
+        self.output.push('<');
+        self.output.push_str(name);
+        if is_root {
+            self.output.push_str(&format!(" xmlns=\"{}\"", SVG_NS));
+        }
+        for (attr_name, value) in attributes {
+            self.output.push_str(&format!(" {}=\"{}\"", attr_name, escape_attribute(&value)));
+        }
+        if children.is_empty() {
+            self.output.push_str("/>");
+        } else {
+            self.output.push('>');
+            self.output.push_str(&children);
+            self.output.push_str(&format!("</{}>", name));
+        }
+    }
+
+    fn write_child(&mut self, child: roxmltree::Node, parent_name: &str) {
+        if child.is_element() {
+            self.write_element(child, false);
+        } else if child.is_text() {
+            let text = child.text().unwrap_or("");
+            if parent_name == "style" {
+                self.output.push_str(&escape_text(&sanitize_css(text)));
+            } else if matches!(parent_name, "text" | "tspan" | "textPath" | "title" | "desc") {
+                // テキスト要素内の空白は表示に影響するためそのまま残す
+                self.output.push_str(&escape_text(text));
+            } else if !text.trim().is_empty() {
+                self.output.push_str(&escape_text(text.trim()));
+            }
+        }
+        // コメント・処理命令は出力しない
+    }
+
+    // 出力する属性の一覧（安全でない属性を除去し、数値を丸める）
+    fn attributes(&mut self, node: roxmltree::Node) -> Vec<(String, String)> {
+        let mut attributes = Vec::new();
+        for attr in node.attributes() {
+            let local = attr.name();
+            let name = match attr.namespace() {
+                None => local.to_string(),
+                Some(XLINK_NS) => format!("xlink:{}", local),
+                Some(XML_NS) => format!("xml:{}", local),
+                // エディタ固有の名前空間（inkscape:・sodipodi: など）
+                Some(_) => continue,
+            };
+            let value = attr.value();
+
+            // イベントハンドラ属性は常に削除
+            if local.to_lowercase().starts_with("on") {
+                tracing::debug!("SVG属性を削除: {}", name);
+                continue;
+            }
+            // 外部への参照（同一文書内の #id と画像の data URL 以外）は削除
+            if local == "href" && !is_local_reference(value) {
+                tracing::debug!("SVGの外部参照を削除: {}", value);
+                continue;
+            }
+            if self.options.remove_metadata && local.starts_with("data-") {
+                continue;
+            }
+
+            let value = match local {
+                "style" => sanitize_css(value),
+                "d" => round_path_data(value, self.options.precision),
+                "x" | "y" | "x1" | "y1" | "x2" | "y2" | "cx" | "cy" | "r" | "rx" | "ry" | "fx" | "fy"
+                | "width" | "height" | "points" | "viewBox" | "transform" | "stroke-width"
+                | "dx" | "dy" | "offset" | "font-size" | "gradientTransform" | "patternTransform" => {
+                    round_numbers(value, self.options.precision)
+                },
+                _ => value.to_string(),
+            };
+            if name.starts_with("xlink:") {
+                self.uses_xlink = true;
+            }
+            attributes.push((name, value));
+        }
+        attributes
+    }
+}
+
+// set・animate が href を外部参照に、またはイベントハンドラ属性を書き換えるか
+fn is_unsafe_animation(node: roxmltree::Node) -> bool {
+    if !matches!(node.tag_name().name(), "set" | "animate" | "animateColor" | "animateMotion" | "animateTransform") {
+        return false;
+    }
+    let Some(target) = node.attribute("attributeName") else {
+        return false;
+    };
+    let target = target.trim().to_lowercase();
+    let target = target.strip_prefix("xlink:").unwrap_or(&target);
+    if target.starts_with("on") {
+        return true;
+    }
+    if target != "href" {
+        return false;
+    }
+    ["to", "from", "by", "values"]
+        .iter()
+        .filter_map(|attr| node.attribute(*attr))
+        .flat_map(|value| value.split(';'))
+        .any(|value| !is_local_reference(value))
+}
+
+// 同一文書内の参照（#id）または画像の data URL か
+fn is_local_reference(value: &str) -> bool {
+    let value = value.trim();
+    if value.starts_with('#') {
+        return true;
+    }
+    let lower = value.to_lowercase();
+    lower.starts_with("data:image/") && !lower.starts_with("data:image/svg")
+}
+
+// CSSから @import と外部の url() 参照を除去
+fn sanitize_css(css: &str) -> String {
+    let mut output = String::with_capacity(css.len());
+    let mut rest = css;
+    loop {
+        let lower = rest.to_ascii_lowercase();
+        let Some(pos) = [lower.find("url("), lower.find("@import")].into_iter().flatten().min() else {
+            break;
+        };
+        let (before, after) = rest.split_at(pos);
+        output.push_str(before);
+        if after.to_ascii_lowercase().starts_with("@import") {
+            // 次のセミコロンまでを削除
+            let end = after.find(';').map(|i| i + 1).unwrap_or(after.len());
+            rest = &after[end..];
+            continue;
+        }
+        let end = after.find(')').map(|i| i + 1).unwrap_or(after.len());
+        let reference = after[4..end.saturating_sub(1).max(4)].trim().trim_matches(|c| c == '"' || c == '\'');
+        if is_local_reference(reference) {
+            output.push_str(&after[..end]);
+        } else {
+            output.push_str("none");
+        }
+        rest = &after[end..];
+    }
+    output.push_str(rest);
+    output
+}
+
+// 文字列中の数値を指定桁数に丸める
+fn round_numbers(value: &str, precision: usize) -> String {
+    let mut output = String::with_capacity(value.len());
+    let mut chars = value.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_ascii_digit() || c == '.' || ((c == '-' || c == '+') && next_starts_number(value, start)) {
+            let end = number_end(value, start);
+            push_number(&mut output, &value[start..end], precision);
+            while chars.peek().is_some_and(|&(i, _)| i < end) {
+                chars.next();
+            }
+        } else {
+            output.push(c);
+            chars.next();
+        }
+    }
+    output
+}
+
+// パスデータの数値を丸める（円弧コマンドのフラグは1文字として扱う）
+fn round_path_data(value: &str, precision: usize) -> String {
+    let mut output = String::with_capacity(value.len());
+    let bytes = value.as_bytes();
+    let mut i = 0;
+    let mut command = b'M';
+    let mut arg_index = 0usize;
+
+    while i < bytes.len() {
+        let c = bytes[i];
+        if c.is_ascii_alphabetic() && c != b'e' && c != b'E' {
+            command = c;
+            arg_index = 0;
+            output.push(c as char);
+            i += 1;
+        } else if matches!(command, b'A' | b'a') && matches!(arg_index % 7, 3 | 4) && (c == b'0' || c == b'1') {
+            // 円弧のフラグ（0 または 1）
+            if output.ends_with(|ch: char| ch.is_ascii_digit() || ch == '.') {
+                output.push(' ');
+            }
+            output.push(c as char);
+            arg_index += 1;
+            i += 1;
+        } else if c.is_ascii_digit() || c == b'.' || ((c == b'-' || c == b'+') && next_starts_number(value, i)) {
+            let end = number_end(value, i);
+            push_number(&mut output, &value[i..end], precision);
+            arg_index += 1;
+            i = end;
+        } else {
+            output.push(c as char);
+            i += 1;
+        }
+    }
+    output
+}
+
+fn next_starts_number(value: &str, sign_pos: usize) -> bool {
+    value[sign_pos + 1..].starts_with(|c: char| c.is_ascii_digit() || c == '.')
+}
+
+// 数値リテラルの終端位置（符号・小数点・指数を含む）
+fn number_end(value: &str, start: usize) -> usize {
+    let bytes = value.as_bytes();
+    let mut i = start;
+    if i < bytes.len() && (bytes[i] == b'-' || bytes[i] == b'+') {
+        i += 1;
+    }
+    while i < bytes.len() && bytes[i].is_ascii_digit() {
+        i += 1;
+    }
+    if i < bytes.len() && bytes[i] == b'.' {
+        i += 1;
+        while i < bytes.len() && bytes[i].is_ascii_digit() {
+            i += 1;
+        }
+    }
+    if i < bytes.len() && (bytes[i] == b'e' || bytes[i] == b'E') {
+        let mut j = i + 1;
+        if j < bytes.len() && (bytes[j] == b'-' || bytes[j] == b'+') {
+            j += 1;
+        }
+        if j < bytes.len() && bytes[j].is_ascii_digit() {
+            while j < bytes.len() && bytes[j].is_ascii_digit() {
+                j += 1;
+            }
+            i = j;
+        }
+    }
+    i
+}
+
+// 丸めた数値を出力（直前の数値と連結しないよう必要に応じて空白を入れる）
+fn push_number(output: &mut String, literal: &str, precision: usize) {
+    let formatted = match literal.parse::<f64>() {
+        Ok(number) => format_number(number, precision),
+        Err(_) => literal.to_string(),
+    };
+    if !formatted.starts_with('-') && output.ends_with(|c: char| c.is_ascii_digit() || c == '.') {
+        output.push(' ');
+    }
+    output.push_str(&formatted);
+}
+
+fn format_number(number: f64, precision: usize) -> String {
+    let formatted = format!("{:.*}", precision, number);
+    let formatted = if formatted.contains('.') {
+        formatted.trim_end_matches('0').trim_end_matches('.').to_string()
+    } else {
+        formatted
+    };
+    if formatted == "-0" { "0".to_string() } else { formatted }
+}
+
+fn escape_text(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+fn escape_attribute(s: &str) -> String {
+    escape_text(s).replace('"', "&quot;")
+}
@@ -0,0 +1,237 @@
+use anyhow::{Result, anyhow};
+use std::str::FromStr;
+use image::{DynamicImage, GenericImageView, GrayImage};
+use image::imageops::FilterType;
+
+// サムネイルの最大辺と一度に指定できるサイズの数
+const MAX_DIMENSION: u32 = 4096;
+const MAX_SIZES: usize = 10;
+
+// 顕著性マップを計算する作業用画像の長辺
+const ANALYSIS_SIZE: u32 = 128;
+// エントロピーを計算するブロックサイズと輝度ヒストグラムのビン数
+const ENTROPY_BLOCK: u32 = 8;
+const ENTROPY_BINS: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ThumbnailSize {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl FromStr for ThumbnailSize {
+    type Err = anyhow::Error;
+
+    // "幅x高さ" 形式を解析（"150" のような単一値は正方形）
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim().to_lowercase();
+        let (w, h) = s.split_once('x').unwrap_or((&s, &s));
+        let width = w.trim().parse::<u32>().map_err(|_| anyhow!("Invalid thumbnail size: {}", s))?;
+        let height = h.trim().parse::<u32>().map_err(|_| anyhow!("Invalid thumbnail size: {}", s))?;
+        if width == 0 || height == 0 || width > MAX_DIMENSION || height > MAX_DIMENSION {
+            return Err(anyhow!("Thumbnail size must be between 1 and {}: {}", MAX_DIMENSION, s));
+        }
+        Ok(ThumbnailSize { width, height })
+    }
+}
+
+// カンマ区切りのサイズ一覧を解析（重複は除外）
+pub fn parse_sizes(s: &str) -> Result<Vec<ThumbnailSize>> {
+    let mut sizes = Vec::new();
+    for part in s.split(',').filter(|p| !p.trim().is_empty()) {
+        let size = part.parse::<ThumbnailSize>()?;
+        if !sizes.contains(&size) {
+            sizes.push(size);
+        }
+    }
+    if sizes.is_empty() {
+        return Err(anyhow!("No thumbnail sizes specified"));
+    }
+    if sizes.len() > MAX_SIZES {
+        return Err(anyhow!("Too many thumbnail sizes (max {})", MAX_SIZES));
+    }
+    Ok(sizes)
+}
+
+// 切り抜き位置の決め方
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum CropMode {
+    // 顕著性（エッジ量と局所エントロピー）が最大となる位置
+    #[default]
+    Smart,
+    // 中央
+    Center,
+}
+
+impl FromStr for CropMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "smart" => Ok(CropMode::Smart),
+            "center" => Ok(CropMode::Center),
+            _ => Err(anyhow!("Invalid crop mode: {}", s)),
+        }
+    }
+}
+
+// 指定サイズのサムネイルを生成
+pub fn generate_thumbnail(img: &DynamicImage, size: ThumbnailSize, mode: CropMode) -> DynamicImage {
+    let (x, y, width, height) = match mode {
+        CropMode::Smart => smart_crop_window(img, size.width, size.height),
+        CropMode::Center => center_crop_window(img.width(), img.height(), size.width, size.height),
+    };
+    tracing::debug!(
+        "サムネイル切り抜き: {}x{}+{}+{} → {}x{}",
+        width, height, x, y, size.width, size.height
+    );
+    img.crop_imm(x, y, width, height)
+        .resize_exact(size.width, size.height, FilterType::Lanczos3)
+}
+
+// 画像内に収まる最大の目標アスペクト比の領域サイズ
+fn crop_dimensions(width: u32, height: u32, target_width: u32, target_height: u32) -> (u32, u32) {
+    let target = target_width as f64 / target_height as f64;
+    if (width as f64 / height as f64) > target {
+        (((height as f64 * target).round() as u32).clamp(1, width), height)
+    } else {
+        (width, ((width as f64 / target).round() as u32).clamp(1, height))
+    }
+}
+
+fn center_crop_window(width: u32, height: u32, target_width: u32, target_height: u32) -> (u32, u32, u32, u32) {
+    let (crop_width, crop_height) = crop_dimensions(width, height, target_width, target_height);
+    ((width - crop_width) / 2, (height - crop_height) / 2, crop_width, crop_height)
+}
+
+// 顕著性マップの合計が最大となる切り抜き領域を探す
+fn smart_crop_window(img: &DynamicImage, target_width: u32, target_height: u32) -> (u32, u32, u32, u32) {
+    let (width, height) = img.dimensions();
+    let (crop_width, crop_height) = crop_dimensions(width, height, target_width, target_height);
+    if crop_width == width && crop_height == height {
+        return (0, 0, width, height);
+    }
+
+    // 縮小した作業用画像で顕著性を計算
+    let scale = (ANALYSIS_SIZE as f64 / width.max(height) as f64).min(1.0);
+    let work_width = ((width as f64 * scale).round() as u32).max(1);
+    let work_height = ((height as f64 * scale).round() as u32).max(1);
+    let gray = img.resize_exact(work_width, work_height, FilterType::Triangle).to_luma8();
+    let saliency = saliency_map(&gray);
+    let integral = integral_image(&saliency, work_width, work_height);
+
+    let window_width = ((crop_width as f64 * scale).round() as u32).clamp(1, work_width);
+    let window_height = ((crop_height as f64 * scale).round() as u32).clamp(1, work_height);
+
+    // 切り抜き領域は一方向にしか動かないため、その軸に沿って全位置を評価
+    let mut best = (0u32, 0u32);
+    let mut best_score = f64::MIN;
+    for y in 0..=(work_height - window_height) {
+        for x in 0..=(work_width - window_width) {
+            let score = window_sum(&integral, work_width, x, y, window_width, window_height);
+            // 同点の場合は中央に近い位置を優先
+            let center_bias = center_distance(x, y, window_width, window_height, work_width, work_height);
+            let score = score - center_bias * 1e-6;
+            if score > best_score {
+                best_score = score;
+                best = (x, y);
+            }
+        }
+    }
+
+    // 元画像の座標に戻す
+    let x = ((best.0 as f64 / scale).round() as u32).min(width - crop_width);
+    let y = ((best.1 as f64 / scale).round() as u32).min(height - crop_height);
+    (x, y, crop_width, crop_height)
+}
+
+// Sobelフィルタのエッジ強度に、ブロック単位の輝度エントロピーで重み付けした顕著性マップ
+fn saliency_map(gray: &GrayImage) -> Vec<f64> {
+    let (width, height) = gray.dimensions();
+    let luma = |x: i64, y: i64| -> f64 {
+        let x = x.clamp(0, width as i64 - 1) as u32;
+        let y = y.clamp(0, height as i64 - 1) as u32;
+        gray.get_pixel(x, y)[0] as f64
+    };
+
+    let entropy = block_entropy(gray);
+    let blocks_per_row = width.div_ceil(ENTROPY_BLOCK);
+
+    let mut map = Vec::with_capacity((width * height) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let (xi, yi) = (x as i64, y as i64);
+            let gx = luma(xi + 1, yi - 1) + 2.0 * luma(xi + 1, yi) + luma(xi + 1, yi + 1)
+                - luma(xi - 1, yi - 1) - 2.0 * luma(xi - 1, yi) - luma(xi - 1, yi + 1);
+            let gy = luma(xi - 1, yi + 1) + 2.0 * luma(xi, yi + 1) + luma(xi + 1, yi + 1)
+                - luma(xi - 1, yi - 1) - 2.0 * luma(xi, yi - 1) - luma(xi + 1, yi - 1);
+            let edge = (gx * gx + gy * gy).sqrt();
+
+            let block = (y / ENTROPY_BLOCK) * blocks_per_row + x / ENTROPY_BLOCK;
+            // エントロピーは最大4ビット（16ビン）なので 0.5〜1.5 倍の重みに正規化
+            let weight = 0.5 + entropy[block as usize] / (ENTROPY_BINS as f64).log2();
+            map.push(edge * weight);
+        }
+    }
+    map
+}
+
+// ブロックごとの輝度ヒストグラムのエントロピー（ビット）
+fn block_entropy(gray: &GrayImage) -> Vec<f64> {
+    let (width, height) = gray.dimensions();
+    let blocks_x = width.div_ceil(ENTROPY_BLOCK);
+    let blocks_y = height.div_ceil(ENTROPY_BLOCK);
+
+    let mut entropies = Vec::with_capacity((blocks_x * blocks_y) as usize);
+    for by in 0..blocks_y {
+        for bx in 0..blocks_x {
+            let mut histogram = [0u32; ENTROPY_BINS];
+            let mut count = 0u32;
+            for y in (by * ENTROPY_BLOCK)..((by + 1) * ENTROPY_BLOCK).min(height) {
+                for x in (bx * ENTROPY_BLOCK)..((bx + 1) * ENTROPY_BLOCK).min(width) {
+                    let bin = gray.get_pixel(x, y)[0] as usize * ENTROPY_BINS / 256;
+                    histogram[bin] += 1;
+                    count += 1;
+                }
+            }
+            let entropy = histogram
+                .iter()
+                .filter(|&&n| n > 0)
+                .map(|&n| {
+                    let p = n as f64 / count as f64;
+                    -p * p.log2()
+                })
+                .sum::<f64>();
+            entropies.push(entropy);
+        }
+    }
+    entropies
+}
+
+// 積分画像（(width + 1) x (height + 1)）
+fn integral_image(values: &[f64], width: u32, height: u32) -> Vec<f64> {
+    let stride = width as usize + 1;
+    let mut integral = vec![0.0; stride * (height as usize + 1)];
+    for y in 0..height as usize {
+        let mut row_sum = 0.0;
+        for x in 0..width as usize {
+            row_sum += values[y * width as usize + x];
+            integral[(y + 1) * stride + x + 1] = integral[y * stride + x + 1] + row_sum;
+        }
+    }
+    integral
+}
+
+fn window_sum(integral: &[f64], width: u32, x: u32, y: u32, window_width: u32, window_height: u32) -> f64 {
+    let stride = width as usize + 1;
+    let (x0, y0) = (x as usize, y as usize);
+    let (x1, y1) = (x0 + window_width as usize, y0 + window_height as usize);
+    integral[y1 * stride + x1] - integral[y0 * stride + x1] - integral[y1 * stride + x0] + integral[y0 * stride + x0]
+}
+
+// 切り抜き領域の中心と画像中心との距離
+fn center_distance(x: u32, y: u32, window_width: u32, window_height: u32, width: u32, height: u32) -> f64 {
+    let dx = (x as f64 + window_width as f64 / 2.0) - width as f64 / 2.0;
+    let dy = (y as f64 + window_height as f64 / 2.0) - height as f64 / 2.0;
+    (dx * dx + dy * dy).sqrt()
+}